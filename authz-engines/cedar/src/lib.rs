@@ -0,0 +1,421 @@
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+
+#[macro_use]
+extern crate async_trait;
+#[macro_use]
+extern crate derivative;
+
+pub use authzen_event::EventKey;
+pub use cedar_policy;
+
+use authzen_event::{SerializationError, SerializedEvent};
+use cedar_policy::{
+    Authorizer, Context, Decision, Entities, EntityId, EntityTypeName, EntityUid, PolicySet, Request, Schema,
+    ValidationMode, Validator,
+};
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Error returned from an [`EntityLoader`] which was unable to load entities.
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum EntityLoaderError {
+    /// The entities could not be fetched, e.g. the service storing them could not be reached.
+    #[error("unable to load entities: {0}")]
+    Unavailable(Arc<dyn std::error::Error + Send + Sync>),
+    /// The entities could not be requested or their representation could not be parsed, e.g. the
+    /// loader's query could not be deserialized from the event's ids.
+    #[error("invalid entities query: {0}")]
+    Invalid(Arc<dyn std::error::Error + Send + Sync>),
+}
+
+impl EntityLoaderError {
+    pub fn unavailable(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Unavailable(Arc::from(err.into()))
+    }
+
+    pub fn invalid(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::Invalid(Arc::from(err.into()))
+    }
+}
+
+/// Loads the attributes of the objects referred to by id in an event's input, so that policies
+/// can refer to the attributes of resources which are not provided in full by the input.
+#[async_trait]
+pub trait EntityLoader<TransactionId>: Send + Sync {
+    /// Returns the json representation of each object with the provided ids which could be found, keyed by id.
+    /// Objects which are not returned are passed to Cedar without attributes.
+    async fn load(
+        &self,
+        service: &'static str,
+        ty: &'static str,
+        ids: &[Value],
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<HashMap<String, Value>, EntityLoaderError>;
+}
+
+/// Loads no entities.
+#[async_trait]
+impl<TransactionId: Sync> EntityLoader<TransactionId> for () {
+    async fn load(
+        &self,
+        _: &'static str,
+        _: &'static str,
+        _: &[Value],
+        _: Option<&TransactionId>,
+    ) -> Result<HashMap<String, Value>, EntityLoaderError> {
+        Ok(Default::default())
+    }
+}
+
+/// An in-process authorization engine which evaluates [Cedar](https://www.cedarpolicy.com) policies.
+///
+/// Events are mapped to Cedar requests as follows
+/// - principal: `{principal_type}::"{id}"`, where the id is the event's subject if it serializes to a
+///   primitive, or the subject's `id` field if it serializes to an object (whose other fields become
+///   the principal's attributes)
+/// - action: `{service}::Action::"{action}"`
+/// - resource: `{service}::{type}::"{id}"` for each item of the event's input; items which serialize to
+///   objects provide their own `id` and attributes (e.g. objects being created), while the attributes of
+///   items which are ids are loaded with the engine's [`EntityLoader`]
+/// - context: the event's context, which must serialize to an object or `null`, along with the event's tenant
+///   as `context.tenant` if it is scoped to one (policies should check `context has tenant` first)
+///
+/// An event is allowed if Cedar allows the request for *every* resource (see [`SerializedEvent`]).
+/// Json `null`s are omitted from attributes and non-integer numbers are passed as strings.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct CedarEngine<L = ()> {
+    #[derivative(Debug = "ignore")]
+    authorizer: Arc<Authorizer>,
+    policies: Arc<PolicySet>,
+    schema: Option<Arc<Schema>>,
+    principal_type: EntityTypeName,
+    #[derivative(Debug = "ignore")]
+    loader: L,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum CedarError {
+    #[error("event denied by cedar policies for `{0}`")]
+    Denied(EventKey),
+    #[error("invalid cedar entity uid `{0}`")]
+    Uid(String),
+    #[error("unable to determine the id of an entity from `{0}`")]
+    MissingId(Value),
+    #[error("invalid cedar entities: {0}")]
+    Entities(Arc<cedar_policy::EntitiesError>),
+    #[error("invalid cedar context: {0}")]
+    Context(Arc<cedar_policy::ContextJsonError>),
+    #[error("cedar policies failed validation: {}", .0.join("; "))]
+    Validation(Vec<String>),
+    #[error(transparent)]
+    EntityLoader(EntityLoaderError),
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+}
+
+impl CedarEngine {
+    /// Constructs an engine which evaluates `policies`, identifying subjects as entities of type `principal_type`.
+    pub fn new(policies: PolicySet, principal_type: &str) -> Result<Self, CedarError> {
+        Ok(Self {
+            authorizer: Arc::new(Authorizer::new()),
+            policies: Arc::new(policies),
+            schema: None,
+            principal_type: EntityTypeName::from_str(principal_type)
+                .map_err(|_| CedarError::Uid(principal_type.to_string()))?,
+            loader: (),
+        })
+    }
+}
+
+impl<L> CedarEngine<L> {
+    /// Statically validates the engine's policies against `schema`, which is also used to parse
+    /// the entities and context of each request.
+    pub fn schema(mut self, schema: Schema) -> Result<Self, CedarError> {
+        let validator = Validator::new(schema.clone());
+        let result = validator.validate(&self.policies, ValidationMode::default());
+        if !result.validation_passed() {
+            return Err(CedarError::Validation(
+                result
+                    .validation_errors()
+                    .map(|err| format!("{}: {err}", err.location().policy_id()))
+                    .collect(),
+            ));
+        }
+        self.schema = Some(Arc::new(schema));
+        Ok(self)
+    }
+
+    /// Loads the attributes of resources referred to by id with `loader`.
+    pub fn loader<L2>(self, loader: L2) -> CedarEngine<L2> {
+        CedarEngine {
+            authorizer: self.authorizer,
+            policies: self.policies,
+            schema: self.schema,
+            principal_type: self.principal_type,
+            loader,
+        }
+    }
+
+    pub fn policies(&self) -> &PolicySet {
+        &self.policies
+    }
+
+    /// Evaluates an already serialized event, i.e.
+    /// ```json
+    /// {
+    ///   "subject": ...,
+    ///   "input": ...,
+    ///   "context": ...,
+    ///   "tenant": ...
+    /// }
+    /// ```
+    pub async fn evaluate<TransactionId>(
+        &self,
+        key: EventKey,
+        event: Value,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<(), CedarError>
+    where
+        L: EntityLoader<TransactionId>,
+    {
+        let SerializedEvent {
+            subject,
+            items,
+            context,
+            tenant,
+            ..
+        } = event.into();
+        let mut context = match context {
+            Value::Null => json!({}),
+            context => context,
+        };
+        if let (Value::Object(context), tenant @ Value::String(_)) = (&mut context, tenant) {
+            context.insert("tenant".into(), tenant);
+        }
+
+        let (principal_id, principal_attrs) = split_id(subject)?;
+        let principal = entity_uid(&self.principal_type.to_string(), &principal_id)?;
+        let action = action_uid(&key)?;
+
+        let mut resource_ids = Vec::<String>::with_capacity(items.len());
+        let mut resources = HashMap::<String, Map<String, Value>>::new();
+        let mut unloaded_ids = Vec::<Value>::new();
+        for item in items {
+            if !item.is_object() {
+                unloaded_ids.push(item.clone());
+            }
+            let (id, attrs) = split_id(item)?;
+            resource_ids.push(id.clone());
+            resources.entry(id).or_default().extend(attrs);
+        }
+
+        if !unloaded_ids.is_empty() {
+            let loaded = self
+                .loader
+                .load(key.service, key.ty, &unloaded_ids, transaction_id)
+                .await
+                .map_err(CedarError::EntityLoader)?;
+            for (id, attrs) in loaded {
+                if let (Some(resource), Value::Object(attrs)) = (resources.get_mut(&id), attrs) {
+                    if resource.is_empty() {
+                        *resource = attrs;
+                    }
+                }
+            }
+        }
+
+        let resource_type = format!("{}::{}", key.service, key.ty);
+        let mut entities = vec![entity_json(
+            &self.principal_type.to_string(),
+            &principal_id,
+            principal_attrs,
+        )];
+        entities.extend(
+            resources
+                .into_iter()
+                .map(|(id, attrs)| entity_json(&resource_type, &id, attrs)),
+        );
+        let entities = Entities::from_json_value(Value::Array(entities), self.schema.as_deref())
+            .map_err(|err| CedarError::Entities(Arc::new(err)))?;
+        let context = Context::from_json_value(context, self.schema.as_deref().map(|schema| (schema, &action)))
+            .map_err(|err| CedarError::Context(Arc::new(err)))?;
+
+        for id in &resource_ids {
+            let request = Request::new(
+                Some(principal.clone()),
+                Some(action.clone()),
+                Some(resource_uid(&key, id)?),
+                context.clone(),
+            );
+            let response = self.authorizer.is_authorized(&request, &self.policies, &entities);
+            if response.decision() != Decision::Allow {
+                return Err(CedarError::Denied(key));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes `event` and then evaluates it (see [`CedarEngine::evaluate`]).
+    pub async fn evaluate_serialize<TransactionId>(
+        &self,
+        key: EventKey,
+        event: &impl Serialize,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<(), CedarError>
+    where
+        L: EntityLoader<TransactionId>,
+    {
+        self.evaluate(key, authzen_event::serialize(event)?, transaction_id)
+            .await
+    }
+}
+
+/// Renders an entity id, using strings as is and the json representation of any other value.
+pub fn entity_id(id: &Value) -> String {
+    match id {
+        Value::String(id) => id.clone(),
+        id => id.to_string(),
+    }
+}
+
+/// Uid of an event's action, i.e. `{service}::Action::"{action}"`.
+fn action_uid(key: &EventKey) -> Result<EntityUid, CedarError> {
+    entity_uid(&format!("{}::Action", key.service), key.action)
+}
+
+/// Uid of the object with the provided id, i.e. `{service}::{type}::"{id}"`.
+fn resource_uid(key: &EventKey, id: &str) -> Result<EntityUid, CedarError> {
+    entity_uid(&format!("{}::{}", key.service, key.ty), id)
+}
+
+fn entity_uid(ty: &str, id: &str) -> Result<EntityUid, CedarError> {
+    let entity_type = EntityTypeName::from_str(ty).map_err(|_| CedarError::Uid(format!("{ty}::{id:?}")))?;
+    let entity_id = EntityId::from_str(id).map_err(|_| CedarError::Uid(format!("{ty}::{id:?}")))?;
+    Ok(EntityUid::from_type_name_and_id(entity_type, entity_id))
+}
+
+fn entity_json(ty: &str, id: &str, attrs: Map<String, Value>) -> Value {
+    json!({
+        "uid": { "type": ty, "id": id },
+        "attrs": Value::Object(attrs.into_iter().filter_map(|(key, value)| Some((key, to_attr(value)?))).collect()),
+        "parents": [],
+    })
+}
+
+/// Splits a value into an entity id and its attributes.
+fn split_id(value: Value) -> Result<(String, Map<String, Value>), CedarError> {
+    match value {
+        Value::Object(mut attrs) => match attrs.get("id").filter(|id| !id.is_null() && !id.is_object()) {
+            Some(id) => Ok((entity_id(id), std::mem::take(&mut attrs))),
+            None => Err(CedarError::MissingId(Value::Object(attrs))),
+        },
+        Value::Null | Value::Array(_) => Err(CedarError::MissingId(value)),
+        id => Ok((entity_id(&id), Map::default())),
+    }
+}
+
+/// Converts a json value into a Cedar attribute value, omitting `null`s.
+fn to_attr(value: Value) -> Option<Value> {
+    match value {
+        Value::Null => None,
+        Value::Number(number) if number.as_i64().is_none() => Some(Value::String(number.to_string())),
+        Value::Array(values) => Some(Value::Array(values.into_iter().filter_map(to_attr).collect())),
+        Value::Object(values) => Some(Value::Object(
+            values
+                .into_iter()
+                .filter_map(|(key, value)| Some((key, to_attr(value)?)))
+                .collect(),
+        )),
+        value => Some(value),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Items;
+
+    #[async_trait]
+    impl EntityLoader<()> for Items {
+        async fn load(
+            &self,
+            _: &'static str,
+            _: &'static str,
+            ids: &[Value],
+            _: Option<&()>,
+        ) -> Result<HashMap<String, Value>, EntityLoaderError> {
+            Ok(ids
+                .iter()
+                .map(|id| {
+                    (
+                        entity_id(id),
+                        json!({ "id": id, "owner": if *id == 1 { "alice" } else { "bob" } }),
+                    )
+                })
+                .collect())
+        }
+    }
+
+    fn engine() -> CedarEngine<Items> {
+        let policies = PolicySet::from_str(
+            r#"
+            permit(principal, action == cart::Action::"read", resource)
+            when { resource.owner == principal.id };
+            permit(principal, action == cart::Action::"create", resource)
+            when { principal.role == "admin" && resource.price == "1.5" };
+            permit(principal, action == cart::Action::"delete", resource)
+            when { context has tenant && context.tenant == principal.tenant };
+            "#,
+        );
+        CedarEngine::new(policies.unwrap(), "accounts::account")
+            .unwrap()
+            .loader(Items)
+    }
+
+    fn evaluate(action: &'static str, event: Value) -> Result<(), CedarError> {
+        futures::executor::block_on(engine().evaluate::<()>(EventKey::new("cart", "item", action), event, None))
+    }
+
+    #[test]
+    fn loads_resources_by_id() {
+        assert!(evaluate("read", json!({ "subject": { "id": "alice" }, "input": [1] })).is_ok());
+        assert!(matches!(
+            evaluate("read", json!({ "subject": { "id": "alice" }, "input": [1, 2] })),
+            Err(CedarError::Denied(_)),
+        ));
+    }
+
+    #[test]
+    fn uses_provided_resources() {
+        let input = json!([{ "id": 3, "price": 1.5, "discount": null }]);
+        assert!(evaluate(
+            "create",
+            json!({ "subject": { "id": "alice", "role": "admin" }, "input": input })
+        )
+        .is_ok());
+        assert!(matches!(
+            evaluate("create", json!({ "subject": { "id": "bob" }, "input": input })),
+            Err(CedarError::Denied(_)),
+        ));
+    }
+
+    #[test]
+    fn exposes_the_tenant_in_the_context() {
+        let event =
+            |tenant: Value| json!({ "subject": { "id": "alice", "tenant": "a" }, "input": [1], "tenant": tenant });
+        assert!(evaluate("delete", event(json!("a"))).is_ok());
+        assert!(matches!(
+            evaluate("delete", event(json!("b"))),
+            Err(CedarError::Denied(_))
+        ));
+        assert!(matches!(
+            evaluate("delete", event(Value::Null)),
+            Err(CedarError::Denied(_))
+        ));
+    }
+}
@@ -0,0 +1,96 @@
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+
+#[macro_use]
+extern crate derive_more;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Identifies the action performed on a specific object type in an event.
+/// Each field corresponds to the respective `SERVICE` / `TYPE` constants found on
+/// `ObjectType` and `ActionType` implementations.
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq, Serialize)]
+#[display(fmt = "{service}.{ty}:{action}")]
+pub struct EventKey {
+    pub service: &'static str,
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    pub action: &'static str,
+}
+
+impl EventKey {
+    pub fn new(service: &'static str, ty: &'static str, action: &'static str) -> Self {
+        Self { service, ty, action }
+    }
+}
+
+/// The json representation of an event evaluated by an in-process engine, split into its fields, i.e.
+/// ```json
+/// {
+///   "subject": ...,
+///   "input": ...,
+///   "context": ...,
+///   "tenant": ...,
+///   "transaction_id": ...
+/// }
+/// ```
+/// where missing fields are `null`.
+///
+/// Inputs which serialize to arrays are split into their items while any other input is a single item.
+/// Engines allow an event only if *every* item is allowed, so events with empty inputs are always allowed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SerializedEvent {
+    pub subject: Value,
+    pub items: Vec<Value>,
+    pub context: Value,
+    pub tenant: Value,
+    pub transaction_id: Value,
+}
+
+impl From<Value> for SerializedEvent {
+    fn from(mut event: Value) -> Self {
+        let mut take = |field: &str| event.get_mut(field).map(Value::take).unwrap_or_default();
+        Self {
+            subject: take("subject"),
+            items: match take("input") {
+                Value::Array(items) => items,
+                input => vec![input],
+            },
+            context: take("context"),
+            tenant: take("tenant"),
+            transaction_id: take("transaction_id"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("unable to serialize event: {0}")]
+pub struct SerializationError(pub Arc<serde_json::Error>);
+
+/// Serializes an event into the json representation evaluated by in-process engines.
+pub fn serialize(event: &impl Serialize) -> Result<Value, SerializationError> {
+    serde_json::to_value(event).map_err(|err| SerializationError(Arc::new(err)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn splits_inputs_into_items() {
+        let event = SerializedEvent::from(json!({ "subject": 1, "input": [2, 3], "tenant": "a" }));
+        assert_eq!(
+            event,
+            SerializedEvent {
+                subject: json!(1),
+                items: vec![json!(2), json!(3)],
+                tenant: json!("a"),
+                ..Default::default()
+            }
+        );
+        assert_eq!(SerializedEvent::from(json!({ "input": 2 })).items, vec![json!(2)]);
+        assert!(SerializedEvent::from(json!({ "input": [] })).items.is_empty());
+    }
+}
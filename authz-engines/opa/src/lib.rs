@@ -50,6 +50,9 @@ pub struct _OPAClient<Connector = HttpsConnector<HttpConnector>> {
     timeout: Duration,
     pub data_path: String,
     pub query: String,
+    /// Rule which evaluates a batch of events provided in `input.events`, producing an array of
+    /// decisions in the same order; defaults to `{query}_batch`.
+    pub batch_query: String,
 }
 
 impl Deref for OPAClient {
@@ -72,7 +75,7 @@ impl Client for OPAClient {
         let response = timeout(self.timeout, self.client.request(request))
             .await
             .map_err(|_| Error::new(StatusCode::REQUEST_TIMEOUT))?
-            .map_err(Error::default_details)?;
+            .map_err(Error::service_unavailable_details)?;
         cfg_if! {
             if #[cfg(feature = "debug")] {
                 let (parts, body) = response.into_parts();
@@ -111,6 +114,7 @@ impl OPAClient {
         let headers = HeaderMap::new();
         let port = port.map(|x| format!(":{x}")).unwrap_or_default();
         let base_uri = format!("{scheme}://{host}{port}");
+        let query = query.to_string();
 
         Ok(Self(Arc::new(_OPAClient {
             timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECONDS),
@@ -118,7 +122,8 @@ impl OPAClient {
             client,
             headers,
             data_path: data_path.to_string(),
-            query: query.to_string(),
+            batch_query: format!("{query}_batch"),
+            query,
         })))
     }
     pub fn timeout(self, timeout: Duration) -> Self {
@@ -126,4 +131,9 @@ impl OPAClient {
         _opa_client.timeout = timeout;
         Self(Arc::new(_opa_client))
     }
+    pub fn batch_query(self, batch_query: impl ToString) -> Self {
+        let mut _opa_client = Arc::try_unwrap(self.0).unwrap();
+        _opa_client.batch_query = batch_query.to_string();
+        Self(Arc::new(_opa_client))
+    }
 }
@@ -0,0 +1,256 @@
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+
+#[macro_use]
+extern crate derivative;
+
+pub use authzen_event::EventKey;
+pub use oso;
+
+use authzen_event::{SerializationError, SerializedEvent};
+use oso::{Class, Oso, PolarClass, PolarValue, ToPolar};
+use serde::Serialize;
+use serde_json::Value;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+/// An object being acted upon, passed to Polar as the `resource` in `allow(actor, action, resource)`.
+///
+/// Each object type is exposed to Polar as its own class, registered with [`Resource::class`], so
+/// that policies can specialize on it. Resources have the following attributes
+/// - `service`: the object's service
+/// - `type`: the object's type
+/// - `value`: a single item of the event's input, e.g. an object being created or the id of an object being read
+/// - `context`: the event's context
+/// - `tenant`: the tenant the event is scoped to, or `nil`
+/// - `transaction_id`: the transaction id of the event, or `nil`
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct Resource<O: ?Sized> {
+    pub service: &'static str,
+    pub ty: &'static str,
+    pub value: PolarValue,
+    pub context: PolarValue,
+    pub tenant: PolarValue,
+    pub transaction_id: PolarValue,
+    #[derivative(Debug = "ignore")]
+    _marker: PhantomData<fn(&O)>,
+}
+
+impl<O: ?Sized + 'static> PolarClass for Resource<O> {}
+
+impl<O: ?Sized + 'static> Resource<O> {
+    pub fn new(
+        service: &'static str,
+        ty: &'static str,
+        value: PolarValue,
+        context: PolarValue,
+        tenant: PolarValue,
+        transaction_id: PolarValue,
+    ) -> Self {
+        Self {
+            service,
+            ty,
+            value,
+            context,
+            tenant,
+            transaction_id,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The Polar class of objects of type `O`, referred to as `name` in policies.
+    /// ```rs
+    /// let mut oso = Oso::new();
+    /// oso.register_class(Resource::<Item<'static>>::class("Item"))?;
+    /// oso.load_str(r#"allow(actor, "read", item: Item) if item.value.owner_id = actor.id;"#)?;
+    /// ```
+    pub fn class(name: &str) -> Class {
+        Self::get_polar_class_builder()
+            .name(name)
+            .add_attribute_getter("service", |resource: &Self| resource.service)
+            .add_attribute_getter("type", |resource: &Self| resource.ty)
+            .add_attribute_getter("value", |resource: &Self| resource.value.clone())
+            .add_attribute_getter("context", |resource: &Self| resource.context.clone())
+            .add_attribute_getter("tenant", |resource: &Self| resource.tenant.clone())
+            .add_attribute_getter("transaction_id", |resource: &Self| resource.transaction_id.clone())
+            .build()
+    }
+}
+
+/// An in-process authorization engine which evaluates [Polar](https://docs.osohq.com) policies with oso.
+///
+/// An event is allowed if `allow(actor, action, resource)` succeeds for *every* item of its input, where
+/// - `actor` is the event's subject
+/// - `action` is the action's type, e.g. `"read"`
+/// - `resource` is a [`Resource`] wrapping an item of the event's input; inputs which are not arrays
+///   are treated as a single item (see [`SerializedEvent`])
+///
+/// Subjects, inputs and contexts are serialized to json and exposed to Polar as dictionaries, lists
+/// and primitives, with json `null` becoming `nil`.
+///
+/// Object classes must be registered (see [`Resource::class`]) before loading the policies which refer to them.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct OsoEngine {
+    #[derivative(Debug = "ignore")]
+    oso: Oso,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum OsoEngineError {
+    #[error("event denied by polar policy for `{0}`")]
+    Denied(EventKey),
+    #[error("unable to evaluate polar policy for `{0}`: {1}")]
+    Polar(EventKey, Arc<oso::OsoError>),
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+}
+
+impl OsoEngine {
+    /// Wraps an oso instance whose classes have been registered and policies loaded.
+    pub fn new(oso: Oso) -> Self {
+        Self { oso }
+    }
+
+    pub fn oso(&self) -> &Oso {
+        &self.oso
+    }
+
+    /// Evaluates `allow(actor, action, resource)` for each item of an already serialized event, i.e.
+    /// ```json
+    /// {
+    ///   "subject": ...,
+    ///   "input": ...,
+    ///   "context": ...,
+    ///   "tenant": ...,
+    ///   "transaction_id": ...
+    /// }
+    /// ```
+    /// where each resource is an instance of the Polar class registered for `O`.
+    pub fn evaluate<O: ?Sized + 'static>(&self, key: EventKey, event: Value) -> Result<(), OsoEngineError> {
+        let event = SerializedEvent::from(event);
+        let actor = to_polar_value(event.subject);
+        let context = to_polar_value(event.context);
+        let tenant = to_polar_value(event.tenant);
+        let transaction_id = to_polar_value(event.transaction_id);
+
+        for item in event.items {
+            let resource = Resource::<O>::new(
+                key.service,
+                key.ty,
+                to_polar_value(item),
+                context.clone(),
+                tenant.clone(),
+                transaction_id.clone(),
+            );
+            let allowed = self
+                .oso
+                .is_allowed(actor.clone(), key.action, resource)
+                .map_err(|err| OsoEngineError::Polar(key, Arc::new(err)))?;
+            if !allowed {
+                return Err(OsoEngineError::Denied(key));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes `event` and then evaluates it (see [`OsoEngine::evaluate`]).
+    pub fn evaluate_serialize<O: ?Sized + 'static>(
+        &self,
+        key: EventKey,
+        event: &impl Serialize,
+    ) -> Result<(), OsoEngineError> {
+        self.evaluate::<O>(key, authzen_event::serialize(event)?)
+    }
+}
+
+/// Converts a json value into its Polar equivalent, mapping `null` to `nil`.
+pub fn to_polar_value(value: Value) -> PolarValue {
+    match value {
+        Value::Null => None::<PolarValue>.to_polar(),
+        Value::Bool(value) => PolarValue::Boolean(value),
+        Value::Number(value) => match value.as_i64() {
+            Some(value) => PolarValue::Integer(value),
+            None => PolarValue::Float(value.as_f64().unwrap_or(f64::NAN)),
+        },
+        Value::String(value) => PolarValue::String(value),
+        Value::Array(values) => PolarValue::List(values.into_iter().map(to_polar_value).collect()),
+        Value::Object(values) => PolarValue::Map(
+            values
+                .into_iter()
+                .map(|(key, value)| (key, to_polar_value(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn converts_json_to_polar_values() {
+        let value = to_polar_value(json!({ "id": 1, "price": 1.5, "tags": ["a"], "owner": { "admin": true } }));
+        let PolarValue::Map(fields) = value else {
+            panic!("expected a map")
+        };
+        assert!(matches!(fields["id"], PolarValue::Integer(1)));
+        assert!(matches!(fields["price"], PolarValue::Float(price) if price == 1.5));
+        assert!(
+            matches!(&fields["tags"], PolarValue::List(tags) if matches!(&tags[..], [PolarValue::String(tag)] if tag == "a"))
+        );
+        assert!(
+            matches!(&fields["owner"], PolarValue::Map(owner) if matches!(owner["admin"], PolarValue::Boolean(true)))
+        );
+    }
+
+    struct Item;
+
+    fn engine(policy: &str) -> OsoEngine {
+        let mut oso = Oso::new();
+        oso.register_class(Resource::<Item>::class("Item")).unwrap();
+        oso.load_str(policy).unwrap();
+        OsoEngine::new(oso)
+    }
+
+    #[test]
+    fn evaluates_each_item_as_a_registered_resource() {
+        let engine =
+            engine(r#"allow(actor, "read", item: Item) if item.service = "cart" and item.value.owner_id = actor.id;"#);
+        let key = EventKey::new("cart", "item", "read");
+        let event = |owner_ids: Value| json!({ "subject": { "id": 1 }, "input": owner_ids, "context": null });
+
+        assert!(engine.evaluate::<Item>(key, event(json!([{ "owner_id": 1 }]))).is_ok());
+        assert!(engine.evaluate::<Item>(key, event(json!({ "owner_id": 1 }))).is_ok());
+        assert!(engine.evaluate::<Item>(key, event(json!([]))).is_ok());
+        assert!(matches!(
+            engine.evaluate::<Item>(key, event(json!([{ "owner_id": 1 }, { "owner_id": 2 }]))),
+            Err(OsoEngineError::Denied(_))
+        ));
+        assert!(matches!(
+            engine.evaluate::<Item>(
+                EventKey::new("cart", "item", "delete"),
+                event(json!([{ "owner_id": 1 }]))
+            ),
+            Err(OsoEngineError::Denied(_))
+        ));
+    }
+
+    #[test]
+    fn exposes_the_tenant_to_polar() {
+        let engine = engine(r#"allow(actor, "read", item: Item) if item.tenant = actor.tenant;"#);
+        let key = EventKey::new("cart", "item", "read");
+        let event = |tenant: Value| json!({ "subject": { "tenant": "a" }, "input": 1, "tenant": tenant });
+
+        assert!(engine.evaluate::<Item>(key, event(json!("a"))).is_ok());
+        assert!(matches!(
+            engine.evaluate::<Item>(key, event(json!("b"))),
+            Err(OsoEngineError::Denied(_))
+        ));
+        assert!(matches!(
+            engine.evaluate::<Item>(key, event(Value::Null)),
+            Err(OsoEngineError::Denied(_))
+        ));
+    }
+}
@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+/// Grants a role to a subject, either globally or within a single scope such as a tenant.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct RoleBinding {
+    pub subject_id: String,
+    pub role: String,
+    /// the scope in which the role is granted, or `None` if it is granted in every scope
+    pub scope: Option<String>,
+}
+
+impl RoleBinding {
+    pub fn global(subject_id: impl Into<String>, role: impl Into<String>) -> Self {
+        Self {
+            subject_id: subject_id.into(),
+            role: role.into(),
+            scope: None,
+        }
+    }
+
+    pub fn scoped(subject_id: impl Into<String>, role: impl Into<String>, scope: impl Into<String>) -> Self {
+        Self {
+            subject_id: subject_id.into(),
+            role: role.into(),
+            scope: Some(scope.into()),
+        }
+    }
+
+    /// Returns whether this binding grants its role in `scope`.
+    pub fn applies_to(&self, scope: Option<&str>) -> bool {
+        match &self.scope {
+            None => true,
+            Some(binding_scope) => scope == Some(&**binding_scope),
+        }
+    }
+}
+
+/// Error returned from a [`BindingStore`] which was unable to read bindings.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("unable to read role bindings: {0}")]
+pub struct BindingStoreError(pub Arc<dyn std::error::Error + Send + Sync>);
+
+impl BindingStoreError {
+    pub fn new(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(Arc::from(err.into()))
+    }
+}
+
+/// Storage of the role bindings read by an [`RbacEngine`](crate::RbacEngine).
+#[async_trait]
+pub trait BindingStore<TransactionId>: Send + Sync {
+    /// Returns all bindings of the subject with id `subject_id`, regardless of scope.
+    async fn bindings(
+        &self,
+        subject_id: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<RoleBinding>, BindingStoreError>;
+}
+
+/// A [`BindingStore`] which keeps its bindings in memory. Cloning shares the same bindings.
+#[derive(Clone, Debug, Default)]
+pub struct InMemoryBindingStore {
+    bindings: Arc<RwLock<Vec<RoleBinding>>>,
+}
+
+impl InMemoryBindingStore {
+    pub fn new(bindings: impl IntoIterator<Item = RoleBinding>) -> Self {
+        Self {
+            bindings: Arc::new(RwLock::new(bindings.into_iter().collect())),
+        }
+    }
+
+    pub fn bind(&self, binding: RoleBinding) {
+        let mut bindings = self.bindings.write().unwrap_or_else(|err| err.into_inner());
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    pub fn unbind(&self, binding: &RoleBinding) {
+        self.bindings
+            .write()
+            .unwrap_or_else(|err| err.into_inner())
+            .retain(|existing| existing != binding);
+    }
+}
+
+#[async_trait]
+impl<TransactionId: Sync> BindingStore<TransactionId> for InMemoryBindingStore {
+    async fn bindings(
+        &self,
+        subject_id: &str,
+        _: Option<&TransactionId>,
+    ) -> Result<Vec<RoleBinding>, BindingStoreError> {
+        Ok(self
+            .bindings
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .filter(|binding| binding.subject_id == subject_id)
+            .cloned()
+            .collect())
+    }
+}
@@ -0,0 +1,278 @@
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+
+#[macro_use]
+extern crate async_trait;
+#[macro_use]
+extern crate derivative;
+
+mod bindings;
+mod roles;
+
+pub use authzen_event::EventKey;
+pub use bindings::*;
+pub use roles::*;
+
+use authzen_event::SerializationError;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// An in-process role-based authorization engine.
+///
+/// An event is allowed if any role bound to its subject grants a [`Permission`] matching the event's
+/// service, object type and action. Bindings are read from a [`BindingStore`] by the subject's id, which
+/// is the event's subject if it serializes to a primitive, or its `id` field if it serializes to an object.
+///
+/// Bindings can be limited to a scope such as a tenant, in which case they only apply to events whose
+/// scope (see [`RbacEngine::scope`]) is the same. Events without a scope are only allowed by global bindings.
+///
+/// Roles loaded with [`RbacEngine::from_path`] can be reloaded from their file with [`RbacEngine::reload`],
+/// or whenever the file changes with [`RbacEngine::watch`].
+#[derive(Derivative)]
+#[derivative(Clone(bound = "B: Clone"), Debug(bound = "B: std::fmt::Debug"))]
+pub struct RbacEngine<B> {
+    roles: Arc<RwLock<Arc<Roles>>>,
+    path: Option<Arc<PathBuf>>,
+    scope: Option<String>,
+    bindings: B,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum RbacError {
+    #[error("event denied by role bindings for `{0}`")]
+    Denied(EventKey),
+    #[error("unable to determine the id of a subject from `{0}`")]
+    MissingId(Value),
+    #[error(transparent)]
+    BindingStore(BindingStoreError),
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+}
+
+impl<B> RbacEngine<B> {
+    pub fn new(roles: Roles, bindings: B) -> Self {
+        Self {
+            roles: Arc::new(RwLock::new(Arc::new(roles))),
+            path: None,
+            scope: None,
+            bindings,
+        }
+    }
+
+    /// Constructs an engine whose roles are read from a yaml or json file (see [`Roles::from_path`]).
+    pub fn from_path(path: impl Into<PathBuf>, bindings: B) -> Result<Self, RolesError> {
+        let path = path.into();
+        let mut engine = Self::new(Roles::from_path(&path)?, bindings);
+        engine.path = Some(Arc::new(path));
+        Ok(engine)
+    }
+
+    /// Scopes each event by the value found at `pointer`, a [json pointer](https://www.rfc-editor.org/rfc/rfc6901)
+    /// into the serialized event, e.g. `/context/tenant_id`. Strings are used as is, while any other
+    /// values are rendered as json.
+    pub fn scope(mut self, pointer: impl Into<String>) -> Self {
+        self.scope = Some(pointer.into());
+        self
+    }
+
+    pub fn bindings(&self) -> &B {
+        &self.bindings
+    }
+
+    /// The roles currently used to evaluate events.
+    pub fn roles(&self) -> Arc<Roles> {
+        self.roles.read().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Replaces the roles used to evaluate events, including in clones of this engine.
+    pub fn set_roles(&self, roles: Roles) {
+        *self.roles.write().unwrap_or_else(|err| err.into_inner()) = Arc::new(roles);
+    }
+
+    /// Rereads roles from the file this engine was constructed with, keeping the current roles if
+    /// they cannot be read. Does nothing for engines not constructed with [`RbacEngine::from_path`].
+    pub fn reload(&self) -> Result<(), RolesError> {
+        if let Some(path) = &self.path {
+            self.set_roles(Roles::from_path(&**path)?);
+        }
+        Ok(())
+    }
+
+    /// Spawns a task which checks the modification time of the roles file every `period` and
+    /// reloads roles whenever it changes, passing any errors to `on_error` and keeping the current roles.
+    /// Returns `None` for engines not constructed with [`RbacEngine::from_path`].
+    ///
+    /// Must be called within a tokio runtime.
+    pub fn watch(
+        &self,
+        period: Duration,
+        on_error: impl Fn(RolesError) + Send + 'static,
+    ) -> Option<tokio::task::JoinHandle<()>> {
+        let path = self.path.clone()?;
+        let roles = self.roles.clone();
+        let modified = |path: &PathBuf| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+
+        let mut last_modified = modified(&path);
+        Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(period);
+            loop {
+                interval.tick().await;
+                let current = modified(&path);
+                if current == last_modified {
+                    continue;
+                }
+                last_modified = current;
+                match Roles::from_path(&**path) {
+                    Ok(reloaded) => *roles.write().unwrap_or_else(|err| err.into_inner()) = Arc::new(reloaded),
+                    Err(err) => on_error(err),
+                }
+            }
+        }))
+    }
+
+    /// Evaluates an already serialized event, i.e.
+    /// ```json
+    /// {
+    ///   "subject": ...,
+    ///   "input": ...,
+    ///   "context": ...
+    /// }
+    /// ```
+    pub async fn evaluate<TransactionId>(
+        &self,
+        key: EventKey,
+        event: Value,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<(), RbacError>
+    where
+        B: BindingStore<TransactionId>,
+    {
+        let subject_id = match event.get("subject") {
+            Some(Value::Object(subject)) => subject
+                .get("id")
+                .and_then(render)
+                .ok_or_else(|| RbacError::MissingId(Value::Object(subject.clone())))?,
+            subject => subject
+                .and_then(render)
+                .ok_or_else(|| RbacError::MissingId(subject.cloned().unwrap_or_default()))?,
+        };
+        let scope = self
+            .scope
+            .as_ref()
+            .and_then(|pointer| event.pointer(pointer))
+            .and_then(render);
+
+        let bindings = self
+            .bindings
+            .bindings(&subject_id, transaction_id)
+            .await
+            .map_err(RbacError::BindingStore)?;
+        let roles = self.roles();
+        let allowed = bindings.iter().any(|binding| {
+            binding.applies_to(scope.as_deref()) && roles.allows(&binding.role, key.service, key.ty, key.action)
+        });
+        match allowed {
+            true => Ok(()),
+            false => Err(RbacError::Denied(key)),
+        }
+    }
+
+    /// Serializes `event` and then evaluates it (see [`RbacEngine::evaluate`]).
+    pub async fn evaluate_serialize<TransactionId>(
+        &self,
+        key: EventKey,
+        event: &impl Serialize,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<(), RbacError>
+    where
+        B: BindingStore<TransactionId>,
+    {
+        self.evaluate(key, authzen_event::serialize(event)?, transaction_id)
+            .await
+    }
+}
+
+/// Renders a primitive as a subject id or scope, using strings as is and the json representation
+/// of any other primitive.
+fn render(value: &Value) -> Option<String> {
+    match value {
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+        Value::String(value) => Some(value.clone()),
+        value => Some(value.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn evaluates_scoped_bindings() {
+        let roles = Roles::from_yaml(
+            r#"
+            roles:
+              viewer:
+                permissions: ["cart.*:read"]
+              admin:
+                inherits: [viewer]
+                permissions: ["*.*:*"]
+            "#,
+        )
+        .unwrap();
+        let bindings = InMemoryBindingStore::new([
+            RoleBinding::global("1", "viewer"),
+            RoleBinding::scoped("2", "admin", "acme"),
+        ]);
+        let engine = RbacEngine::new(roles, bindings).scope("/context/tenant_id");
+
+        let evaluate = |subject: Value, action, tenant_id: &str| {
+            futures::executor::block_on(engine.evaluate::<()>(
+                EventKey::new("cart", "item", action),
+                json!({ "subject": subject, "input": [], "context": { "tenant_id": tenant_id } }),
+                None,
+            ))
+        };
+        assert!(evaluate(json!(1), "read", "acme").is_ok());
+        assert!(matches!(
+            evaluate(json!(1), "delete", "acme"),
+            Err(RbacError::Denied(_))
+        ));
+        assert!(evaluate(json!({ "id": "2" }), "delete", "acme").is_ok());
+        assert!(matches!(
+            evaluate(json!({ "id": "2" }), "read", "globex"),
+            Err(RbacError::Denied(_)),
+        ));
+        assert!(matches!(
+            evaluate(json!({ "name": "3" }), "read", "acme"),
+            Err(RbacError::MissingId(_)),
+        ));
+    }
+
+    #[test]
+    fn reloads_roles() {
+        let path = std::env::temp_dir().join(format!("authzen-rbac-{}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"{ "roles": { "viewer": { "permissions": ["cart.item:read"] } } }"#,
+        )
+        .unwrap();
+        let engine = RbacEngine::from_path(&path, InMemoryBindingStore::default()).unwrap();
+        assert!(engine.roles().allows("viewer", "cart", "item", "read"));
+
+        std::fs::write(
+            &path,
+            r#"{ "roles": { "viewer": { "permissions": ["cart.cart:read"] } } }"#,
+        )
+        .unwrap();
+        engine.reload().unwrap();
+        assert!(!engine.roles().allows("viewer", "cart", "item", "read"));
+
+        std::fs::write(&path, "{").unwrap();
+        assert!(matches!(engine.reload(), Err(RolesError::Json(_))));
+        assert!(engine.roles().allows("viewer", "cart", "cart", "read"));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
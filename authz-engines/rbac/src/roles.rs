@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// Permission to perform an action on objects of a type in a service, written `{service}.{type}:{action}`
+/// where any segment can be the wildcard `*`, e.g. `cart.item:read`, `cart.*:read` or `*.*:*`.
+#[derive(Clone, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Permission {
+    pub service: String,
+    pub ty: String,
+    pub action: String,
+}
+
+impl Permission {
+    pub const WILDCARD: &'static str = "*";
+
+    pub fn allows(&self, service: &str, ty: &str, action: &str) -> bool {
+        let matches = |pattern: &str, value: &str| pattern == Self::WILDCARD || pattern == value;
+        matches(&self.service, service) && matches(&self.ty, ty) && matches(&self.action, action)
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}:{}", self.service, self.ty, self.action)
+    }
+}
+
+impl FromStr for Permission {
+    type Err = RolesError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || RolesError::Permission(s.to_string());
+        let (object, action) = s.split_once(':').ok_or_else(invalid)?;
+        let (service, ty) = object.split_once('.').ok_or_else(invalid)?;
+        if [service, ty, action]
+            .iter()
+            .any(|segment| segment.is_empty() || segment.contains([':', '.']))
+        {
+            return Err(invalid());
+        }
+        Ok(Self {
+            service: service.to_string(),
+            ty: ty.to_string(),
+            action: action.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for Permission {
+    type Error = RolesError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Permission> for String {
+    fn from(value: Permission) -> Self {
+        value.to_string()
+    }
+}
+
+/// Role definitions as written in a roles file, e.g.
+/// ```yaml
+/// roles:
+///   viewer:
+///     permissions: ["*.*:read"]
+///   editor:
+///     inherits: [viewer]
+///     permissions: ["cart.*:create", "cart.*:update"]
+///   admin:
+///     permissions: ["*.*:*"]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoleDefinitions {
+    pub roles: BTreeMap<String, RoleDefinition>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct RoleDefinition {
+    /// roles whose permissions are also granted by this role
+    #[serde(default)]
+    pub inherits: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<Permission>,
+}
+
+/// Roles with the permissions they grant, including those of the roles they inherit.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Roles {
+    permissions: BTreeMap<String, BTreeSet<Permission>>,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum RolesError {
+    #[error("invalid permission `{0}`, expected `{{service}}.{{type}}:{{action}}`")]
+    Permission(String),
+    #[error("role `{role}` inherits undefined role `{inherits}`")]
+    UnknownRole { role: String, inherits: String },
+    #[error("role `{0}` inherits itself")]
+    Cycle(String),
+    #[error("unable to read roles from `{0}`: {1}")]
+    Io(String, Arc<std::io::Error>),
+    #[error("unable to parse roles: {0}")]
+    Yaml(Arc<serde_yaml::Error>),
+    #[error("unable to parse roles: {0}")]
+    Json(Arc<serde_json::Error>),
+    #[error("unable to determine the format of roles file `{0}`, expected a `.yaml`, `.yml` or `.json` extension")]
+    Format(String),
+}
+
+impl Roles {
+    /// Resolves the permissions of each role, failing if a role inherits an undefined role or itself.
+    pub fn new(definitions: RoleDefinitions) -> Result<Self, RolesError> {
+        let mut roles = Self::default();
+        for role in definitions.roles.keys() {
+            roles.resolve(&definitions, role, &mut vec![])?;
+        }
+        Ok(roles)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, RolesError> {
+        Self::new(serde_yaml::from_str(yaml).map_err(|err| RolesError::Yaml(Arc::new(err)))?)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, RolesError> {
+        Self::new(serde_json::from_str(json).map_err(|err| RolesError::Json(Arc::new(err)))?)
+    }
+
+    /// Reads roles from a yaml or json file, depending on its extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, RolesError> {
+        let path = path.as_ref();
+        let display = path.display().to_string();
+        let contents = std::fs::read_to_string(path).map_err(|err| RolesError::Io(display.clone(), Arc::new(err)))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => Self::from_yaml(&contents),
+            Some("json") => Self::from_json(&contents),
+            _ => Err(RolesError::Format(display)),
+        }
+    }
+
+    /// Permissions granted by a role, or `None` if the role is not defined.
+    pub fn permissions(&self, role: &str) -> Option<&BTreeSet<Permission>> {
+        self.permissions.get(role)
+    }
+
+    /// Returns whether `role` grants permission to perform `action` on objects of type `ty` in `service`.
+    pub fn allows(&self, role: &str, service: &str, ty: &str, action: &str) -> bool {
+        self.permissions(role)
+            .map(|permissions| {
+                permissions
+                    .iter()
+                    .any(|permission| permission.allows(service, ty, action))
+            })
+            .unwrap_or_default()
+    }
+
+    fn resolve<'a>(
+        &mut self,
+        definitions: &'a RoleDefinitions,
+        role: &'a str,
+        path: &mut Vec<&'a str>,
+    ) -> Result<BTreeSet<Permission>, RolesError> {
+        if let Some(permissions) = self.permissions.get(role) {
+            return Ok(permissions.clone());
+        }
+        if path.contains(&role) {
+            return Err(RolesError::Cycle(role.to_string()));
+        }
+        let definition = &definitions.roles[role];
+        let mut permissions = definition.permissions.iter().cloned().collect::<BTreeSet<_>>();
+
+        path.push(role);
+        for inherits in &definition.inherits {
+            if !definitions.roles.contains_key(inherits) {
+                return Err(RolesError::UnknownRole {
+                    role: role.to_string(),
+                    inherits: inherits.clone(),
+                });
+            }
+            permissions.extend(self.resolve(definitions, inherits, path)?);
+        }
+        path.pop();
+
+        self.permissions.insert(role.to_string(), permissions.clone());
+        Ok(permissions)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn resolves_inherited_permissions() {
+        let roles = Roles::from_yaml(
+            r#"
+            roles:
+              viewer:
+                permissions: ["*.*:read"]
+              editor:
+                inherits: [viewer]
+                permissions: ["cart.*:update"]
+            "#,
+        )
+        .unwrap();
+        assert!(roles.allows("editor", "cart", "item", "read"));
+        assert!(roles.allows("editor", "cart", "item", "update"));
+        assert!(!roles.allows("editor", "accounts", "account", "update"));
+        assert!(!roles.allows("viewer", "cart", "item", "update"));
+        assert!(!roles.allows("admin", "cart", "item", "read"));
+    }
+
+    #[test]
+    fn rejects_invalid_roles() {
+        let json = |roles: &str| Roles::from_json(&format!(r#"{{ "roles": {roles} }}"#)).unwrap_err();
+        assert!(matches!(
+            json(r#"{ "a": { "inherits": ["b"] } }"#),
+            RolesError::UnknownRole { .. }
+        ));
+        assert!(matches!(
+            json(r#"{ "a": { "inherits": ["b"] }, "b": { "inherits": ["a"] } }"#),
+            RolesError::Cycle(_),
+        ));
+        assert!(matches!(
+            json(r#"{ "a": { "permissions": ["cart:read"] } }"#),
+            RolesError::Json(_)
+        ));
+    }
+}
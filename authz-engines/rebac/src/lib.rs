@@ -0,0 +1,524 @@
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+
+#[macro_use]
+extern crate async_trait;
+#[macro_use]
+extern crate derivative;
+
+mod schema;
+mod store;
+mod tuples;
+
+pub use authzen_event::EventKey;
+pub use schema::*;
+pub use store::*;
+pub use tuples::*;
+
+use authzen_event::{SerializationError, SerializedEvent};
+use futures::future::{try_join_all, BoxFuture};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::Arc;
+
+/// An in-process relationship-based authorization engine in the style of
+/// [Zanzibar](https://research.google/pubs/pub48190), which answers whether a subject has a relation
+/// to an object from the relation tuples in a [`TupleStore`] and the rewrites of a [`Schema`].
+///
+/// Events are mapped to checks as follows
+/// - object: `{service}.{type}:{id}` for each item of the event's input, where items which serialize
+///   to objects are identified by their `id` field
+/// - relation: the action's type, e.g. `read`
+/// - subject: the event's subject if it serializes to a primitive, or its `id` field if it serializes to an object
+///
+/// An event is allowed if the check succeeds for *every* object (see [`SerializedEvent`]).
+#[derive(Clone, Debug)]
+pub struct RebacEngine<S> {
+    schema: Arc<Schema>,
+    store: S,
+    max_depth: usize,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum RebacError {
+    #[error("event denied by relation tuples for `{0}`")]
+    Denied(EventKey),
+    #[error("relation `{relation}` is not defined in namespace `{namespace}`")]
+    UnknownRelation { namespace: String, relation: String },
+    #[error("exceeded the maximum depth of nested relations while evaluating `{0}`")]
+    MaxDepth(SubjectSet),
+    #[error("unable to determine the id of an object or subject from `{0}`")]
+    MissingId(Value),
+    #[error(transparent)]
+    TupleStore(TupleStoreError),
+    #[error(transparent)]
+    Serialization(#[from] SerializationError),
+}
+
+/// The subjects which have a relation to an object, as computed by [`RebacEngine::expand`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Expansion {
+    pub object: ObjectRef,
+    pub relation: String,
+    /// ids of the subjects related to the object directly by tuples
+    pub subject_ids: Vec<String>,
+    /// expansions of the subject sets, computed relations and tuple-to-userset relations which
+    /// also make up this relation
+    pub children: Vec<Expansion>,
+}
+
+impl Expansion {
+    /// Ids of all subjects in this expansion and its children.
+    pub fn all_subject_ids(&self) -> BTreeSet<&str> {
+        let mut ids = self.subject_ids.iter().map(|id| &**id).collect::<BTreeSet<_>>();
+        for child in &self.children {
+            ids.extend(child.all_subject_ids());
+        }
+        ids
+    }
+}
+
+type Path = Vec<(ObjectRef, String)>;
+
+impl<S> RebacEngine<S> {
+    pub const DEFAULT_MAX_DEPTH: usize = 32;
+
+    pub fn new(schema: Schema, store: S) -> Self {
+        Self {
+            schema: Arc::new(schema),
+            store,
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+        }
+    }
+
+    /// Maximum number of nested relations followed while evaluating a single check or expansion,
+    /// exceeding which returns [`RebacError::MaxDepth`]. Cycles between relations are detected separately.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns whether the subject with id `subject_id` has `relation` to `object`.
+    pub async fn check<TransactionId: Sync>(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        subject_id: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<bool, RebacError>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        self.usersets(&object.namespace, relation)?;
+        self.check_inner(object.clone(), relation.to_string(), subject_id, transaction_id, vec![])
+            .await
+    }
+
+    /// Returns the tree of subjects which have `relation` to `object`.
+    pub async fn expand<TransactionId: Sync>(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Expansion, RebacError>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        self.usersets(&object.namespace, relation)?;
+        self.expand_inner(object.clone(), relation.to_string(), transaction_id, vec![])
+            .await
+    }
+
+    /// Returns the ids of the objects in `namespace` to which the subject with id `subject_id` has `relation`.
+    ///
+    /// Candidate objects are those which are the object of at least one tuple (see [`TupleStore::object_ids`]),
+    /// each of which is then checked.
+    pub async fn lookup_resources<TransactionId: Sync>(
+        &self,
+        namespace: &str,
+        relation: &str,
+        subject_id: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<String>, RebacError>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        self.usersets(namespace, relation)?;
+        let ids = self
+            .store
+            .object_ids(namespace, transaction_id)
+            .await
+            .map_err(RebacError::TupleStore)?;
+        let allowed = try_join_all(ids.iter().map(|id| {
+            self.check_inner(
+                ObjectRef::new(namespace, id),
+                relation.to_string(),
+                subject_id,
+                transaction_id,
+                vec![],
+            )
+        }))
+        .await?;
+        Ok(ids
+            .into_iter()
+            .zip(allowed)
+            .filter_map(|(id, allowed)| allowed.then_some(id))
+            .collect())
+    }
+
+    /// Evaluates an already serialized event, i.e.
+    /// ```json
+    /// {
+    ///   "subject": ...,
+    ///   "input": ...
+    /// }
+    /// ```
+    pub async fn evaluate<TransactionId: Sync>(
+        &self,
+        key: EventKey,
+        event: Value,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<(), RebacError>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        let namespace = namespace(&key);
+        self.usersets(&namespace, key.action)?;
+
+        let event = SerializedEvent::from(event);
+        let subject_id = object_id(event.subject)?;
+
+        for item in event.items {
+            let object = ObjectRef::new(&namespace, object_id(item)?);
+            if !self.check(&object, key.action, &subject_id, transaction_id).await? {
+                return Err(RebacError::Denied(key));
+            }
+        }
+        Ok(())
+    }
+
+    /// Serializes `event` and then evaluates it (see [`RebacEngine::evaluate`]).
+    pub async fn evaluate_serialize<TransactionId: Sync>(
+        &self,
+        key: EventKey,
+        event: &impl Serialize,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<(), RebacError>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        self.evaluate(key, authzen_event::serialize(event)?, transaction_id)
+            .await
+    }
+
+    fn usersets(&self, namespace: &str, relation: &str) -> Result<&[Userset], RebacError> {
+        self.schema
+            .relation(namespace, relation)
+            .ok_or_else(|| RebacError::UnknownRelation {
+                namespace: namespace.to_string(),
+                relation: relation.to_string(),
+            })
+    }
+
+    /// Appends `(object, relation)` to `path`, returning `None` if it would form a cycle.
+    fn enter(&self, path: &Path, object: &ObjectRef, relation: &str) -> Result<Option<Path>, RebacError> {
+        if path.iter().any(|(o, r)| o == object && r == relation) {
+            return Ok(None);
+        }
+        if path.len() >= self.max_depth {
+            return Err(RebacError::MaxDepth(SubjectSet {
+                object: object.clone(),
+                relation: Some(relation.to_string()),
+            }));
+        }
+        let mut path = path.clone();
+        path.push((object.clone(), relation.to_string()));
+        Ok(Some(path))
+    }
+
+    fn check_inner<'a, TransactionId: Sync>(
+        &'a self,
+        object: ObjectRef,
+        relation: String,
+        subject_id: &'a str,
+        transaction_id: Option<&'a TransactionId>,
+        path: Path,
+    ) -> BoxFuture<'a, Result<bool, RebacError>>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        Box::pin(async move {
+            // tuples may refer to namespaces and relations which are not defined, which are ignored
+            let Ok(usersets) = self.usersets(&object.namespace, &relation) else {
+                return Ok(false);
+            };
+            let Some(path) = self.enter(&path, &object, &relation)? else {
+                return Ok(false);
+            };
+
+            for userset in usersets {
+                let allowed = match userset {
+                    Userset::This => {
+                        let mut allowed = false;
+                        for subject in self.read(&object, &relation, transaction_id).await? {
+                            allowed = match subject {
+                                Subject::Id(id) => id == subject_id,
+                                Subject::Set(SubjectSet {
+                                    object,
+                                    relation: Some(relation),
+                                }) => {
+                                    self.check_inner(object, relation, subject_id, transaction_id, path.clone())
+                                        .await?
+                                }
+                                Subject::Set(SubjectSet { relation: None, .. }) => false,
+                            };
+                            if allowed {
+                                break;
+                            }
+                        }
+                        allowed
+                    }
+                    Userset::Computed(computed) => {
+                        self.check_inner(
+                            object.clone(),
+                            computed.clone(),
+                            subject_id,
+                            transaction_id,
+                            path.clone(),
+                        )
+                        .await?
+                    }
+                    Userset::TupleToUserset { tupleset, computed } => {
+                        let mut allowed = false;
+                        for subject in self.read(&object, tupleset, transaction_id).await? {
+                            if let Subject::Set(set) = subject {
+                                allowed = self
+                                    .check_inner(set.object, computed.clone(), subject_id, transaction_id, path.clone())
+                                    .await?;
+                                if allowed {
+                                    break;
+                                }
+                            }
+                        }
+                        allowed
+                    }
+                };
+                if allowed {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+    }
+
+    fn expand_inner<'a, TransactionId: Sync>(
+        &'a self,
+        object: ObjectRef,
+        relation: String,
+        transaction_id: Option<&'a TransactionId>,
+        path: Path,
+    ) -> BoxFuture<'a, Result<Expansion, RebacError>>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        Box::pin(async move {
+            let mut expansion = Expansion {
+                object: object.clone(),
+                relation: relation.clone(),
+                subject_ids: vec![],
+                children: vec![],
+            };
+            let Ok(usersets) = self.usersets(&object.namespace, &relation) else {
+                return Ok(expansion);
+            };
+            let Some(path) = self.enter(&path, &object, &relation)? else {
+                return Ok(expansion);
+            };
+
+            for userset in usersets {
+                match userset {
+                    Userset::This => {
+                        for subject in self.read(&object, &relation, transaction_id).await? {
+                            match subject {
+                                Subject::Id(id) => expansion.subject_ids.push(id),
+                                Subject::Set(SubjectSet {
+                                    object,
+                                    relation: Some(relation),
+                                }) => expansion.children.push(
+                                    self.expand_inner(object, relation, transaction_id, path.clone())
+                                        .await?,
+                                ),
+                                Subject::Set(SubjectSet { relation: None, .. }) => {}
+                            }
+                        }
+                    }
+                    Userset::Computed(computed) => expansion.children.push(
+                        self.expand_inner(object.clone(), computed.clone(), transaction_id, path.clone())
+                            .await?,
+                    ),
+                    Userset::TupleToUserset { tupleset, computed } => {
+                        for subject in self.read(&object, tupleset, transaction_id).await? {
+                            if let Subject::Set(set) = subject {
+                                expansion.children.push(
+                                    self.expand_inner(set.object, computed.clone(), transaction_id, path.clone())
+                                        .await?,
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+            Ok(expansion)
+        })
+    }
+
+    async fn read<TransactionId: Sync>(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<Subject>, RebacError>
+    where
+        S: TupleStore<TransactionId>,
+    {
+        self.store
+            .read(object, relation, transaction_id)
+            .await
+            .map_err(RebacError::TupleStore)
+    }
+}
+
+/// Namespace of an event's objects, i.e. `{service}.{type}`.
+pub fn namespace(key: &EventKey) -> String {
+    format!("{}.{}", key.service, key.ty)
+}
+
+/// Renders the id of an object or subject, using strings as is, the json representation of any
+/// other primitive and the `id` field of objects.
+pub fn object_id(value: Value) -> Result<String, RebacError> {
+    match value {
+        Value::String(id) => Ok(id),
+        Value::Object(ref fields) => match fields.get("id").filter(|id| !id.is_null() && !id.is_object()) {
+            Some(Value::String(id)) => Ok(id.clone()),
+            Some(id) => Ok(id.to_string()),
+            None => Err(RebacError::MissingId(value)),
+        },
+        Value::Null | Value::Array(_) => Err(RebacError::MissingId(value)),
+        id => Ok(id.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn engine() -> RebacEngine<InMemoryTupleStore<u32>> {
+        let schema = r#"
+            namespace cart.cart {
+                relation owner
+                relation editor = this | owner
+            }
+            namespace cart.item {
+                relation parent
+                relation editor = this | parent->editor
+                relation read = editor
+            }
+        "#;
+        let tuples = [
+            "cart.cart:1#owner@alice",
+            "cart.cart:1#editor@cart.cart:2#owner",
+            "cart.cart:2#owner@bob",
+            "cart.item:1#parent@cart.cart:1",
+            "cart.item:2#editor@carol",
+        ];
+        RebacEngine::new(
+            schema.parse().unwrap(),
+            InMemoryTupleStore::new(tuples.map(|tuple| tuple.parse().unwrap())),
+        )
+    }
+
+    fn check(engine: &RebacEngine<InMemoryTupleStore<u32>>, object: &str, relation: &str, subject: &str) -> bool {
+        futures::executor::block_on(engine.check::<u32>(&object.parse().unwrap(), relation, subject, None)).unwrap()
+    }
+
+    #[test]
+    fn checks_nested_relations() {
+        let engine = engine();
+        assert!(check(&engine, "cart.item:1", "read", "alice"));
+        assert!(check(&engine, "cart.item:1", "read", "bob"));
+        assert!(!check(&engine, "cart.item:1", "read", "carol"));
+        assert!(check(&engine, "cart.item:2", "read", "carol"));
+    }
+
+    #[test]
+    fn expands_and_looks_up_relations() {
+        let engine = engine();
+        let item = "cart.item:1".parse().unwrap();
+        let expansion = futures::executor::block_on(engine.expand::<u32>(&item, "read", None)).unwrap();
+        assert_eq!(expansion.all_subject_ids(), BTreeSet::from(["alice", "bob"]));
+
+        let lookup = |subject| {
+            futures::executor::block_on(engine.lookup_resources::<u32>("cart.item", "read", subject, None)).unwrap()
+        };
+        assert_eq!(lookup("bob"), ["1"]);
+        assert_eq!(lookup("carol"), ["2"]);
+    }
+
+    #[test]
+    fn stages_transaction_writes() {
+        let engine = engine();
+        let write = TupleChange::Write("cart.item:2#editor@dave".parse().unwrap());
+        let delete = TupleChange::Delete("cart.item:2#editor@carol".parse().unwrap());
+        engine.store().write([write, delete], Some(7));
+
+        let evaluate = |subject, transaction_id| {
+            futures::executor::block_on(engine.evaluate(
+                EventKey::new("cart", "item", "read"),
+                json!({ "subject": { "id": subject }, "input": [2] }),
+                transaction_id,
+            ))
+        };
+        assert!(evaluate("dave", Some(&7)).is_ok());
+        assert!(matches!(evaluate("carol", Some(&7)), Err(RebacError::Denied(_))));
+        assert!(evaluate("carol", None).is_ok());
+
+        engine.store().commit(&7);
+        assert!(evaluate("dave", None).is_ok());
+        assert!(matches!(
+            futures::executor::block_on(engine.evaluate::<u32>(
+                EventKey::new("cart", "item", "delete"),
+                json!({ "subject": "dave", "input": [2] }),
+                None,
+            )),
+            Err(RebacError::UnknownRelation { .. }),
+        ));
+    }
+
+    #[test]
+    fn discards_scoped_changes_when_dropped() {
+        let pending = TransactionTuples::<u32>::new();
+        let owners = || {
+            let mut subjects = vec![];
+            pending.apply(Some(&7), &ObjectRef::new("cart.cart", "3"), "owner", &mut subjects);
+            subjects.iter().map(ToString::to_string).collect::<Vec<_>>()
+        };
+        let outer = pending.stage_scoped(7, [TupleChange::Write("cart.cart:3#owner@alice".parse().unwrap())]);
+        let inner = pending.stage_scoped(7, [TupleChange::Write("cart.cart:3#owner@bob".parse().unwrap())]);
+        assert_eq!(owners(), ["alice", "bob"]);
+
+        drop(inner);
+        assert_eq!(owners(), ["alice"]);
+
+        assert_eq!(outer.take().len(), 1);
+        assert!(owners().is_empty());
+    }
+}
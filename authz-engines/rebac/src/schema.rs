@@ -0,0 +1,309 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// The namespaces known to a [`RebacEngine`](crate::RebacEngine) and how each of their relations is computed.
+///
+/// Schemas are written in a small DSL, where each relation is the union of one or more usersets
+/// ```text
+/// // objects of type `item` in service `cart`
+/// namespace cart.item {
+///     relation parent
+///     relation owner
+///     relation editor = this | owner | parent->editor
+///     relation viewer = this | editor
+/// }
+/// ```
+/// - `this`: subjects related to the object directly by a tuple of this relation, e.g. `cart.item:1#editor@3`;
+///   a relation without an `=` is equivalent to `= this`
+/// - `{relation}`: subjects with another relation to the same object, e.g. owners of an item are also its editors
+/// - `{tupleset}->{relation}`: subjects with `relation` to any object related to this one by `tupleset`, e.g.
+///   editors of an item's parent (as stated by `cart.item:1#parent@cart.cart:7`) are also editors of the item
+///
+/// Line comments start with `//`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Schema {
+    namespaces: BTreeMap<String, Namespace>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Namespace {
+    /// the usersets whose union makes up each relation
+    pub relations: BTreeMap<String, Vec<Userset>>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Userset {
+    /// subjects related to the object by tuples of the relation being computed
+    This,
+    /// subjects with another relation to the same object
+    Computed(String),
+    /// subjects with relation `computed` to the objects related to this object by `tupleset`
+    TupleToUserset { tupleset: String, computed: String },
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("invalid schema at line {line}: {message}")]
+pub struct SchemaError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl Schema {
+    pub fn namespace(&self, name: &str) -> Option<&Namespace> {
+        self.namespaces.get(name)
+    }
+
+    pub fn namespaces(&self) -> impl Iterator<Item = (&str, &Namespace)> {
+        self.namespaces.iter().map(|(name, namespace)| (&**name, namespace))
+    }
+
+    /// The usersets making up a relation, or `None` if either the namespace or relation is not defined.
+    pub fn relation(&self, namespace: &str, relation: &str) -> Option<&[Userset]> {
+        self.namespace(namespace)?
+            .relations
+            .get(relation)
+            .map(|usersets| &**usersets)
+    }
+}
+
+impl FromStr for Schema {
+    type Err = SchemaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+        }
+        .schema()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Token<'a> {
+    Name(&'a str),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 5] = ["->", "{", "}", "=", "|"];
+
+fn tokenize(source: &str) -> Result<Vec<(usize, Token<'_>)>, SchemaError> {
+    let mut tokens = vec![];
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let mut rest = line.split_once("//").map(|(line, _)| line).unwrap_or(line).trim_start();
+        while !rest.is_empty() {
+            if let Some(symbol) = SYMBOLS.into_iter().find(|symbol| rest.starts_with(symbol)) {
+                tokens.push((line_number, Token::Symbol(symbol)));
+                rest = &rest[symbol.len()..];
+            } else {
+                let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+                if end == 0 {
+                    return Err(SchemaError {
+                        line: line_number,
+                        message: format!("unexpected character `{}`", rest.chars().next().unwrap_or_default()),
+                    });
+                }
+                tokens.push((line_number, Token::Name(&rest[..end])));
+                rest = &rest[end..];
+            }
+            rest = rest.trim_start();
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+struct Parser<'a> {
+    tokens: Vec<(usize, Token<'a>)>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn schema(mut self) -> Result<Schema, SchemaError> {
+        let mut schema = Schema::default();
+        while self.peek().is_some() {
+            self.keyword("namespace")?;
+            let line = self.line();
+            let name = self.name()?;
+            let namespace = self.namespace()?;
+            if schema.namespaces.insert(name.to_string(), namespace).is_some() {
+                return Err(SchemaError {
+                    line,
+                    message: format!("namespace `{name}` is defined more than once"),
+                });
+            }
+        }
+        Ok(schema)
+    }
+
+    fn namespace(&mut self) -> Result<Namespace, SchemaError> {
+        let mut namespace = Namespace::default();
+        let mut references = vec![];
+        self.symbol("{")?;
+        while self.peek() != Some(Token::Symbol("}")) {
+            self.keyword("relation")?;
+            let line = self.line();
+            let name = self.name()?;
+            let usersets = match self.peek() {
+                Some(Token::Symbol("=")) => {
+                    self.position += 1;
+                    self.usersets()?
+                }
+                _ => vec![Userset::This],
+            };
+            for userset in &usersets {
+                match userset {
+                    Userset::This => {}
+                    Userset::Computed(relation) => references.push((line, relation.clone())),
+                    Userset::TupleToUserset { tupleset, .. } => references.push((line, tupleset.clone())),
+                }
+            }
+            if namespace.relations.insert(name.to_string(), usersets).is_some() {
+                return Err(SchemaError {
+                    line,
+                    message: format!("relation `{name}` is defined more than once"),
+                });
+            }
+        }
+        self.symbol("}")?;
+
+        // relations computed from tuples of another namespace may be undefined there, but relations of
+        // this namespace must be defined here
+        if let Some((line, relation)) = references
+            .into_iter()
+            .find(|(_, relation)| !namespace.relations.contains_key(relation))
+        {
+            return Err(SchemaError {
+                line,
+                message: format!("relation `{relation}` is not defined"),
+            });
+        }
+        Ok(namespace)
+    }
+
+    fn usersets(&mut self) -> Result<Vec<Userset>, SchemaError> {
+        let mut usersets = vec![self.userset()?];
+        while self.peek() == Some(Token::Symbol("|")) {
+            self.position += 1;
+            usersets.push(self.userset()?);
+        }
+        Ok(usersets)
+    }
+
+    fn userset(&mut self) -> Result<Userset, SchemaError> {
+        let name = self.name()?;
+        if name == "this" {
+            return Ok(Userset::This);
+        }
+        if self.peek() != Some(Token::Symbol("->")) {
+            return Ok(Userset::Computed(name.to_string()));
+        }
+        self.position += 1;
+        Ok(Userset::TupleToUserset {
+            tupleset: name.to_string(),
+            computed: self.name()?.to_string(),
+        })
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).map(|(_, token)| *token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or_else(|| self.tokens.last())
+            .map(|(line, _)| *line)
+            .unwrap_or(1)
+    }
+
+    fn next(&mut self, expected: &str) -> Result<Token<'a>, SchemaError> {
+        let token = self.peek().ok_or_else(|| SchemaError {
+            line: self.line(),
+            message: format!("expected {expected}, found end of schema"),
+        })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn name(&mut self) -> Result<&'a str, SchemaError> {
+        let line = self.line();
+        match self.next("a name")? {
+            Token::Name(name) => Ok(name),
+            Token::Symbol(symbol) => Err(SchemaError {
+                line,
+                message: format!("expected a name, found `{symbol}`"),
+            }),
+        }
+    }
+
+    fn keyword(&mut self, keyword: &str) -> Result<(), SchemaError> {
+        let line = self.line();
+        match self.next(&format!("`{keyword}`"))? {
+            Token::Name(name) if name == keyword => Ok(()),
+            Token::Name(found) | Token::Symbol(found) => Err(SchemaError {
+                line,
+                message: format!("expected `{keyword}`, found `{found}`"),
+            }),
+        }
+    }
+
+    fn symbol(&mut self, symbol: &str) -> Result<(), SchemaError> {
+        let line = self.line();
+        match self.next(&format!("`{symbol}`"))? {
+            Token::Symbol(found) if found == symbol => Ok(()),
+            Token::Name(found) | Token::Symbol(found) => Err(SchemaError {
+                line,
+                message: format!("expected `{symbol}`, found `{found}`"),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_schemas() {
+        let schema: Schema = r#"
+            namespace cart.item {
+                relation parent // the cart containing this item
+                relation editor = this | parent->editor
+                relation viewer = this | editor
+            }
+        "#
+        .parse()
+        .unwrap();
+        assert_eq!(schema.relation("cart.item", "parent"), Some(&[Userset::This][..]));
+        assert_eq!(
+            schema.relation("cart.item", "editor"),
+            Some(
+                &[
+                    Userset::This,
+                    Userset::TupleToUserset {
+                        tupleset: "parent".into(),
+                        computed: "editor".into()
+                    }
+                ][..]
+            ),
+        );
+        assert_eq!(
+            schema.relation("cart.item", "viewer"),
+            Some(&[Userset::This, Userset::Computed("editor".into())][..]),
+        );
+        assert_eq!(schema.relation("cart.cart", "editor"), None);
+    }
+
+    #[test]
+    fn rejects_invalid_schemas() {
+        let line = |schema: &str| schema.parse::<Schema>().unwrap_err().line;
+        assert_eq!(line("namespace a {\n relation b = c\n}"), 2);
+        assert_eq!(line("namespace a {\n relation b\n relation b\n}"), 3);
+        assert_eq!(line("namespace a {\n relation b =\n}"), 3);
+        assert_eq!(line("namespace a {\n relation b = this |\n"), 2);
+    }
+}
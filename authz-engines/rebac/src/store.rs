@@ -0,0 +1,330 @@
+use crate::{ObjectRef, RelationTuple, Subject, TupleChange};
+use std::collections::{BTreeSet, HashMap};
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, RwLock};
+
+/// Error returned from a [`TupleStore`] which was unable to read tuples.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("unable to read relation tuples: {0}")]
+pub struct TupleStoreError(pub Arc<dyn std::error::Error + Send + Sync>);
+
+impl TupleStoreError {
+    pub fn new(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self(Arc::from(err.into()))
+    }
+}
+
+/// Storage of the relation tuples read by a [`RebacEngine`](crate::RebacEngine).
+///
+/// Reads made with a transaction id should include the tuples written and exclude the tuples
+/// deleted within that transaction, so that events are authorized against the relations they will
+/// have once the transaction is committed (see [`TransactionTuples`]).
+#[async_trait]
+pub trait TupleStore<TransactionId>: Send + Sync {
+    /// Returns the subjects of all tuples with the provided object and relation.
+    async fn read(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<Subject>, TupleStoreError>;
+
+    /// Returns the ids of all objects in a namespace which are the object of at least one tuple.
+    /// Used by [`RebacEngine::lookup_resources`](crate::RebacEngine::lookup_resources) to find candidate objects.
+    async fn object_ids(
+        &self,
+        namespace: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<String>, TupleStoreError>;
+}
+
+/// Changes made to the tuples of a [`TupleStore`] within transactions which have not yet been committed,
+/// keyed by transaction id.
+///
+/// Cloning shares the same changes.
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = "TransactionId: std::fmt::Debug"),
+    Default(bound = "")
+)]
+pub struct TransactionTuples<TransactionId> {
+    changes: Arc<Mutex<Changes<TransactionId>>>,
+}
+
+/// The changes staged within each transaction, each tagged with an id so that the changes staged by a
+/// [`StagedTuples`] can be removed without removing other changes staged within the same transaction.
+#[derive(Derivative)]
+#[derivative(Debug(bound = "TransactionId: std::fmt::Debug"), Default(bound = ""))]
+struct Changes<TransactionId> {
+    next_id: u64,
+    staged: HashMap<TransactionId, Vec<(u64, Vec<TupleChange>)>>,
+}
+
+impl<TransactionId: Eq + Hash> TransactionTuples<TransactionId> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records changes made within a transaction.
+    pub fn stage(&self, transaction_id: TransactionId, changes: impl IntoIterator<Item = TupleChange>) {
+        self.push(transaction_id, changes);
+    }
+
+    /// Records changes made within a transaction until the returned [`StagedTuples`] is either
+    /// [taken](StagedTuples::take) or dropped, e.g. when the transaction is committed or rolled back.
+    pub fn stage_scoped(
+        &self,
+        transaction_id: TransactionId,
+        changes: impl IntoIterator<Item = TupleChange>,
+    ) -> StagedTuples<TransactionId>
+    where
+        TransactionId: Clone,
+    {
+        let id = self.push(transaction_id.clone(), changes);
+        StagedTuples {
+            tuples: self.clone(),
+            staged: Some((transaction_id, id)),
+        }
+    }
+
+    /// Removes and returns the changes made within a transaction, e.g. once it is committed or rolled back.
+    pub fn take(&self, transaction_id: &TransactionId) -> Vec<TupleChange> {
+        let staged = self.lock().staged.remove(transaction_id).unwrap_or_default();
+        staged.into_iter().flat_map(|(_, changes)| changes).collect()
+    }
+
+    /// Applies the changes made within a transaction to the subjects read for an object and relation.
+    pub fn apply(
+        &self,
+        transaction_id: Option<&TransactionId>,
+        object: &ObjectRef,
+        relation: &str,
+        subjects: &mut Vec<Subject>,
+    ) {
+        let Some(transaction_id) = transaction_id else { return };
+        let changes = self.lock();
+        for change in changes.changes(transaction_id) {
+            let (TupleChange::Write(tuple) | TupleChange::Delete(tuple)) = change;
+            if tuple.object != *object || tuple.relation != relation {
+                continue;
+            }
+            subjects.retain(|subject| *subject != tuple.subject);
+            if let TupleChange::Write(tuple) = change {
+                subjects.push(tuple.subject.clone());
+            }
+        }
+    }
+
+    /// Adds the ids of objects in a namespace written within a transaction.
+    pub fn extend_object_ids(&self, transaction_id: Option<&TransactionId>, namespace: &str, ids: &mut Vec<String>) {
+        let Some(transaction_id) = transaction_id else { return };
+        let changes = self.lock();
+        for change in changes.changes(transaction_id) {
+            if let TupleChange::Write(tuple) = change {
+                if tuple.object.namespace == namespace && !ids.contains(&tuple.object.id) {
+                    ids.push(tuple.object.id.clone());
+                }
+            }
+        }
+    }
+
+    fn push(&self, transaction_id: TransactionId, changes: impl IntoIterator<Item = TupleChange>) -> u64 {
+        let mut all_changes = self.lock();
+        let id = all_changes.next_id;
+        all_changes.next_id += 1;
+        all_changes
+            .staged
+            .entry(transaction_id)
+            .or_default()
+            .push((id, changes.into_iter().collect()));
+        id
+    }
+
+    fn remove(&self, transaction_id: &TransactionId, id: u64) -> Vec<TupleChange> {
+        let mut all_changes = self.lock();
+        let Some(staged) = all_changes.staged.get_mut(transaction_id) else {
+            return vec![];
+        };
+        let changes = match staged.iter().position(|(staged_id, _)| *staged_id == id) {
+            Some(index) => staged.remove(index).1,
+            None => vec![],
+        };
+        if staged.is_empty() {
+            all_changes.staged.remove(transaction_id);
+        }
+        changes
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Changes<TransactionId>> {
+        self.changes.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<TransactionId: Eq + Hash> Changes<TransactionId> {
+    fn changes(&self, transaction_id: &TransactionId) -> impl Iterator<Item = &TupleChange> {
+        self.staged
+            .get(transaction_id)
+            .into_iter()
+            .flatten()
+            .flat_map(|(_, changes)| changes)
+    }
+}
+
+/// Changes staged within a transaction with [`TransactionTuples::stage_scoped`], which are discarded
+/// when dropped unless they have been [taken](StagedTuples::take) first.
+///
+/// Capturing a `StagedTuples` in a function which only runs once its transaction is committed, e.g. one
+/// registered with authzen's `TransactionalDataSource::tx_commit`, ensures that the changes staged within
+/// a transaction which is rolled back do not outlive it.
+#[derive(Derivative)]
+#[derivative(Debug(bound = "TransactionId: std::fmt::Debug"))]
+#[must_use = "staged changes are discarded when dropped"]
+pub struct StagedTuples<TransactionId: Eq + Hash> {
+    tuples: TransactionTuples<TransactionId>,
+    staged: Option<(TransactionId, u64)>,
+}
+
+impl<TransactionId: Eq + Hash> StagedTuples<TransactionId> {
+    /// Removes and returns the staged changes, e.g. to apply them once their transaction is committed.
+    pub fn take(mut self) -> Vec<TupleChange> {
+        match self.staged.take() {
+            Some((transaction_id, id)) => self.tuples.remove(&transaction_id, id),
+            None => vec![],
+        }
+    }
+}
+
+impl<TransactionId: Eq + Hash> Drop for StagedTuples<TransactionId> {
+    fn drop(&mut self) {
+        if let Some((transaction_id, id)) = self.staged.take() {
+            self.tuples.remove(&transaction_id, id);
+        }
+    }
+}
+
+/// A [`TupleStore`] which keeps its tuples in memory, e.g. for tests or for relations which are
+/// loaded from elsewhere on startup.
+///
+/// Changes written with a transaction id are only visible to reads made with that transaction id
+/// until they are [committed](InMemoryTupleStore::commit) or, if written with
+/// [`InMemoryTupleStore::write_scoped`], until they are [committed](InMemoryTupleStore::commit_staged)
+/// or dropped. Cloning shares the same tuples.
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = ""),
+    Debug(bound = "TransactionId: std::fmt::Debug"),
+    Default(bound = "")
+)]
+pub struct InMemoryTupleStore<TransactionId = ()> {
+    tuples: Arc<RwLock<BTreeSet<RelationTuple>>>,
+    pending: TransactionTuples<TransactionId>,
+}
+
+impl<TransactionId: Eq + Hash> InMemoryTupleStore<TransactionId> {
+    pub fn new(tuples: impl IntoIterator<Item = RelationTuple>) -> Self {
+        Self {
+            tuples: Arc::new(RwLock::new(tuples.into_iter().collect())),
+            pending: Default::default(),
+        }
+    }
+
+    /// Applies changes immediately if `transaction_id` is `None`, otherwise stages them until the
+    /// transaction is committed.
+    pub fn write(&self, changes: impl IntoIterator<Item = TupleChange>, transaction_id: Option<TransactionId>) {
+        match transaction_id {
+            Some(transaction_id) => self.pending.stage(transaction_id, changes),
+            None => self.apply(changes),
+        }
+    }
+
+    /// Applies the changes staged within a transaction.
+    pub fn commit(&self, transaction_id: &TransactionId) {
+        self.apply(self.pending.take(transaction_id));
+    }
+
+    /// Discards the changes staged within a transaction.
+    pub fn rollback(&self, transaction_id: &TransactionId) {
+        self.pending.take(transaction_id);
+    }
+
+    /// Stages changes within a transaction until the returned [`StagedTuples`] is either
+    /// [committed](InMemoryTupleStore::commit_staged) or dropped.
+    pub fn write_scoped(
+        &self,
+        changes: impl IntoIterator<Item = TupleChange>,
+        transaction_id: TransactionId,
+    ) -> StagedTuples<TransactionId>
+    where
+        TransactionId: Clone,
+    {
+        self.pending.stage_scoped(transaction_id, changes)
+    }
+
+    /// Applies changes staged with [`InMemoryTupleStore::write_scoped`].
+    pub fn commit_staged(&self, staged: StagedTuples<TransactionId>) {
+        self.apply(staged.take());
+    }
+
+    /// All committed tuples.
+    pub fn tuples(&self) -> Vec<RelationTuple> {
+        self.tuples
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    fn apply(&self, changes: impl IntoIterator<Item = TupleChange>) {
+        let mut tuples = self.tuples.write().unwrap_or_else(|err| err.into_inner());
+        for change in changes {
+            match change {
+                TupleChange::Write(tuple) => tuples.insert(tuple),
+                TupleChange::Delete(tuple) => tuples.remove(&tuple),
+            };
+        }
+    }
+}
+
+#[async_trait]
+impl<TransactionId: Eq + Hash + Send + Sync> TupleStore<TransactionId> for InMemoryTupleStore<TransactionId> {
+    async fn read(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<Subject>, TupleStoreError> {
+        let start = RelationTuple::new(object.clone(), relation, Subject::id(""));
+        let mut subjects = self
+            .tuples
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .range(start..)
+            .take_while(|tuple| tuple.object == *object && tuple.relation == relation)
+            .map(|tuple| tuple.subject.clone())
+            .collect();
+        self.pending.apply(transaction_id, object, relation, &mut subjects);
+        Ok(subjects)
+    }
+
+    async fn object_ids(
+        &self,
+        namespace: &str,
+        transaction_id: Option<&TransactionId>,
+    ) -> Result<Vec<String>, TupleStoreError> {
+        let start = RelationTuple::new(ObjectRef::new(namespace, ""), "", Subject::id(""));
+        let mut ids = self
+            .tuples
+            .read()
+            .unwrap_or_else(|err| err.into_inner())
+            .range(start..)
+            .take_while(|tuple| tuple.object.namespace == namespace)
+            .map(|tuple| tuple.object.id.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        self.pending.extend_object_ids(transaction_id, namespace, &mut ids);
+        Ok(ids)
+    }
+}
@@ -0,0 +1,177 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// An object in a namespace, written `{namespace}:{id}`, e.g. `cart.item:42`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct ObjectRef {
+    pub namespace: String,
+    pub id: String,
+}
+
+/// The set of subjects which have a relation to an object, written `{namespace}:{id}#{relation}`.
+///
+/// Subject sets without a relation refer to the object itself and are written `{namespace}:{id}`;
+/// they are the subjects of tuples read by tuple-to-userset rewrites, e.g. `cart.item:42#parent@cart.cart:7`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct SubjectSet {
+    pub object: ObjectRef,
+    pub relation: Option<String>,
+}
+
+/// The subject of a relation tuple: either a subject id (e.g. an account id) or a set of subjects.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Subject {
+    Id(String),
+    Set(SubjectSet),
+}
+
+/// States that `subject` has `relation` to `object`, written `{object}#{relation}@{subject}`,
+/// e.g. `cart.item:42#editor@3` or `cart.item:42#viewer@cart.cart:7#editor`.
+///
+/// Namespaces, ids and relations cannot contain `:`, `#` or `@`.
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RelationTuple {
+    pub object: ObjectRef,
+    pub relation: String,
+    pub subject: Subject,
+}
+
+/// A change to the tuples of a [`TupleStore`](crate::TupleStore).
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum TupleChange {
+    Write(RelationTuple),
+    Delete(RelationTuple),
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("invalid {kind} `{value}`")]
+pub struct ParseTupleError {
+    pub kind: &'static str,
+    pub value: String,
+}
+
+impl ObjectRef {
+    pub fn new(namespace: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            namespace: namespace.into(),
+            id: id.into(),
+        }
+    }
+}
+
+impl Subject {
+    pub fn id(id: impl Into<String>) -> Self {
+        Self::Id(id.into())
+    }
+
+    pub fn set(object: ObjectRef, relation: impl Into<String>) -> Self {
+        Self::Set(SubjectSet {
+            object,
+            relation: Some(relation.into()),
+        })
+    }
+
+    pub fn object(object: ObjectRef) -> Self {
+        Self::Set(SubjectSet { object, relation: None })
+    }
+}
+
+impl RelationTuple {
+    pub fn new(object: ObjectRef, relation: impl Into<String>, subject: Subject) -> Self {
+        Self {
+            object,
+            relation: relation.into(),
+            subject,
+        }
+    }
+}
+
+impl fmt::Display for ObjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.namespace, self.id)
+    }
+}
+
+impl fmt::Display for SubjectSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.relation {
+            Some(relation) => write!(f, "{}#{relation}", self.object),
+            None => write!(f, "{}", self.object),
+        }
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Id(id) => write!(f, "{id}"),
+            Self::Set(set) => write!(f, "{set}"),
+        }
+    }
+}
+
+impl fmt::Display for RelationTuple {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}#{}@{}", self.object, self.relation, self.subject)
+    }
+}
+
+impl FromStr for ObjectRef {
+    type Err = ParseTupleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((namespace, id)) if is_name(namespace) && is_name(id) => Ok(Self::new(namespace, id)),
+            _ => Err(ParseTupleError::new("object", s)),
+        }
+    }
+}
+
+impl FromStr for Subject {
+    type Err = ParseTupleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if !s.contains(':') {
+            return match is_name(s) {
+                true => Ok(Self::id(s)),
+                false => Err(ParseTupleError::new("subject", s)),
+            };
+        }
+        let (object, relation) = match s.split_once('#') {
+            Some((object, relation)) if is_name(relation) => (object, Some(relation.to_string())),
+            Some(_) => return Err(ParseTupleError::new("subject", s)),
+            None => (s, None),
+        };
+        Ok(Self::Set(SubjectSet {
+            object: object.parse()?,
+            relation,
+        }))
+    }
+}
+
+impl FromStr for RelationTuple {
+    type Err = ParseTupleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseTupleError::new("relation tuple", s);
+        let (object, rest) = s.split_once('#').ok_or_else(invalid)?;
+        let (relation, subject) = rest.split_once('@').ok_or_else(invalid)?;
+        if !is_name(relation) {
+            return Err(invalid());
+        }
+        Ok(Self::new(object.parse()?, relation, subject.parse()?))
+    }
+}
+
+impl ParseTupleError {
+    fn new(kind: &'static str, value: &str) -> Self {
+        Self {
+            kind,
+            value: value.to_string(),
+        }
+    }
+}
+
+fn is_name(s: &str) -> bool {
+    !s.is_empty() && !s.contains([':', '#', '@'])
+}
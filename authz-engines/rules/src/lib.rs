@@ -0,0 +1,159 @@
+#![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+
+#[macro_use]
+extern crate derivative;
+#[macro_use]
+extern crate derive_more;
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Identifies the rules which apply to a specific action performed on a specific object type.
+/// Each field corresponds to the respective `SERVICE` / `TYPE` constants found on
+/// `ObjectType` and `ActionType` implementations.
+#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq, Serialize)]
+#[display(fmt = "{service}.{ty}:{action}")]
+pub struct RuleKey {
+    pub service: &'static str,
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    pub action: &'static str,
+}
+
+impl RuleKey {
+    pub fn new(service: &'static str, ty: &'static str, action: &'static str) -> Self {
+        Self { service, ty, action }
+    }
+}
+
+/// A single policy rule, evaluated against the json representation of an event, i.e.
+/// ```json
+/// {
+///   "subject": ...,
+///   "action": "<action-type>",
+///   "object": { "service": "<object-service>", "type": "<object-type>" },
+///   "input": ...,
+///   "context": ...,
+///   "transaction_id": ...
+/// }
+/// ```
+/// which is the same structure provided as input to OPA policies.
+pub type Rule = Arc<dyn Fn(&Value) -> bool + Send + Sync>;
+
+/// An in-process authorization engine composed of rust closures.
+///
+/// Rules are registered per object service, object type and action type. An event is allowed if
+/// *any* of the rules registered for its key accept it; events for which no rules are registered
+/// are always rejected.
+#[derive(Clone, Default, Derivative)]
+#[derivative(Debug)]
+pub struct RulesEngine {
+    #[derivative(Debug(format_with = "fmt_rules"))]
+    rules: Arc<HashMap<RuleKey, Vec<Rule>>>,
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum RulesError {
+    #[error("event denied by rules registered for `{0}`")]
+    Denied(RuleKey),
+    #[error("no rules registered for `{0}`")]
+    Unregistered(RuleKey),
+    #[error("unable to serialize event: {0}")]
+    Serialization(Arc<serde_json::Error>),
+}
+
+impl RulesEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers an additional rule for the provided key.
+    pub fn rule(
+        mut self,
+        service: &'static str,
+        ty: &'static str,
+        action: &'static str,
+        rule: impl Fn(&Value) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.register(RuleKey::new(service, ty, action), rule);
+        self
+    }
+
+    /// Registers an additional rule for the provided key.
+    pub fn register(&mut self, key: RuleKey, rule: impl Fn(&Value) -> bool + Send + Sync + 'static) {
+        Arc::make_mut(&mut self.rules)
+            .entry(key)
+            .or_default()
+            .push(Arc::new(rule));
+    }
+
+    /// Returns true if at least one rule has been registered for the provided key.
+    pub fn contains(&self, key: &RuleKey) -> bool {
+        self.rules.contains_key(key)
+    }
+
+    /// Evaluates the rules registered for `key` against an already serialized event.
+    pub fn evaluate(&self, key: RuleKey, event: &Value) -> Result<(), RulesError> {
+        let rules = self.rules.get(&key).ok_or(RulesError::Unregistered(key))?;
+        if rules.iter().any(|rule| rule(event)) {
+            Ok(())
+        } else {
+            Err(RulesError::Denied(key))
+        }
+    }
+
+    /// Serializes `event` and then evaluates the rules registered for `key` against it.
+    pub fn evaluate_serialize(&self, key: RuleKey, event: &impl Serialize) -> Result<(), RulesError> {
+        if !self.contains(&key) {
+            return Err(RulesError::Unregistered(key));
+        }
+        let event = serde_json::to_value(event).map_err(|err| RulesError::Serialization(Arc::new(err)))?;
+        self.evaluate(key, &event)
+    }
+}
+
+fn fmt_rules(rules: &Arc<HashMap<RuleKey, Vec<Rule>>>, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+    f.debug_map()
+        .entries(rules.iter().map(|(key, rules)| (key.to_string(), rules.len())))
+        .finish()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn engine() -> RulesEngine {
+        RulesEngine::new()
+            .rule("cart", "item", "create", |event| event["subject"] == "admin")
+            .rule("cart", "item", "create", |event| {
+                event["input"]["owner"] == event["subject"]
+            })
+    }
+
+    #[test]
+    fn allows_if_any_rule_matches() {
+        let key = RuleKey::new("cart", "item", "create");
+        assert!(engine()
+            .evaluate(key, &json!({ "subject": "admin", "input": {} }))
+            .is_ok());
+        assert!(engine()
+            .evaluate(key, &json!({ "subject": "a", "input": { "owner": "a" } }))
+            .is_ok());
+        assert!(matches!(
+            engine().evaluate(key, &json!({ "subject": "a", "input": { "owner": "b" } })),
+            Err(RulesError::Denied(_)),
+        ));
+    }
+
+    #[test]
+    fn denies_unregistered_keys() {
+        let key = RuleKey::new("cart", "item", "delete");
+        assert!(matches!(
+            engine().evaluate(key, &json!({ "subject": "admin" })),
+            Err(RulesError::Unregistered(_)),
+        ));
+    }
+}
@@ -0,0 +1,3 @@
+fn main() -> Result<(), serde_json::Error> {
+    authzen::cli::run()
+}
@@ -0,0 +1,47 @@
+use crate::registry::Registry;
+use clap::{Parser, Subcommand};
+
+#[derive(Clone, Debug, Parser)]
+#[clap(author, version, about, long_about = None)]
+pub struct Args {
+    #[clap(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// print the object and action types registered in this binary as json
+    Dump {
+        /// pretty print the registry
+        #[clap(long)]
+        pretty: bool,
+    },
+}
+
+/// Runs the `authzen` command line interface against the object and action types linked into the
+/// running binary (see [`Registry`]).
+///
+/// Objects and actions are only registered in binaries which link the crates defining them, so
+/// applications should provide their own `authzen` binary which references their library crate.
+/// ```rs
+/// // src/bin/authzen.rs
+/// use my_service as _;
+///
+/// fn main() -> Result<(), serde_json::Error> {
+///     authzen::cli::run()
+/// }
+/// ```
+pub fn run() -> Result<(), serde_json::Error> {
+    match Args::parse().command {
+        Command::Dump { pretty } => {
+            let registry = Registry::collect();
+            let json = if pretty {
+                serde_json::to_string_pretty(&registry)?
+            } else {
+                serde_json::to_string(&registry)?
+            };
+            println!("{json}");
+        }
+    }
+    Ok(())
+}
@@ -7,6 +7,10 @@ pub use authzen_proc_macros::*;
 
 pub use authzen_proc_macros as proc_macros;
 
+/// Command line interface for inspecting the authorization types defined by an application.
+#[cfg(feature = "cli")]
+pub mod cli;
+
 #[cfg(feature = "proc-macro-util")]
 #[doc(alias = "authzen_proc_macro_util")]
 pub use authzen_proc_macro_util as proc_macro_util;
@@ -21,9 +25,29 @@ pub use authzen_session as session;
 
 /// Implementations of common authorization engine clients.
 pub mod authz_engines {
+    #[cfg(feature = "cedar-authz-engine")]
+    #[doc(alias = "authzen_cedar")]
+    pub use authzen_cedar as cedar;
+
     #[cfg(feature = "opa-authz-engine")]
     #[doc(alias = "authzen_opa")]
     pub use authzen_opa as opa;
+
+    #[cfg(feature = "oso-authz-engine")]
+    #[doc(alias = "authzen_oso")]
+    pub use authzen_oso as oso;
+
+    #[cfg(feature = "rbac-authz-engine")]
+    #[doc(alias = "authzen_rbac")]
+    pub use authzen_rbac as rbac;
+
+    #[cfg(feature = "rebac-authz-engine")]
+    #[doc(alias = "authzen_rebac")]
+    pub use authzen_rebac as rebac;
+
+    #[cfg(feature = "rules-authz-engine")]
+    #[doc(alias = "authzen_rules")]
+    pub use authzen_rules as rules;
 }
 
 /// Implementations of common data source clients.
@@ -0,0 +1,468 @@
+use crate::{
+    ActionType, AuthzEngine, AuthzFilter, BatchAuthzEngine, BatchEvent, Event, ObjectType, PartialAuthzEngine, Tenant,
+};
+use ::derivative::Derivative;
+use ::serde::Serialize;
+use ::std::any::Any;
+use ::std::collections::{BTreeMap, HashMap, HashSet};
+use ::std::sync::{Arc, Mutex};
+use ::std::time::{Duration, Instant};
+
+/// Wraps an [`AuthzEngine`], memoizing the successful outcomes of `can_act` so that identical
+/// checks made in quick succession do not each reach the underlying engine.
+///
+/// Entries are keyed by the serialized subject, action type, object type, input, context and tenant.
+/// Only `Ok` outcomes are cached, errors (including denials) are always recomputed.
+/// Calls made with a transaction id always bypass the cache, since the outcome may depend on
+/// uncommitted changes which are only visible within that transaction.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct CachedEngine<AE> {
+    engine: AE,
+    ttl: Duration,
+    max_entries: usize,
+    uncached_actions: HashSet<&'static str>,
+    #[derivative(Debug = "ignore")]
+    cache: Arc<Mutex<Cache>>,
+}
+
+/// Outcomes of authorization checks keyed by their serialized events, see [`cache_key`].
+///
+/// Keys are compared in full on lookup, so distinct events whose keys happen to hash
+/// identically never share an outcome.
+#[derive(Default)]
+pub(crate) struct Cache {
+    entries: HashMap<Arc<[u8]>, CacheEntry>,
+    /// keys of `entries` ordered by when they were inserted, so that the oldest entries
+    /// can be evicted without scanning all of `entries`
+    insertion_order: BTreeMap<u64, Arc<[u8]>>,
+    next_sequence: u64,
+}
+
+struct CacheEntry {
+    inserted_at: Instant,
+    sequence: u64,
+    ok: Box<dyn Any + Send + Sync>,
+}
+
+#[derive(Serialize)]
+struct CacheKey<'a, Subject, Input, Context> {
+    subject: &'a Subject,
+    action: &'static str,
+    service: &'static str,
+    #[serde(rename = "type")]
+    ty: &'static str,
+    input: &'a Input,
+    context: &'a Context,
+    tenant: Option<&'a Tenant>,
+}
+
+/// Serializes the subject, action type, object type, input, context and tenant of an event.
+pub(crate) fn cache_key<Subject, Action, Object, Input, Context>(
+    subject: &Subject,
+    input: &Input,
+    context: &Context,
+    tenant: Option<&Tenant>,
+) -> Option<Vec<u8>>
+where
+    Subject: Serialize,
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+    Input: Serialize,
+    Context: Serialize,
+{
+    serde_json::to_vec(&CacheKey {
+        subject,
+        action: Action::TYPE,
+        service: Object::SERVICE,
+        ty: Object::TYPE,
+        input,
+        context,
+        tenant,
+    })
+    .ok()
+}
+
+impl Cache {
+    pub(crate) fn get<T: Clone + 'static>(&mut self, key: &[u8], ttl: Duration) -> Option<T> {
+        let entry = self.entries.get(key)?;
+        if entry.inserted_at.elapsed() >= ttl {
+            self.remove(key);
+            return None;
+        }
+        entry.ok.downcast_ref::<T>().cloned()
+    }
+
+    /// Inserts an outcome, first evicting expired entries and then the oldest entries until there is room for it.
+    /// Since entries are evicted in insertion order, each eviction takes logarithmic time.
+    pub(crate) fn insert<T: Send + Sync + 'static>(&mut self, key: &[u8], ok: T, ttl: Duration, max_entries: usize) {
+        self.remove(key);
+        while let Some((_, oldest)) = self.insertion_order.first_key_value() {
+            let is_expired = self
+                .entries
+                .get(oldest)
+                .is_none_or(|entry| entry.inserted_at.elapsed() >= ttl);
+            if !is_expired && self.entries.len() < max_entries {
+                break;
+            }
+            if let Some((_, oldest)) = self.insertion_order.pop_first() {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        let key: Arc<[u8]> = key.into();
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.insertion_order.insert(sequence, key.clone());
+        self.entries.insert(
+            key,
+            CacheEntry {
+                inserted_at: Instant::now(),
+                sequence,
+                ok: Box::new(ok),
+            },
+        );
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.insertion_order.clear();
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.insertion_order.remove(&entry.sequence);
+        }
+    }
+}
+
+impl<AE> CachedEngine<AE> {
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(30);
+    pub const DEFAULT_MAX_ENTRIES: usize = 10_000;
+
+    pub fn new(engine: AE) -> Self {
+        Self {
+            engine,
+            ttl: Self::DEFAULT_TTL,
+            max_entries: Self::DEFAULT_MAX_ENTRIES,
+            uncached_actions: Default::default(),
+            cache: Default::default(),
+        }
+    }
+
+    /// How long a cached outcome remains valid for.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// The maximum number of outcomes held at once; once reached, the oldest entries are dropped.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Opts action `A` out of caching, all checks for it will be sent to the underlying engine.
+    pub fn uncached<A: ?Sized + ActionType>(mut self) -> Self {
+        self.uncached_actions.insert(A::TYPE);
+        self
+    }
+
+    /// The wrapped authorization engine.
+    pub fn engine(&self) -> &AE {
+        &self.engine
+    }
+
+    /// Removes all cached outcomes.
+    pub fn clear(&self) {
+        self.lock().clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Cache> {
+        self.cache.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn key<Subject, Action, Object, Input, Context>(
+        &self,
+        subject: &Subject,
+        input: &Input,
+        context: &Context,
+        tenant: Option<&Tenant>,
+    ) -> Option<Vec<u8>>
+    where
+        Subject: Serialize,
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+        Input: Serialize,
+        Context: Serialize,
+    {
+        if self.max_entries == 0 || self.uncached_actions.contains(Action::TYPE) {
+            return None;
+        }
+        cache_key::<Subject, Action, Object, Input, Context>(subject, input, context, tenant)
+    }
+
+    fn get<T: Clone + 'static>(&self, key: &[u8]) -> Option<T> {
+        self.lock().get(key, self.ttl)
+    }
+
+    fn insert<T: Send + Sync + 'static>(&self, key: &[u8], ok: T) {
+        self.lock().insert(key, ok, self.ttl, self.max_entries)
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, AE>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for CachedEngine<AE>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Send + Serialize + Sync,
+    Action: ?Sized + ActionType + Sync,
+    Object: ?Sized + ObjectType + Sync,
+    Input: Serialize + Sync,
+    Context: Send + Serialize + Sync,
+    TransactionId: Send,
+    AE: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Send + Sync,
+    AE::Ok: Clone + Sync + 'static,
+{
+    type Ok = AE::Ok;
+    type Error = AE::Error;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let key = match transaction_id {
+            Some(_) => None,
+            None => self.key::<Subject, Action, Object, Input, Context>(&subject, input, &context, tenant.as_ref()),
+        };
+        if let Some(ok) = key.as_deref().and_then(|key| self.get::<AE::Ok>(key)) {
+            return Ok(ok);
+        }
+        let ok = self
+            .engine
+            .can_act(subject, input, context, tenant, transaction_id)
+            .await?;
+        if let Some(key) = key {
+            self.insert(&key, ok.clone());
+        }
+        Ok(ok)
+    }
+}
+
+#[async_trait]
+impl<TransactionId, AE> BatchAuthzEngine<TransactionId> for CachedEngine<AE>
+where
+    TransactionId: Send,
+    AE: BatchAuthzEngine<TransactionId> + Send + Sync,
+{
+    type Ok = AE::Ok;
+    type Error = AE::Error;
+
+    async fn can_act_batch(
+        &self,
+        events: Vec<BatchEvent>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Vec<Self::Ok>, Self::Error>
+    where
+        TransactionId: 'async_trait,
+    {
+        self.engine.can_act_batch(events, transaction_id).await
+    }
+}
+
+#[async_trait]
+impl<Subject, Context, TransactionId, AE> PartialAuthzEngine<Subject, Context, TransactionId> for CachedEngine<AE>
+where
+    Subject: Send,
+    Context: Send,
+    TransactionId: Send,
+    AE: PartialAuthzEngine<Subject, Context, TransactionId> + Send + Sync,
+{
+    type Error = AE::Error;
+
+    async fn partial_filter<Action, Object>(
+        &self,
+        subject: Subject,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<AuthzFilter, Self::Error>
+    where
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+        Subject: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        self.engine
+            .partial_filter::<Action, Object>(subject, context, tenant, transaction_id)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Default)]
+    struct CountingEngine(AtomicUsize);
+
+    #[async_trait]
+    impl<Action, Object> AuthzEngine<String, Action, Object, (), (), u64> for CountingEngine
+    where
+        Action: ?Sized + Send + Sync,
+        Object: ?Sized + Send + Sync,
+    {
+        type Ok = usize;
+        type Error = ();
+
+        async fn can_act(
+            &self,
+            _: String,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<u64>,
+        ) -> Result<Self::Ok, Self::Error>
+        where
+            Action: 'async_trait,
+            Object: 'async_trait,
+        {
+            Ok(self.0.fetch_add(1, Ordering::SeqCst))
+        }
+    }
+
+    struct Item;
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+    struct Read;
+    impl ActionType for Read {
+        const TYPE: &'static str = "read";
+    }
+    struct Delete;
+    impl ActionType for Delete {
+        const TYPE: &'static str = "delete";
+    }
+
+    fn can_act<A: ActionType + Send + Sync>(
+        engine: &CachedEngine<CountingEngine>,
+        subject: &str,
+        tx: Option<u64>,
+    ) -> usize {
+        futures::executor::block_on(AuthzEngine::<_, A, Item, _, _, _>::can_act(
+            engine,
+            subject.to_string(),
+            &(),
+            (),
+            None,
+            tx,
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn memoizes_outside_of_transactions() {
+        let engine = CachedEngine::new(CountingEngine::default()).uncached::<Delete>();
+        assert_eq!(can_act::<Read>(&engine, "a", None), 0);
+        assert_eq!(can_act::<Read>(&engine, "a", None), 0);
+        assert_eq!(can_act::<Read>(&engine, "b", None), 1);
+        assert_eq!(can_act::<Read>(&engine, "a", Some(1)), 2);
+        assert_eq!(can_act::<Delete>(&engine, "a", None), 3);
+        assert_eq!(can_act::<Delete>(&engine, "a", None), 4);
+
+        let engine = engine.max_entries(1);
+        engine.clear();
+        assert_eq!(can_act::<Read>(&engine, "a", None), 5);
+        assert_eq!(can_act::<Read>(&engine, "b", None), 6);
+        assert_eq!(can_act::<Read>(&engine, "a", None), 7);
+    }
+
+    #[test]
+    fn compares_full_keys() {
+        let mut cache = Cache::default();
+        let ttl = Duration::from_secs(60);
+        let a = cache_key::<_, Read, Item, _, _>(&"a", &(), &(), None).unwrap();
+        let b = cache_key::<_, Read, Item, _, _>(&"b", &(), &(), None).unwrap();
+        cache.insert(&a, 1usize, ttl, 10);
+        assert_eq!(cache.get::<usize>(&a, ttl), Some(1));
+        assert_eq!(cache.get::<usize>(&b, ttl), None);
+        assert_eq!(cache.get::<usize>(&a[..a.len() - 1], ttl), None);
+    }
+
+    #[test]
+    fn evicts_the_oldest_entries() {
+        let mut cache = Cache::default();
+        let ttl = Duration::from_secs(60);
+        let key = |subject: &str| cache_key::<_, Read, Item, _, _>(&subject, &(), &(), None).unwrap();
+        cache.insert(&key("a"), 1usize, ttl, 2);
+        cache.insert(&key("b"), 2usize, ttl, 2);
+        cache.insert(&key("a"), 3usize, ttl, 2);
+        cache.insert(&key("c"), 4usize, ttl, 2);
+        assert_eq!(cache.get::<usize>(&key("a"), ttl), Some(3));
+        assert_eq!(cache.get::<usize>(&key("b"), ttl), None);
+        assert_eq!(cache.get::<usize>(&key("c"), ttl), Some(4));
+        assert_eq!(cache.insertion_order.len(), 2);
+
+        cache.insert(&key("d"), 5usize, Duration::ZERO, 2);
+        assert_eq!(cache.entries.len(), 1);
+        assert_eq!(cache.insertion_order.len(), 1);
+        assert_eq!(cache.get::<usize>(&key("d"), ttl), Some(5));
+    }
+
+    /// Allows checks made in tenant "acme" only.
+    struct TenantEngine;
+
+    #[async_trait]
+    impl AuthzEngine<String, Read, Item, (), (), u64> for TenantEngine {
+        type Ok = ();
+        type Error = ();
+
+        async fn can_act(
+            &self,
+            _: String,
+            _: &(),
+            _: (),
+            tenant: Option<Tenant>,
+            _: Option<u64>,
+        ) -> Result<Self::Ok, Self::Error> {
+            match tenant {
+                Some(tenant) if tenant.as_str() == "acme" => Ok(()),
+                _ => Err(()),
+            }
+        }
+    }
+
+    #[test]
+    fn keys_outcomes_by_tenant() {
+        let engine = CachedEngine::new(TenantEngine);
+        let can_act = |tenant: &str| {
+            futures::executor::block_on(AuthzEngine::<_, Read, Item, _, _, _>::can_act(
+                &engine,
+                "a".to_string(),
+                &(),
+                (),
+                Some(Tenant::from(tenant)),
+                None,
+            ))
+        };
+        assert_eq!(can_act("acme"), Ok(()));
+        assert_eq!(can_act("globex"), Err(()));
+        assert_eq!(can_act("acme"), Ok(()));
+    }
+}
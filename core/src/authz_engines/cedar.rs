@@ -0,0 +1,32 @@
+use crate::{Applicability, AuthzErrorKind, ClassifyAuthzError};
+use ::authzen_cedar::{CedarEngine, CedarError, EntityLoader, EntityLoaderError};
+
+event_authz_engine! {
+    impl<L> for CedarEngine<L> where { L: EntityLoader<TransactionId>, }
+    type Error = CedarError;
+    |engine, key, event, transaction_id| engine.evaluate_serialize(key, &event, transaction_id.as_ref()).await
+}
+
+impl Applicability for CedarError {
+    /// Cedar denies requests which no policy permits, so it does not distinguish events without
+    /// applicable policies from denied events.
+    fn is_not_applicable(&self) -> bool {
+        false
+    }
+}
+
+impl ClassifyAuthzError for CedarError {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Denied(_) => AuthzErrorKind::Denied,
+            Self::EntityLoader(EntityLoaderError::Unavailable(_)) => AuthzErrorKind::Unavailable,
+            Self::EntityLoader(EntityLoaderError::Invalid(_))
+            | Self::Uid(_)
+            | Self::MissingId(_)
+            | Self::Entities(_)
+            | Self::Context(_)
+            | Self::Validation(_)
+            | Self::Serialization(_) => AuthzErrorKind::Misconfigured,
+        }
+    }
+}
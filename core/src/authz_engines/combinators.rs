@@ -0,0 +1,332 @@
+use crate::{AuthzEngine, AuthzErrorKind, ClassifyAuthzError, Decision, DecisionError, Event, Tenant};
+
+/// Combines authorization engines such that an event is only allowed if every engine allows it.
+///
+/// Engines are consulted in order and evaluation stops at the first engine which does not allow the
+/// event, so cheaper and coarser engines (e.g. a role gate) should be placed first. Combinators can
+/// be nested to combine more than two engines, e.g. `AllOf((a, AllOf((b, c))))`.
+#[derive(Clone, Copy, Debug, Default, Deref, DerefMut, Eq, From, PartialEq)]
+pub struct AllOf<T>(pub T);
+
+/// Combines authorization engines such that an event is allowed if any engine allows it.
+///
+/// Engines are consulted in order and evaluation stops at the first engine which allows the event.
+#[derive(Clone, Copy, Debug, Default, Deref, DerefMut, Eq, From, PartialEq)]
+pub struct AnyOf<T>(pub T);
+
+/// Combines authorization engines such that the first engine which is applicable to an event decides
+/// its outcome, where an engine is considered inapplicable to an event if it returns an error for which
+/// [`Applicability::is_not_applicable`] is true.
+#[derive(Clone, Copy, Debug, Default, Deref, DerefMut, Eq, From, PartialEq)]
+pub struct FirstApplicable<T>(pub T);
+
+/// Identifies which engine of a combinator produced a value, in the order the engines were provided.
+#[derive(Clone, Copy, Debug, Eq, IsVariant, PartialEq, Unwrap, thiserror::Error)]
+pub enum Either<A, B> {
+    #[error("first engine: {0}")]
+    First(A),
+    #[error("second engine: {0}")]
+    Second(B),
+}
+
+/// Error returned from [`AnyOf`] when none of its engines allowed an event.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, thiserror::Error)]
+#[error("first engine: {first}; second engine: {second}")]
+pub struct AnyOfError<A, B> {
+    pub first: A,
+    pub second: B,
+}
+
+/// Distinguishes an engine which has no policy for an event from one which explicitly rejected it.
+pub trait Applicability {
+    /// Returns true if the engine which produced this error had no policy applicable to the event.
+    fn is_not_applicable(&self) -> bool;
+}
+
+impl Applicability for Decision {
+    fn is_not_applicable(&self) -> bool {
+        !self.allow && self.reasons.iter().any(|reason| reason == Decision::NOT_APPLICABLE)
+    }
+}
+
+impl<E> Applicability for DecisionError<E> {
+    fn is_not_applicable(&self) -> bool {
+        match self {
+            Self::Denied(decision) => decision.is_not_applicable(),
+            Self::Engine(_) => false,
+        }
+    }
+}
+
+impl<A: Applicability, B: Applicability> Applicability for Either<A, B> {
+    fn is_not_applicable(&self) -> bool {
+        match self {
+            Self::First(err) => err.is_not_applicable(),
+            Self::Second(err) => err.is_not_applicable(),
+        }
+    }
+}
+
+impl<A: Applicability, B: Applicability> Applicability for AnyOfError<A, B> {
+    fn is_not_applicable(&self) -> bool {
+        self.first.is_not_applicable() && self.second.is_not_applicable()
+    }
+}
+
+impl<A: ClassifyAuthzError, B: ClassifyAuthzError> ClassifyAuthzError for Either<A, B> {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::First(err) => err.authz_error_kind(),
+            Self::Second(err) => err.authz_error_kind(),
+        }
+    }
+}
+
+/// An [`AnyOf`] is only considered to have denied an event if every engine denied it; otherwise, if any
+/// engine was unavailable the event may be allowed on a retry, so the error is classified as unavailable.
+impl<A: ClassifyAuthzError, B: ClassifyAuthzError> ClassifyAuthzError for AnyOfError<A, B> {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match (self.first.authz_error_kind(), self.second.authz_error_kind()) {
+            (AuthzErrorKind::Denied, AuthzErrorKind::Denied) => AuthzErrorKind::Denied,
+            (AuthzErrorKind::Unavailable, _) | (_, AuthzErrorKind::Unavailable) => AuthzErrorKind::Unavailable,
+            _ => AuthzErrorKind::Misconfigured,
+        }
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, A, B>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for AllOf<(A, B)>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Clone + Send + Sync,
+    Action: ?Sized + Sync,
+    Object: ?Sized + Sync,
+    Input: Sync,
+    Context: Clone + Send + Sync,
+    TransactionId: Clone + Send + Sync,
+    A: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+    B: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+{
+    type Ok = (A::Ok, B::Ok);
+    type Error = Either<A::Error, B::Error>;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let (a, b) = &self.0;
+        let first = a
+            .can_act(
+                subject.clone(),
+                input,
+                context.clone(),
+                tenant.clone(),
+                transaction_id.clone(),
+            )
+            .await
+            .map_err(Either::First)?;
+        let second = b
+            .can_act(subject, input, context, tenant, transaction_id)
+            .await
+            .map_err(Either::Second)?;
+        Ok((first, second))
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, A, B>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for AnyOf<(A, B)>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Clone + Send + Sync,
+    Action: ?Sized + Sync,
+    Object: ?Sized + Sync,
+    Input: Sync,
+    Context: Clone + Send + Sync,
+    TransactionId: Clone + Send + Sync,
+    A: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+    B: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+{
+    type Ok = Either<A::Ok, B::Ok>;
+    type Error = AnyOfError<A::Error, B::Error>;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let (a, b) = &self.0;
+        let first = match a
+            .can_act(
+                subject.clone(),
+                input,
+                context.clone(),
+                tenant.clone(),
+                transaction_id.clone(),
+            )
+            .await
+        {
+            Ok(ok) => return Ok(Either::First(ok)),
+            Err(err) => err,
+        };
+        match b.can_act(subject, input, context, tenant, transaction_id).await {
+            Ok(ok) => Ok(Either::Second(ok)),
+            Err(second) => Err(AnyOfError { first, second }),
+        }
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, A, B>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for FirstApplicable<(A, B)>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Clone + Send + Sync,
+    Action: ?Sized + Sync,
+    Object: ?Sized + Sync,
+    Input: Sync,
+    Context: Clone + Send + Sync,
+    TransactionId: Clone + Send + Sync,
+    A: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+    B: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+    A::Error: Applicability,
+{
+    type Ok = Either<A::Ok, B::Ok>;
+    type Error = Either<A::Error, B::Error>;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let (a, b) = &self.0;
+        match a
+            .can_act(
+                subject.clone(),
+                input,
+                context.clone(),
+                tenant.clone(),
+                transaction_id.clone(),
+            )
+            .await
+        {
+            Ok(ok) => return Ok(Either::First(ok)),
+            Err(err) if !err.is_not_applicable() => return Err(Either::First(err)),
+            Err(_) => {}
+        };
+        b.can_act(subject, input, context, tenant, transaction_id)
+            .await
+            .map(Either::Second)
+            .map_err(Either::Second)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Allows subjects found in the provided list and is not applicable to any other subject.
+    struct Subjects(&'static [&'static str]);
+
+    #[async_trait]
+    impl AuthzEngine<&'static str, (), (), (), (), ()> for Subjects {
+        type Ok = ();
+        type Error = DecisionError<()>;
+
+        async fn can_act(
+            &self,
+            subject: &'static str,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<(), Self::Error> {
+            if self.0.contains(&subject) {
+                Ok(())
+            } else {
+                Err(DecisionError::Denied(Decision::not_applicable()))
+            }
+        }
+    }
+
+    /// Denies every event.
+    struct Deny;
+
+    #[async_trait]
+    impl AuthzEngine<&'static str, (), (), (), (), ()> for Deny {
+        type Ok = ();
+        type Error = DecisionError<()>;
+
+        async fn can_act(
+            &self,
+            _: &'static str,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<(), Self::Error> {
+            Err(DecisionError::Denied(Decision::deny()))
+        }
+    }
+
+    fn can_act<AE: AuthzEngine<&'static str, (), (), (), (), ()>>(
+        engine: &AE,
+        subject: &'static str,
+    ) -> Result<AE::Ok, AE::Error> {
+        futures::executor::block_on(engine.can_act(subject, &(), (), None, None))
+    }
+
+    #[test]
+    fn combines_engines() {
+        let engine = AllOf((Subjects(&["a", "b"]), Subjects(&["b"])));
+        assert!(can_act(&engine, "b").is_ok());
+        assert!(can_act(&engine, "a").unwrap_err().is_second());
+        assert!(can_act(&engine, "c").unwrap_err().is_first());
+
+        let engine = AnyOf((Subjects(&["a"]), Subjects(&["b"])));
+        assert!(can_act(&engine, "a").unwrap().is_first());
+        assert!(can_act(&engine, "b").unwrap().is_second());
+        assert!(can_act(&engine, "c").unwrap_err().is_not_applicable());
+
+        let engine = FirstApplicable((Subjects(&["a"]), Deny));
+        assert!(can_act(&engine, "a").unwrap().is_first());
+        assert!(can_act(&engine, "b").unwrap_err().is_second());
+        let engine = FirstApplicable((Deny, Subjects(&["a"])));
+        assert!(can_act(&engine, "a").unwrap_err().is_first());
+    }
+}
@@ -0,0 +1,44 @@
+use crate::DecisionError;
+
+/// Classifies the reason an authorization engine did not allow an event.
+///
+/// Callers can use this classification to decide whether an action is worth retrying; a denial
+/// will be reached again given the same event while an unavailable engine may recover.
+#[derive(Clone, Copy, Debug, Eq, Hash, IsVariant, PartialEq)]
+pub enum AuthzErrorKind {
+    /// The engine reached a decision which did not allow the event.
+    Denied,
+    /// The engine could not be reached or did not respond in time, e.g. a network error or timeout.
+    Unavailable,
+    /// The engine was reachable but could not reach a decision, e.g. its response could not be
+    /// deserialized or the event could not be serialized.
+    Misconfigured,
+}
+
+/// Implemented by errors returned from [`AuthzEngine`](crate::AuthzEngine)s to expose an [`AuthzErrorKind`].
+pub trait ClassifyAuthzError {
+    fn authz_error_kind(&self) -> AuthzErrorKind;
+}
+
+impl<E: ClassifyAuthzError> ClassifyAuthzError for DecisionError<E> {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Denied(_) => AuthzErrorKind::Denied,
+            Self::Engine(err) => err.authz_error_kind(),
+        }
+    }
+}
+
+/// Classifies errors by status code: `408`, `429`, `502`, `503` and `504` indicate the engine is
+/// unavailable and all other status codes indicate a misconfiguration. Notably, `401` and `403` mean
+/// that the service could not authenticate to the engine rather than that the engine denied an event,
+/// denials are instead returned as decisions.
+#[cfg(feature = "authzen-service-util")]
+impl ClassifyAuthzError for authzen_service_util::Error {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self.status_code.as_u16() {
+            408 | 429 | 502 | 503 | 504 => AuthzErrorKind::Unavailable,
+            _ => AuthzErrorKind::Misconfigured,
+        }
+    }
+}
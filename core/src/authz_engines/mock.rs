@@ -0,0 +1,471 @@
+use crate::{
+    ActionType, AuthzEngine, AuthzErrorKind, ClassifyAuthzError, Decision, DecisionError, Event, ObjectType, Tenant,
+};
+use ::derivative::Derivative;
+use ::serde::{Deserialize, Serialize};
+use ::serde_json::Value;
+use ::std::fmt::Debug;
+use ::std::fs::File;
+use ::std::io::{BufReader, BufWriter};
+use ::std::path::Path;
+use ::std::sync::{Arc, Mutex};
+
+/// A single event seen by a [`MockEngine`] or [`RecordingEngine`] along with the decision reached for it.
+/// Fixture files used by [`MockEngine::replay`] are json arrays of records.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct MockRecord {
+    pub service: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    pub action: String,
+    /// the serialized event, i.e. the same structure used as input to OPA policies
+    pub event: Value,
+    pub decision: Decision,
+}
+
+/// Fields of recorded events which are ignored when replaying fixtures, since they are expected
+/// to differ between test runs.
+const UNMATCHED_EVENT_FIELDS: &[&str] = &["transaction_id"];
+
+impl MockRecord {
+    fn matches(&self, other: &MockRecord) -> bool {
+        self.service == other.service
+            && self.ty == other.ty
+            && self.action == other.action
+            && self.matched_event() == other.matched_event()
+    }
+
+    fn matched_event(&self) -> Value {
+        let mut event = self.event.clone();
+        if let Value::Object(fields) = &mut event {
+            for field in UNMATCHED_EVENT_FIELDS {
+                fields.remove(*field);
+            }
+        }
+        event
+    }
+}
+
+/// Describes the events which a [`MockEngine`] should reach a specific decision for.
+///
+/// Events are matched on their object service, object type and action, and optionally on the
+/// serialized representation of their subject, input and context.
+/// ```rs
+/// let engine = MockEngine::new().expect(Expectation::allow::<Update<Cart>, Cart>().subject(account_id));
+/// ```
+#[derive(Clone, Debug)]
+pub struct Expectation {
+    service: &'static str,
+    ty: &'static str,
+    action: &'static str,
+    subject: Option<Value>,
+    input: Option<Value>,
+    context: Option<Value>,
+    decision: Decision,
+}
+
+impl Expectation {
+    pub fn new<Action, Object>(decision: impl Into<Decision>) -> Self
+    where
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+    {
+        Self {
+            service: Object::SERVICE,
+            ty: Object::TYPE,
+            action: Action::TYPE,
+            subject: None,
+            input: None,
+            context: None,
+            decision: decision.into(),
+        }
+    }
+
+    pub fn allow<Action: ?Sized + ActionType, Object: ?Sized + ObjectType>() -> Self {
+        Self::new::<Action, Object>(true)
+    }
+
+    pub fn deny<Action: ?Sized + ActionType, Object: ?Sized + ObjectType>() -> Self {
+        Self::new::<Action, Object>(false)
+    }
+
+    /// Only match events whose subject serializes to the same value as `subject`.
+    pub fn subject(mut self, subject: impl Serialize) -> Self {
+        self.subject = Some(serde_json::to_value(subject).expect("unable to serialize expected subject"));
+        self
+    }
+
+    /// Only match events whose input serializes to the same value as `input`.
+    pub fn input(mut self, input: impl Serialize) -> Self {
+        self.input = Some(serde_json::to_value(input).expect("unable to serialize expected input"));
+        self
+    }
+
+    /// Only match events whose context serializes to the same value as `context`.
+    pub fn context(mut self, context: impl Serialize) -> Self {
+        self.context = Some(serde_json::to_value(context).expect("unable to serialize expected context"));
+        self
+    }
+
+    /// Overrides the decision returned for matching events.
+    pub fn decision(mut self, decision: impl Into<Decision>) -> Self {
+        self.decision = decision.into();
+        self
+    }
+
+    fn matches(&self, record: &MockRecord) -> bool {
+        let field_matches = |expected: &Option<Value>, field: &str| match expected {
+            Some(expected) => record.event.get(field) == Some(expected),
+            None => true,
+        };
+        self.service == record.service
+            && self.ty == record.ty
+            && self.action == record.action
+            && field_matches(&self.subject, "subject")
+            && field_matches(&self.input, "input")
+            && field_matches(&self.context, "context")
+    }
+}
+
+#[derive(Clone, Debug, thiserror::Error)]
+pub enum MockError {
+    #[error("no recorded decision for `{}.{}:{}` event: {}", .0.service, .0.ty, .0.action, .0.event)]
+    Unrecorded(Box<UnrecordedEvent>),
+    #[error("unable to serialize event: {0}")]
+    Serialization(Arc<serde_json::Error>),
+    #[error("unable to access fixture file: {0}")]
+    Io(Arc<std::io::Error>),
+}
+
+/// An event replayed by a [`MockEngine`] which matched none of its fixtures.
+#[derive(Clone, Debug)]
+pub struct UnrecordedEvent {
+    pub service: String,
+    pub ty: String,
+    pub action: String,
+    pub event: Value,
+}
+
+impl ClassifyAuthzError for MockError {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        AuthzErrorKind::Misconfigured
+    }
+}
+
+/// An authorization engine for use in tests which reaches decisions using programmable
+/// [`Expectation`]s and/or fixtures recorded from a real engine with [`RecordingEngine`].
+///
+/// Expectations are checked in the order they were added and the first one matching an event
+/// decides it. Events matching no expectation are decided by the first fixture with an identical
+/// event other than its transaction id, which differs between test runs; if fixtures were loaded
+/// with [`MockEngine::replay`] and none match, an [`MockError::Unrecorded`] error is returned,
+/// otherwise the event is denied as not applicable.
+///
+/// Every event seen is recorded and can be inspected with [`MockEngine::records`]. Clones of a
+/// `MockEngine` share their expectations and records.
+#[derive(Clone, Debug, Default)]
+pub struct MockEngine {
+    state: Arc<Mutex<MockState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    expectations: Vec<Expectation>,
+    fixtures: Option<Vec<MockRecord>>,
+    records: Vec<MockRecord>,
+}
+
+impl MockEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an expectation, which takes precedence over any fixtures.
+    pub fn expect(self, expectation: Expectation) -> Self {
+        self.lock().expectations.push(expectation);
+        self
+    }
+
+    /// Creates an engine which decides events using the fixture file at `path`,
+    /// as written by [`RecordingEngine::save`].
+    pub fn replay(path: impl AsRef<Path>) -> Result<Self, MockError> {
+        let file = File::open(path).map_err(|err| MockError::Io(Arc::new(err)))?;
+        let fixtures =
+            serde_json::from_reader(BufReader::new(file)).map_err(|err| MockError::Serialization(Arc::new(err)))?;
+        Ok(Self::from_fixtures(fixtures))
+    }
+
+    pub fn from_fixtures(fixtures: Vec<MockRecord>) -> Self {
+        let engine = Self::default();
+        engine.lock().fixtures = Some(fixtures);
+        engine
+    }
+
+    /// All events seen by this engine along with the decisions reached, in the order they were seen.
+    pub fn records(&self) -> Vec<MockRecord> {
+        self.lock().records.clone()
+    }
+
+    /// Removes all records of events seen so far.
+    pub fn clear_records(&self) {
+        self.lock().records.clear();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn decide(&self, mut record: MockRecord) -> Result<Decision, MockError> {
+        let mut state = self.lock();
+        let decision = match state
+            .expectations
+            .iter()
+            .find(|expectation| expectation.matches(&record))
+        {
+            Some(expectation) => expectation.decision.clone(),
+            None => match &state.fixtures {
+                Some(fixtures) => match fixtures.iter().find(|fixture| fixture.matches(&record)) {
+                    Some(fixture) => fixture.decision.clone(),
+                    None => {
+                        return Err(MockError::Unrecorded(Box::new(UnrecordedEvent {
+                            service: record.service,
+                            ty: record.ty,
+                            action: record.action,
+                            event: record.event,
+                        })))
+                    }
+                },
+                None => Decision::not_applicable(),
+            },
+        };
+        record.decision = decision.clone();
+        state.records.push(record);
+        Ok(decision)
+    }
+}
+
+#[derive(Serialize)]
+struct MockEvent<E, TransactionId> {
+    #[serde(flatten)]
+    event: E,
+    transaction_id: Option<TransactionId>,
+}
+
+fn to_record<Subject, Action, Object, Input, Context, TransactionId>(
+    subject: Subject,
+    input: &Input,
+    context: Context,
+    tenant: Option<Tenant>,
+    transaction_id: Option<TransactionId>,
+) -> Result<MockRecord, MockError>
+where
+    Subject: Serialize,
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+    Input: Serialize,
+    Context: Serialize,
+    TransactionId: Serialize,
+{
+    let event = MockEvent {
+        event: Event {
+            action: std::marker::PhantomData::<Action>,
+            object: std::marker::PhantomData::<Object>,
+            subject,
+            input,
+            context,
+            tenant,
+        },
+        transaction_id,
+    };
+    Ok(MockRecord {
+        service: Object::SERVICE.into(),
+        ty: Object::TYPE.into(),
+        action: Action::TYPE.into(),
+        event: serde_json::to_value(event).map_err(|err| MockError::Serialization(Arc::new(err)))?,
+        decision: Decision::deny(),
+    })
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for MockEngine
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Send + Serialize + Sync,
+    Action: ?Sized + ActionType + Send + Sync,
+    Object: ?Sized + ObjectType + Send + Sync,
+    Input: Serialize + Send + Sync,
+    Context: Send + Serialize + Sync,
+    TransactionId: Send + Serialize + Sync,
+{
+    type Ok = Decision;
+    type Error = DecisionError<MockError>;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let record = to_record::<_, Action, Object, _, _, _>(subject, input, context, tenant, transaction_id)
+            .map_err(DecisionError::Engine)?;
+        self.decide(record).map_err(DecisionError::Engine)?.into_result()
+    }
+}
+
+/// Wraps an authorization engine which produces [`Decision`]s (e.g. an `OPAClient`), recording
+/// every event and decision so that they can be saved to a fixture file and later replayed with
+/// [`MockEngine::replay`], making tests which would otherwise require a live engine hermetic.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct RecordingEngine<AE> {
+    engine: AE,
+    #[derivative(Debug = "ignore")]
+    records: Arc<Mutex<Vec<MockRecord>>>,
+}
+
+impl<AE> RecordingEngine<AE> {
+    pub fn new(engine: AE) -> Self {
+        Self {
+            engine,
+            records: Default::default(),
+        }
+    }
+
+    /// All events seen by this engine along with the decisions reached, in the order they were seen.
+    pub fn records(&self) -> Vec<MockRecord> {
+        self.records.lock().unwrap_or_else(|err| err.into_inner()).clone()
+    }
+
+    /// Writes all records to a fixture file at `path`, overwriting it if it exists.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), MockError> {
+        let file = File::create(path).map_err(|err| MockError::Io(Arc::new(err)))?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &self.records())
+            .map_err(|err| MockError::Serialization(Arc::new(err)))
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, AE, E>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for RecordingEngine<AE>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Clone + Send + Serialize + Sync,
+    Action: ?Sized + ActionType + Send + Sync,
+    Object: ?Sized + ObjectType + Send + Sync,
+    Input: Serialize + Send + Sync,
+    Context: Clone + Send + Serialize + Sync,
+    TransactionId: Clone + Send + Serialize + Sync,
+    AE: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId, Ok = Decision, Error = DecisionError<E>>
+        + Sync,
+    E: Debug + Send,
+{
+    type Ok = Decision;
+    type Error = DecisionError<E>;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let record = to_record::<_, Action, Object, _, _, _>(
+            subject.clone(),
+            input,
+            context.clone(),
+            tenant.clone(),
+            transaction_id.clone(),
+        );
+        let result = self
+            .engine
+            .can_act(subject, input, context, tenant, transaction_id)
+            .await;
+        let decision = match &result {
+            Ok(decision) | Err(DecisionError::Denied(decision)) => decision.clone(),
+            Err(DecisionError::Engine(_)) => return result,
+        };
+        if let Ok(mut record) = record {
+            record.decision = decision;
+            self.records.lock().unwrap_or_else(|err| err.into_inner()).push(record);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct Cart;
+    impl ObjectType for Cart {
+        const SERVICE: &'static str = "examples_cart";
+        const TYPE: &'static str = "cart";
+    }
+    struct Update;
+    impl ActionType for Update {
+        const TYPE: &'static str = "update";
+    }
+
+    fn can_act<AE>(engine: &AE, subject: &'static str) -> Result<Decision, AE::Error>
+    where
+        AE: AuthzEngine<&'static str, Update, Cart, (), (), ()>,
+        AE: AuthzEngine<&'static str, Update, Cart, (), (), (), Ok = Decision>,
+    {
+        futures::executor::block_on(engine.can_act(subject, &(), (), None, None))
+    }
+
+    #[test]
+    fn records_and_replays_decisions() {
+        let engine = MockEngine::new().expect(Expectation::allow::<Update, Cart>().subject("x"));
+        assert!(can_act(&engine, "x").is_ok());
+        assert!(can_act(&engine, "y").unwrap_err().is_denied());
+        let records = engine.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].event["subject"], "y");
+
+        let recording = RecordingEngine::new(engine);
+        assert!(can_act(&recording, "x").is_ok());
+        let replay = MockEngine::from_fixtures(recording.records());
+        assert!(can_act(&replay, "x").is_ok());
+        assert!(matches!(
+            can_act(&replay, "y"),
+            Err(DecisionError::Engine(MockError::Unrecorded(_)))
+        ));
+    }
+
+    fn can_act_within<AE>(engine: &AE, transaction_id: u64) -> Result<Decision, AE::Error>
+    where
+        AE: AuthzEngine<&'static str, Update, Cart, (), (), u64, Ok = Decision>,
+    {
+        futures::executor::block_on(engine.can_act("x", &(), (), None, Some(transaction_id)))
+    }
+
+    #[test]
+    fn replays_decisions_recorded_within_other_transactions() {
+        let recording = RecordingEngine::new(MockEngine::new().expect(Expectation::allow::<Update, Cart>()));
+        assert!(can_act_within(&recording, 1).is_ok());
+        assert_eq!(recording.records()[0].event["transaction_id"], 1);
+        let replay = MockEngine::from_fixtures(recording.records());
+        assert!(can_act_within(&replay, 2).is_ok());
+    }
+}
@@ -1,2 +1,100 @@
+/// Implements [`AuthzEngine`](crate::AuthzEngine) for an in-process engine which evaluates the json
+/// representation of an [`Event`](crate::Event) (see [`authzen_event::SerializedEvent`]).
+/// `$evaluate` is provided the engine, the [`EventKey`](authzen_event::EventKey) of the event's object
+/// type and action, the event and its transaction id.
+#[cfg(any(
+    feature = "cedar-authz-engine",
+    feature = "oso-authz-engine",
+    feature = "rbac-authz-engine",
+    feature = "rebac-authz-engine",
+))]
+macro_rules! event_authz_engine {
+    (
+        impl<$($param:ident),*> for $engine:ty where { $($bounds:tt)* }
+        type Error = $error:ty;
+        |$this:ident, $key:ident, $event:ident, $transaction_id:ident| $evaluate:expr
+    ) => {
+        #[async_trait]
+        impl<Subject, Action, Object, Input, Context, TransactionId, $($param),*>
+            $crate::AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for $engine
+        where
+            $crate::Event<Subject, Action, Object, Input, Context>: Send + Sync,
+            Subject: ::std::fmt::Debug + Send + ::serde::Serialize + Sync,
+            Action: ?Sized + $crate::ActionType + Send + Sync,
+            Object: ?Sized + $crate::ObjectType + Send + Sync,
+            Input: ::std::fmt::Debug + ::serde::Serialize + Send + Sync,
+            Context: ::std::fmt::Debug + Send + ::serde::Serialize + Sync,
+            TransactionId: ::std::fmt::Debug + Send + Sync,
+            $($bounds)*
+        {
+            type Ok = ();
+            type Error = $error;
+
+            async fn can_act(
+                &self,
+                subject: Subject,
+                input: &Input,
+                context: Context,
+                tenant: Option<$crate::Tenant>,
+                transaction_id: Option<TransactionId>,
+            ) -> Result<Self::Ok, Self::Error>
+            where
+                Subject: 'async_trait,
+                Action: 'async_trait,
+                Object: 'async_trait,
+                Input: 'async_trait,
+                Context: 'async_trait,
+                TransactionId: 'async_trait,
+            {
+                let $this = self;
+                let $key = ::authzen_event::EventKey::new(Object::SERVICE, Object::TYPE, Action::TYPE);
+                let $event = $crate::Event {
+                    action: ::std::marker::PhantomData::<Action>,
+                    object: ::std::marker::PhantomData::<Object>,
+                    subject,
+                    input,
+                    context,
+                    tenant,
+                };
+                let $transaction_id = transaction_id;
+                $evaluate
+            }
+        }
+    };
+}
+
+mod cached;
+#[cfg(feature = "cedar-authz-engine")]
+mod cedar;
+mod combinators;
+mod error_kind;
+#[cfg(feature = "mock-authz-engine")]
+mod mock;
 #[cfg(feature = "opa-authz-engine")]
 mod opa;
+#[cfg(feature = "oso-authz-engine")]
+mod oso;
+#[cfg(feature = "rbac-authz-engine")]
+mod rbac;
+#[cfg(feature = "rebac-authz-engine")]
+mod rebac;
+#[cfg(feature = "resilient-authz-engine")]
+mod resilient;
+#[cfg(feature = "rules-authz-engine")]
+mod rules;
+#[cfg(feature = "shadow-authz-engine")]
+mod shadow;
+
+pub use cached::*;
+pub use combinators::*;
+pub use error_kind::*;
+#[cfg(feature = "mock-authz-engine")]
+pub use mock::*;
+#[cfg(all(feature = "rbac-authz-engine", feature = "diesel-data-source"))]
+pub use rbac::*;
+#[cfg(feature = "rebac-authz-engine")]
+pub use rebac::*;
+#[cfg(feature = "resilient-authz-engine")]
+pub use resilient::*;
+#[cfg(feature = "shadow-authz-engine")]
+pub use shadow::*;
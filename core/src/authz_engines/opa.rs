@@ -1,8 +1,13 @@
-use crate::{ActionType, AuthzEngine, Event, ObjectType};
+use crate::{
+    ActionType, AuthzEngine, BatchAuthzEngine, BatchEvent, Decision, DecisionError, Event, ObjectType,
+    PartialAuthzEngine, Tenant,
+};
+use ::authzen_data_sources::{AuthzFilter, Comparison, Condition};
 use ::authzen_opa::OPAClient;
 use ::authzen_service_util::*;
+use ::futures::future;
 use ::hyper::{body::Bytes, http::header::*, Body, Method};
-use ::serde::{Deserialize, Deserializer, Serialize};
+use ::serde::{de::Error as _, Deserialize, Deserializer, Serialize};
 use ::serde_json::Value;
 use ::std::fmt::Debug;
 use ::typed_builder::TypedBuilder;
@@ -26,14 +31,15 @@ where
     Context: Debug + Send + Serialize + Sync,
     TransactionId: Debug + Send + Serialize + Sync,
 {
-    type Ok = ();
-    type Error = authzen_service_util::Error;
+    type Ok = Decision;
+    type Error = DecisionError<authzen_service_util::Error>;
 
     async fn can_act(
         &self,
         subject: Subject,
         input: &Input,
         context: Context,
+        tenant: Option<Tenant>,
         transaction_id: Option<TransactionId>,
     ) -> Result<Self::Ok, Self::Error>
     where
@@ -65,16 +71,148 @@ where
                     subject,
                     input,
                     context,
+                    tenant,
                 },
                 transaction_id,
             },
         }
         .query(self)
+        .await
+        .map_err(DecisionError::Engine)?;
+        result.0.into_result()
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct OPABatchEvent<TransactionId> {
+    events: Vec<BatchEvent>,
+    transaction_id: Option<TransactionId>,
+}
+
+#[async_trait]
+impl<TransactionId> BatchAuthzEngine<TransactionId> for OPAClient
+where
+    TransactionId: Debug + Send + Serialize + Sync,
+{
+    type Ok = Decision;
+    type Error = authzen_service_util::Error;
+
+    async fn can_act_batch(
+        &self,
+        events: Vec<BatchEvent>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Vec<Self::Ok>, Self::Error>
+    where
+        TransactionId: 'async_trait,
+    {
+        if events.is_empty() {
+            return Ok(vec![]);
+        }
+        let num_events = events.len();
+        let explain = std::env::var("OPA_EXPLAIN").ok();
+        let result: OPABatchQueryResult = OPAQuery {
+            config: OPAQueryConfig::builder()
+                .data_path(&*self.data_path)
+                .query(&*self.batch_query)
+                .pretty(
+                    std::env::var("OPA_PRETTY")
+                        .ok()
+                        .and_then(|x| x.parse::<bool>().ok())
+                        .unwrap_or_default(),
+                )
+                .explain(explain.as_deref())
+                .build(),
+            data: None,
+            input: OPABatchEvent { events, transaction_id },
+        }
+        .query(self)
         .await?;
-        if result == false {
-            return Err(authzen_service_util::Error::bad_request());
+        match result.0 {
+            Some(decisions) if decisions.len() == num_events => Ok(decisions),
+            Some(decisions) => Err(authzen_service_util::Error::default_msg(format!(
+                "expected {num_events} decisions from batch query `{}/{}`, received {}",
+                self.data_path,
+                self.batch_query,
+                decisions.len(),
+            ))),
+            None => Ok(vec![Decision::not_applicable(); num_events]),
         }
-        Ok(())
+    }
+}
+
+/// Policies evaluated with [`PartialAuthzEngine::partial_filter`] should reference the object being
+/// authorized as `input.record`, which is left unknown during partial evaluation. References to
+/// `input.record.<field>` in the residual policy are mapped to the field (e.g. column) of the same name.
+const PARTIAL_EVALUATION_UNKNOWN: &str = "input.record";
+
+/// Since a policy may evaluate to either a boolean or a [`Decision`] object, the policy is partially
+/// evaluated once for each shape and an object is allowed if any of the residuals allow it; the residual
+/// of a shape which the policy never produces allows nothing.
+fn partial_evaluation_queries(data_path: &str, query: &str) -> [String; 3] {
+    let decision = format!("data.{}.{query}", data_path.replace('/', "."));
+    [
+        format!("{decision} == true"),
+        format!("{decision}.allow == true"),
+        format!("{decision}.response == true"),
+    ]
+}
+
+#[async_trait]
+impl<Subject, Context, TransactionId> PartialAuthzEngine<Subject, Context, TransactionId> for OPAClient
+where
+    Subject: Debug + Send + Serialize + Sync,
+    Context: Debug + Send + Serialize + Sync,
+    TransactionId: Debug + Send + Serialize + Sync,
+{
+    type Error = authzen_service_util::Error;
+
+    async fn partial_filter<Action, Object>(
+        &self,
+        subject: Subject,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<AuthzFilter, Self::Error>
+    where
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+        Subject: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let explain = std::env::var("OPA_EXPLAIN").ok();
+        let input = serde_json::to_value(OPAEvent {
+            event: Event {
+                action: std::marker::PhantomData::<Action>,
+                object: std::marker::PhantomData::<Object>,
+                subject,
+                input: (),
+                context,
+                tenant,
+            },
+            transaction_id,
+        })
+        .map_err(authzen_service_util::Error::default_details)?;
+        let config = OPAQueryConfig::builder()
+            .data_path(&*self.data_path)
+            .query(&*self.query)
+            .pretty(
+                std::env::var("OPA_PRETTY")
+                    .ok()
+                    .and_then(|x| x.parse::<bool>().ok())
+                    .unwrap_or_default(),
+            )
+            .explain(explain.as_deref())
+            .build();
+        let compiles = partial_evaluation_queries(&self.data_path, &self.query).map(|query| OPACompile {
+            config,
+            query,
+            input: input.clone(),
+            unknowns: [PARTIAL_EVALUATION_UNKNOWN],
+        });
+        let results: Vec<OPACompileResult> =
+            future::try_join_all(compiles.iter().map(|compile| compile.query(self))).await?;
+        OPACompileResult::try_into_filter_union(results).map_err(authzen_service_util::Error::default_msg)
     }
 }
 
@@ -129,52 +267,400 @@ impl<Input: Serialize> Endpoint for OPAQuery<'_, Input> {
     }
 }
 
-#[derive(Clone, Copy, Debug, Deref, DerefMut, Eq, From, Into, Ord, PartialEq, PartialOrd, Serialize)]
-pub struct OPAQueryResult(pub bool);
+/// Policies may either evaluate to a boolean or to an object representation of a [`Decision`];
+/// undefined results and results of any other type are treated as a denial with reason
+/// [`Decision::NOT_APPLICABLE`].
+#[derive(Clone, Debug, Default, Deref, DerefMut, Eq, From, Into, PartialEq, Serialize)]
+pub struct OPAQueryResult(pub Decision);
 
-impl PartialEq<bool> for OPAQueryResult {
-    fn eq(&self, rhs: &bool) -> bool {
-        self.0 == *rhs
+#[derive(Clone, Debug, Deserialize)]
+struct _OPAQueryResult {
+    result: Option<Value>,
+}
+
+impl<'de> Deserialize<'de> for OPAQueryResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let _opa_query_result = _OPAQueryResult::deserialize(deserializer)?;
+        Ok(OPAQueryResult(match _opa_query_result.result {
+            Some(result @ (Value::Bool(_) | Value::Object(_))) => {
+                serde_json::from_value(result).map_err(D::Error::custom)?
+            }
+            _ => Decision::not_applicable(),
+        }))
+    }
+}
+
+/// Result of a batch query, which is expected to be an array with one decision per event. As with
+/// [`OPAQueryResult`], undefined results and results which are neither a boolean nor an object are
+/// treated as a denial with reason [`Decision::NOT_APPLICABLE`].
+#[derive(Clone, Debug, Default, Deref, DerefMut, Eq, From, Into, PartialEq, Serialize)]
+pub struct OPABatchQueryResult(pub Option<Vec<Decision>>);
+
+impl<'de> Deserialize<'de> for OPABatchQueryResult {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let _opa_query_result = _OPAQueryResult::deserialize(deserializer)?;
+        Ok(OPABatchQueryResult(match _opa_query_result.result {
+            Some(Value::Array(results)) => Some(
+                results
+                    .into_iter()
+                    .map(|result| match result {
+                        Value::Bool(_) | Value::Object(_) => serde_json::from_value(result).map_err(D::Error::custom),
+                        _ => Ok(Decision::not_applicable()),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+            Some(_) => return Err(D::Error::custom("expected batch query result to be an array")),
+            None => None,
+        }))
     }
 }
 
-impl PartialEq<OPAQueryResult> for bool {
-    fn eq(&self, rhs: &OPAQueryResult) -> bool {
-        *self == rhs.0
+#[derive(Clone, Debug, Serialize)]
+struct OPACompile<'a> {
+    #[serde(skip_serializing)]
+    config: OPAQueryConfig<'a>,
+    query: String,
+    input: Value,
+    unknowns: [&'static str; 1],
+}
+
+impl Endpoint for OPACompile<'_> {
+    const METHOD: Method = Method::POST;
+    type Params<'a> = OPAQueryConfig<'a> where Self: 'a;
+
+    fn params(&self) -> Self::Params<'_> {
+        self.config
+    }
+    fn path(&self) -> Path {
+        "/v1/compile".into()
+    }
+    fn headers(&self) -> HeaderMap {
+        HeaderMap::from_iter(vec![(CONTENT_TYPE, HeaderValue::from_str("application/json").unwrap())])
+    }
+    fn body(&self) -> Body {
+        let body = serde_json::to_string(&self).unwrap();
+        Body::from(Bytes::copy_from_slice(body.as_bytes()))
     }
 }
 
 #[derive(Clone, Debug, Deserialize)]
-struct _OPAQueryResult {
-    result: Option<Value>,
+struct OPACompileResult {
+    #[serde(default)]
+    result: OPAPartialResult,
 }
 
-impl<'de> Deserialize<'de> for OPAQueryResult {
-    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let _simple_query_result = _OPAQueryResult::deserialize(deserializer)?;
-        Ok(OPAQueryResult(
-            _simple_query_result
-                .result
-                .map(|result| if let Value::Bool(bool) = result { bool } else { false })
-                .unwrap_or_default(),
-        ))
-    }
-}
-
-impl OPAQueryResult {
-    pub fn ok_or<E>(self, e: E) -> Result<(), E> {
-        if self.0 {
-            Ok(())
-        } else {
-            Err(e)
+#[derive(Clone, Debug, Default, Deserialize)]
+struct OPAPartialResult {
+    /// absent if the query can never be true
+    queries: Option<Vec<Vec<OPAExpr>>>,
+    #[serde(default)]
+    support: Vec<Value>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct OPAExpr {
+    #[serde(default)]
+    negated: bool,
+    terms: OPATerms,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(untagged)]
+enum OPATerms {
+    Call(Vec<OPATerm>),
+    Single(OPATerm),
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "lowercase", tag = "type", content = "value")]
+enum OPATerm {
+    Boolean(bool),
+    Null,
+    Number(serde_json::Number),
+    Ref(Vec<OPATerm>),
+    String(String),
+    Var(String),
+    #[serde(other)]
+    Other,
+}
+
+impl OPACompileResult {
+    fn try_into_filter_union(results: impl IntoIterator<Item = Self>) -> Result<AuthzFilter, String> {
+        results.into_iter().try_fold(AuthzFilter::deny_all(), |filter, result| {
+            Ok(filter.or(result.try_into_filter()?))
+        })
+    }
+
+    fn try_into_filter(self) -> Result<AuthzFilter, String> {
+        if !self.result.support.is_empty() {
+            return Err("partial evaluation produced support modules, which cannot be converted into a filter".into());
+        }
+        let queries = match self.result.queries {
+            Some(queries) => queries,
+            None => return Ok(AuthzFilter::deny_all()),
+        };
+        queries
+            .into_iter()
+            .map(|query| query.into_iter().map(OPAExpr::try_into_condition).collect())
+            .collect::<Result<_, _>>()
+            .map(AuthzFilter)
+    }
+}
+
+impl OPAExpr {
+    fn try_into_condition(self) -> Result<Condition, String> {
+        let condition = match self.terms {
+            OPATerms::Single(term) => Condition::new(
+                term.field()
+                    .ok_or_else(|| format!("unsupported term in residual policy: {term:?}"))?,
+                Comparison::Eq,
+                true,
+            ),
+            OPATerms::Call(terms) => {
+                let [operator, lhs, rhs]: [OPATerm; 3] = terms
+                    .try_into()
+                    .map_err(|terms| format!("unsupported expression in residual policy: {terms:?}"))?;
+                let comparison = match &operator {
+                    OPATerm::Ref(operator) => match operator.as_slice() {
+                        [OPATerm::Var(operator)] => match &**operator {
+                            "eq" | "equal" => Some(Comparison::Eq),
+                            "neq" => Some(Comparison::Ne),
+                            "lt" => Some(Comparison::Lt),
+                            "lte" => Some(Comparison::Le),
+                            "gt" => Some(Comparison::Gt),
+                            "gte" => Some(Comparison::Ge),
+                            _ => None,
+                        },
+                        _ => None,
+                    },
+                    _ => None,
+                }
+                .ok_or_else(|| format!("unsupported operator in residual policy: {operator:?}"))?;
+                match (lhs.field(), rhs.field(), lhs.scalar(), rhs.scalar()) {
+                    (Some(field), None, _, Some(value)) => Condition::new(field, comparison, value),
+                    (None, Some(field), Some(value), _) => Condition::new(field, comparison.flip(), value),
+                    _ => {
+                        return Err(format!(
+                            "residual policy expressions must compare a field of `{PARTIAL_EVALUATION_UNKNOWN}` with a constant, found {lhs:?} and {rhs:?}"
+                        ))
+                    }
+                }
+            }
+        };
+        Ok(if self.negated { condition.negate() } else { condition })
+    }
+}
+
+impl OPATerm {
+    /// Returns the field name if this term is a reference to a field of the unknown object.
+    fn field(&self) -> Option<String> {
+        let path = match self {
+            OPATerm::Ref(path) => path,
+            _ => return None,
+        };
+        let mut unknown = PARTIAL_EVALUATION_UNKNOWN.split('.');
+        let root = unknown.next()?;
+        match path.split_first()? {
+            (OPATerm::Var(var), rest) if var == root => {
+                let mut rest = rest.iter();
+                for segment in unknown {
+                    match rest.next()? {
+                        OPATerm::String(string) if string == segment => {}
+                        _ => return None,
+                    }
+                }
+                match (rest.next()?, rest.next()) {
+                    (OPATerm::String(field), None) => Some(field.clone()),
+                    _ => None,
+                }
+            }
+            _ => None,
         }
     }
 
-    pub fn ok_or_else<E, F: FnOnce() -> E>(self, f: F) -> Result<(), E> {
-        if self.0 {
-            Ok(())
-        } else {
-            Err(f())
+    fn scalar(&self) -> Option<Value> {
+        match self {
+            OPATerm::Boolean(bool) => Some(Value::Bool(*bool)),
+            OPATerm::Null => Some(Value::Null),
+            OPATerm::Number(number) => Some(Value::Number(number.clone())),
+            OPATerm::String(string) => Some(Value::String(string.clone())),
+            _ => None,
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actions::{Delete, Update};
+    use ::serde_json::json;
+    use ::std::marker::PhantomData;
+
+    struct Item;
+
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+
+    fn batch_event<A: ActionType>(input: Value) -> BatchEvent {
+        BatchEvent::new(&Event {
+            subject: "a",
+            action: PhantomData::<A>,
+            object: PhantomData::<Item>,
+            input,
+            context: (),
+            tenant: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn serializes_batch_input_in_order() {
+        let input = serde_json::to_value(OPABatchEvent {
+            events: vec![
+                batch_event::<Update<Item>>(json!([1])),
+                batch_event::<Delete<Item>>(json!([2])),
+            ],
+            transaction_id: Some(7),
+        })
+        .unwrap();
+
+        assert_eq!(input["transaction_id"], 7);
+        let events = input["events"].as_array().unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["action"], "update");
+        assert_eq!(events[0]["input"], json!([1]));
+        assert_eq!(events[1]["action"], "delete");
+        assert_eq!(events[1]["object"], json!({ "service": "cart", "type": "item" }));
+        assert_eq!(events[1]["input"], json!([2]));
+    }
+
+    #[test]
+    fn deserializes_batch_result_in_order() {
+        let result: OPABatchQueryResult = serde_json::from_value(json!({
+            "result": [true, { "allow": false, "reasons": ["not_owner"] }, "unexpected", { "allow": true }],
+        }))
+        .unwrap();
+        let decisions = result.0.unwrap();
+        assert_eq!(decisions.len(), 4);
+        assert!(decisions[0].allow);
+        assert!(!decisions[1].allow);
+        assert_eq!(decisions[1].reasons, vec!["not_owner".to_string()]);
+        assert_eq!(decisions[2], Decision::not_applicable());
+        assert!(decisions[3].allow);
+
+        let undefined: OPABatchQueryResult = serde_json::from_value(json!({})).unwrap();
+        assert_eq!(undefined.0, None);
+
+        assert!(serde_json::from_value::<OPABatchQueryResult>(json!({ "result": true })).is_err());
+    }
+
+    fn field(name: &str) -> Value {
+        json!({
+            "type": "ref",
+            "value": [
+                { "type": "var", "value": "input" },
+                { "type": "string", "value": "record" },
+                { "type": "string", "value": name },
+            ],
+        })
+    }
+
+    fn operator(name: &str) -> Value {
+        json!({ "type": "ref", "value": [{ "type": "var", "value": name }] })
+    }
+
+    fn filter(result: Value) -> Result<AuthzFilter, String> {
+        serde_json::from_value::<OPACompileResult>(json!({ "result": result }))
+            .unwrap()
+            .try_into_filter()
+    }
+
+    #[test]
+    fn translates_residual_policies_into_filters() {
+        let filter = filter(json!({
+            "queries": [
+                [
+                    { "terms": [operator("eq"), field("account_id"), { "type": "string", "value": "a" }] },
+                    { "terms": [operator("lt"), { "type": "number", "value": 5 }, field("quantity")] },
+                ],
+                [
+                    { "terms": field("is_public") },
+                ],
+            ],
+        }))
+        .unwrap();
+        assert_eq!(
+            filter,
+            AuthzFilter(vec![
+                vec![
+                    Condition::new("account_id", Comparison::Eq, "a"),
+                    Condition::new("quantity", Comparison::Gt, 5),
+                ],
+                vec![Condition::new("is_public", Comparison::Eq, true)],
+            ]),
+        );
+    }
+
+    #[test]
+    fn keeps_negated_residuals_distinct_from_negated_comparisons() {
+        let filter = filter(json!({
+            "queries": [[
+                { "negated": true, "terms": [operator("lt"), field("quantity"), { "type": "number", "value": 5 }] },
+                { "negated": true, "terms": field("is_archived") },
+            ]],
+        }))
+        .unwrap();
+        assert_eq!(
+            filter,
+            AuthzFilter(vec![vec![
+                Condition::new("quantity", Comparison::Lt, 5).negate(),
+                Condition::new("is_archived", Comparison::Eq, true).negate(),
+            ]]),
+        );
+    }
+
+    #[test]
+    fn partially_evaluates_boolean_and_object_decisions() {
+        assert_eq!(
+            partial_evaluation_queries("app/cart", "authz"),
+            [
+                "data.app.cart.authz == true",
+                "data.app.cart.authz.allow == true",
+                "data.app.cart.authz.response == true",
+            ],
+        );
+
+        // authz := {"allow": true, "reasons": ["owner"]} { input.record.account_id == "a" }
+        let owner = json!({
+            "queries": [[{ "terms": [operator("eq"), field("account_id"), { "type": "string", "value": "a" }] }]],
+        });
+        let filter_union = |results: [Value; 3]| {
+            OPACompileResult::try_into_filter_union(
+                results.map(|result| serde_json::from_value(json!({ "result": result })).unwrap()),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            filter_union([json!({}), owner, json!({})]),
+            AuthzFilter(vec![vec![Condition::new("account_id", Comparison::Eq, "a")]]),
+        );
+        assert!(filter_union([json!({}), json!({}), json!({})]).is_deny_all());
+    }
+
+    #[test]
+    fn translates_trivial_and_unsupported_residuals() {
+        assert!(filter(json!({})).unwrap().is_deny_all());
+        assert!(filter(json!({ "queries": [[]] })).unwrap().is_allow_all());
+        assert!(filter(json!({
+            "queries": [[{ "terms": [operator("startswith"), field("name"), { "type": "string", "value": "a" }] }]],
+        }))
+        .is_err());
+        assert!(filter(json!({
+            "queries": [[{ "terms": [operator("eq"), field("owner"), field("account_id")] }]],
+        }))
+        .is_err());
+        assert!(filter(json!({ "queries": [[]], "support": [{}] })).is_err());
+    }
+}
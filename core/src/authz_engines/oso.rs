@@ -0,0 +1,32 @@
+use crate::{Applicability, AuthzErrorKind, ClassifyAuthzError};
+use ::authzen_oso::{OsoEngine, OsoEngineError};
+use ::serde::Serialize;
+
+#[derive(Clone, Debug, Serialize)]
+struct OsoEvent<E, TransactionId> {
+    #[serde(flatten)]
+    event: E,
+    transaction_id: Option<TransactionId>,
+}
+
+event_authz_engine! {
+    impl<> for OsoEngine where { Object: 'static, TransactionId: Serialize, }
+    type Error = OsoEngineError;
+    |engine, key, event, transaction_id| engine.evaluate_serialize::<Object>(key, &OsoEvent { event, transaction_id })
+}
+
+impl Applicability for OsoEngineError {
+    /// Polar does not distinguish events without applicable rules from denied events.
+    fn is_not_applicable(&self) -> bool {
+        false
+    }
+}
+
+impl ClassifyAuthzError for OsoEngineError {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Denied(_) => AuthzErrorKind::Denied,
+            Self::Polar(..) | Self::Serialization(_) => AuthzErrorKind::Misconfigured,
+        }
+    }
+}
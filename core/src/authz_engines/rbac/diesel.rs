@@ -0,0 +1,86 @@
+use ::authzen_data_sources::diesel::{connection::Db, prelude::*};
+use ::authzen_rbac::{BindingStore, BindingStoreError, RoleBinding};
+use ::derivative::Derivative;
+use ::diesel::backend::Backend;
+use ::diesel::dsl::SqlTypeOf;
+use ::diesel::expression::{AsExpression, Expression};
+use ::diesel::expression_methods::ExpressionMethods;
+use ::diesel::helper_types as ht;
+use ::diesel::query_dsl::methods::FilterDsl;
+use ::diesel::sql_types::SqlType;
+use ::diesel_async::AsyncConnection;
+use ::std::fmt::Debug;
+use ::std::marker::PhantomData;
+
+/// A [`BindingStore`] which reads role bindings stored as the [`DbEntity`] `E`, selecting the
+/// bindings of a subject by `subject_column`.
+/// ```rs
+/// #[derive(Clone, Debug, Identifiable, Insertable, Queryable)]
+/// #[diesel(table_name = role_binding)]
+/// pub struct DbRoleBinding {
+///     pub id: Uuid,
+///     pub account_id: Uuid,
+///     pub role: String,
+///     pub tenant_id: Option<Uuid>,
+/// }
+///
+/// impl From<DbRoleBinding> for RoleBinding { ... }
+///
+/// let bindings = DieselBindingStore::<_, DbRoleBinding, _, Uuid>::new(pool, role_binding::account_id);
+/// ```
+/// Subject ids are converted into values of `subject_column` with `U: FromStr`, e.g. `Uuid`.
+/// Bindings are read with `db`, typically a connection pool, and bindings which are soft deleted are ignored.
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = "D: Clone, C: Clone"),
+    Debug(bound = "D: std::fmt::Debug, C: std::fmt::Debug")
+)]
+pub struct DieselBindingStore<D, E, C, U = String> {
+    db: D,
+    subject_column: C,
+    #[derivative(Debug = "ignore")]
+    _marker: PhantomData<fn() -> (E, U)>,
+}
+
+impl<D, E, C, U> DieselBindingStore<D, E, C, U> {
+    pub fn new(db: D, subject_column: C) -> Self {
+        Self {
+            db,
+            subject_column,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<D, E, C, U, F, TransactionId> BindingStore<TransactionId> for DieselBindingStore<D, E, C, U>
+where
+    D: Db + Send + Sync,
+    D::Backend: Backend,
+    D::AsyncConnection: AsyncConnection<Backend = D::Backend>,
+
+    E: DbEntity + Into<RoleBinding>,
+    <<E::Table as ::diesel::Table>::PrimaryKey as Expression>::SqlType: SqlType,
+    DbEntityError<<E::Raw as TryInto<E>>::Error>: std::error::Error + Send + Sync + 'static,
+
+    C: Clone + Debug + Expression + ExpressionMethods + Send + Sync,
+    SqlTypeOf<C>: SqlType,
+    U: AsExpression<SqlTypeOf<C>> + Debug + std::str::FromStr + Send,
+    U::Err: std::error::Error + Send + Sync + 'static,
+    E::Table: FilterDsl<ht::EqAny<C, Vec<U>>, Output = F>,
+    F: for<'query> IsNotDeleted<'query, D::AsyncConnection, E::Raw, E::Raw>,
+
+    TransactionId: Sync,
+{
+    async fn bindings(
+        &self,
+        subject_id: &str,
+        _: Option<&TransactionId>,
+    ) -> Result<Vec<RoleBinding>, BindingStoreError> {
+        let subject_id = subject_id.parse::<U>().map_err(BindingStoreError::new)?;
+        let bindings = E::get_by_column(&self.db, self.subject_column.clone(), [subject_id])
+            .await
+            .map_err(BindingStoreError::new)?;
+        Ok(bindings.into_iter().map(Into::into).collect())
+    }
+}
@@ -0,0 +1,32 @@
+#[cfg(feature = "diesel-data-source")]
+mod diesel;
+
+#[cfg(feature = "diesel-data-source")]
+pub use self::diesel::*;
+
+use crate::{Applicability, AuthzErrorKind, ClassifyAuthzError};
+use ::authzen_rbac::{BindingStore, RbacEngine, RbacError};
+
+event_authz_engine! {
+    impl<B> for RbacEngine<B> where { B: BindingStore<TransactionId>, }
+    type Error = RbacError;
+    |engine, key, event, transaction_id| engine.evaluate_serialize(key, &event, transaction_id.as_ref()).await
+}
+
+impl Applicability for RbacError {
+    /// Role-based engines deny events which no role grants, so they do not distinguish events without
+    /// applicable roles from denied events.
+    fn is_not_applicable(&self) -> bool {
+        false
+    }
+}
+
+impl ClassifyAuthzError for RbacError {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Denied(_) => AuthzErrorKind::Denied,
+            Self::BindingStore(_) => AuthzErrorKind::Unavailable,
+            Self::MissingId(_) | Self::Serialization(_) => AuthzErrorKind::Misconfigured,
+        }
+    }
+}
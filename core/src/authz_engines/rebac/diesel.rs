@@ -0,0 +1,181 @@
+use super::TupleWriter;
+use ::authzen_data_sources::{DataSource, TransactionalDataSource};
+use ::authzen_rebac::{ObjectRef, RelationTuple, Subject, TransactionTuples, TupleChange, TupleStore, TupleStoreError};
+use ::derivative::Derivative;
+use ::diesel::prelude::*;
+use ::diesel_async::{AsyncPgConnection, RunQueryDsl};
+
+mod schema {
+    ::diesel::table! {
+        relation_tuples (namespace, object_id, relation, subject) {
+            namespace -> Text,
+            object_id -> Text,
+            relation -> Text,
+            subject -> Text,
+        }
+    }
+}
+
+use schema::relation_tuples;
+
+/// A [`TupleStore`] which keeps its tuples in a postgres table `relation_tuples`, created with
+/// ```sql
+/// create table relation_tuples (
+///     namespace text not null,
+///     object_id text not null,
+///     relation  text not null,
+///     subject   text not null, -- e.g. `3` or `cart.cart:7#editor`
+///     primary key (namespace, object_id, relation, subject)
+/// );
+/// ```
+/// Tuples are read with `db`, typically a connection pool. Tuples written with [`TupleWriter::write_tuples`]
+/// are inserted and deleted using the connection of the provided data source, so that they are committed or
+/// rolled back along with its transaction. Until the transaction is committed, they are only visible to reads
+/// made with its transaction id, and the changes staged within a transaction which is rolled back are discarded.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "D: Clone"), Debug(bound = "D: std::fmt::Debug"))]
+pub struct DieselTupleStore<D: DataSource> {
+    db: D,
+    #[derivative(Debug = "ignore")]
+    pending: TransactionTuples<D::TransactionId>,
+}
+
+impl<D: DataSource> DieselTupleStore<D> {
+    pub fn new(db: D) -> Self {
+        Self {
+            db,
+            pending: Default::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl<D> TupleStore<D::TransactionId> for DieselTupleStore<D>
+where
+    D: TransactionalDataSource<AsyncConnection = AsyncPgConnection, Error = ::diesel::result::Error>,
+{
+    async fn read(
+        &self,
+        object: &ObjectRef,
+        relation: &str,
+        transaction_id: Option<&D::TransactionId>,
+    ) -> Result<Vec<Subject>, TupleStoreError> {
+        let (namespace, object_id, relation_) = (object.namespace.clone(), object.id.clone(), relation.to_string());
+        let rows = self
+            .db
+            .query(move |conn| {
+                Box::pin(
+                    relation_tuples::table
+                        .filter(relation_tuples::namespace.eq(namespace))
+                        .filter(relation_tuples::object_id.eq(object_id))
+                        .filter(relation_tuples::relation.eq(relation_))
+                        .select(relation_tuples::subject)
+                        .load::<String>(conn),
+                )
+            })
+            .await
+            .map_err(TupleStoreError::new)?;
+
+        let mut subjects = rows
+            .iter()
+            .map(|subject| subject.parse())
+            .collect::<Result<Vec<Subject>, _>>()
+            .map_err(TupleStoreError::new)?;
+        self.pending.apply(transaction_id, object, relation, &mut subjects);
+        Ok(subjects)
+    }
+
+    async fn object_ids(
+        &self,
+        namespace: &str,
+        transaction_id: Option<&D::TransactionId>,
+    ) -> Result<Vec<String>, TupleStoreError> {
+        let namespace_ = namespace.to_string();
+        let mut ids = self
+            .db
+            .query(move |conn| {
+                Box::pin(
+                    relation_tuples::table
+                        .filter(relation_tuples::namespace.eq(namespace_))
+                        .select(relation_tuples::object_id)
+                        .distinct()
+                        .load::<String>(conn),
+                )
+            })
+            .await
+            .map_err(TupleStoreError::new)?;
+        self.pending.extend_object_ids(transaction_id, namespace, &mut ids);
+        Ok(ids)
+    }
+}
+
+#[async_trait]
+impl<D, DS> TupleWriter<DS> for DieselTupleStore<D>
+where
+    D: DataSource + Send + Sync,
+    DS: TransactionalDataSource<
+        AsyncConnection = AsyncPgConnection,
+        Error = ::diesel::result::Error,
+        TransactionId = D::TransactionId,
+    >,
+    D::TransactionId: 'static,
+{
+    async fn write_tuples(&self, data_source: &DS, changes: Vec<TupleChange>) -> Result<(), TupleStoreError> {
+        let rows = changes.clone();
+        data_source
+            .query(move |conn| {
+                Box::pin(async move {
+                    for change in rows {
+                        match change {
+                            TupleChange::Write(tuple) => {
+                                ::diesel::insert_into(relation_tuples::table)
+                                    .values(values(tuple))
+                                    .on_conflict_do_nothing()
+                                    .execute(conn)
+                                    .await?
+                            }
+                            TupleChange::Delete(tuple) => {
+                                let (namespace, object_id, relation, subject) = values(tuple);
+                                ::diesel::delete(
+                                    relation_tuples::table
+                                        .filter(namespace)
+                                        .filter(object_id)
+                                        .filter(relation)
+                                        .filter(subject),
+                                )
+                                .execute(conn)
+                                .await?
+                            }
+                        };
+                    }
+                    Ok::<_, ::diesel::result::Error>(())
+                })
+            })
+            .await
+            .map_err(TupleStoreError::new)?;
+
+        if let Some(transaction_id) = data_source.transaction_id() {
+            let staged = self.pending.stage_scoped(transaction_id, changes);
+            // the written tuples are visible to reads made with `db` once the transaction is committed
+            data_source.tx_commit(move || drop(staged)).await;
+        }
+        Ok(())
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn values(
+    tuple: RelationTuple,
+) -> (
+    ::diesel::dsl::Eq<relation_tuples::namespace, String>,
+    ::diesel::dsl::Eq<relation_tuples::object_id, String>,
+    ::diesel::dsl::Eq<relation_tuples::relation, String>,
+    ::diesel::dsl::Eq<relation_tuples::subject, String>,
+) {
+    (
+        relation_tuples::namespace.eq(tuple.object.namespace),
+        relation_tuples::object_id.eq(tuple.object.id),
+        relation_tuples::relation.eq(tuple.relation),
+        relation_tuples::subject.eq(tuple.subject.to_string()),
+    )
+}
@@ -0,0 +1,223 @@
+#[cfg(feature = "diesel-postgres")]
+mod diesel;
+
+#[cfg(feature = "diesel-postgres")]
+pub use self::diesel::*;
+
+use crate::{Applicability, AuthzErrorKind, ClassifyAuthzError};
+use ::authzen_data_sources::TransactionalDataSource;
+use ::authzen_rebac::{InMemoryTupleStore, RebacEngine, RebacError, TupleChange, TupleStore, TupleStoreError};
+
+event_authz_engine! {
+    impl<S> for RebacEngine<S> where { S: TupleStore<TransactionId>, }
+    type Error = RebacError;
+    |engine, key, event, transaction_id| engine.evaluate_serialize(key, &event, transaction_id.as_ref()).await
+}
+
+impl Applicability for RebacError {
+    /// Events whose object type is not a namespace of the schema, or whose action is not a relation
+    /// of that namespace, are not applicable.
+    fn is_not_applicable(&self) -> bool {
+        matches!(self, Self::UnknownRelation { .. })
+    }
+}
+
+impl ClassifyAuthzError for RebacError {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Denied(_) => AuthzErrorKind::Denied,
+            Self::TupleStore(_) => AuthzErrorKind::Unavailable,
+            Self::UnknownRelation { .. } | Self::MaxDepth(_) | Self::MissingId(_) | Self::Serialization(_) => {
+                AuthzErrorKind::Misconfigured
+            }
+        }
+    }
+}
+
+/// Writes relation tuples using a data source, such that tuples written within a transaction are
+/// visible to a [`RebacEngine`] authorizing events within that transaction before they are visible elsewhere.
+///
+/// Changes written within a transaction are staged under its transaction id until the transaction ends.
+/// They are only applied once the transaction has been committed (see [`TransactionalDataSource::tx_commit`]),
+/// while the changes staged within a transaction which is rolled back are discarded along with it.
+/// ```rs
+/// db.tx(|tx| async move {
+///     let cart = DbCart::insert(&tx, [cart]).await?.pop().unwrap();
+///     tuple_store.write_tuples(&tx, vec![
+///         TupleChange::Write(format!("cart.cart:{}#owner@{}", cart.id, account_id).parse()?),
+///     ]).await?;
+///     ctx.try_create::<Item, _>(&tx, [item]).await
+/// }.scope_boxed()).await
+/// ```
+#[async_trait]
+pub trait TupleWriter<D: TransactionalDataSource> {
+    async fn write_tuples(&self, data_source: &D, changes: Vec<TupleChange>) -> Result<(), TupleStoreError>;
+}
+
+/// Changes written within a transaction are applied to the store's tuples once the transaction has been committed.
+#[async_trait]
+impl<D> TupleWriter<D> for InMemoryTupleStore<D::TransactionId>
+where
+    D: TransactionalDataSource,
+    D::TransactionId: 'static,
+{
+    async fn write_tuples(&self, data_source: &D, changes: Vec<TupleChange>) -> Result<(), TupleStoreError> {
+        let Some(transaction_id) = data_source.transaction_id() else {
+            self.write(changes, None);
+            return Ok(());
+        };
+        let staged = self.write_scoped(changes, transaction_id);
+        let store = self.clone();
+        data_source.tx_commit(move || store.commit_staged(staged)).await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::authzen_data_sources::{
+        DataSource, DataSourceConnection, TxCleanupError, TxCleanupFn, TxCommit, TxCommitFn, TxFn,
+    };
+    use ::authzen_rebac::ObjectRef;
+    use ::derivative::Derivative;
+    use ::futures::{FutureExt, TryFutureExt};
+    use ::scoped_futures::{ScopedBoxFuture, ScopedFutureExt};
+    use ::std::fmt::Debug;
+    use ::std::sync::{Arc, Mutex};
+
+    type CleanupFns = Arc<Mutex<Vec<Box<dyn for<'r> TxCleanupFn<'r, (), TxCleanupError, u64>>>>>;
+
+    /// Runs the cleanup functions and then the commit functions of transactions whose callbacks succeed,
+    /// like the diesel data sources.
+    #[derive(Clone, Default, Derivative)]
+    #[derivative(Debug)]
+    struct Store {
+        transaction_id: Option<u64>,
+        #[derivative(Debug = "ignore")]
+        cleanup_fns: CleanupFns,
+        #[derivative(Debug = "ignore")]
+        commit_fns: TxCommit,
+    }
+
+    impl DataSource for Store {
+        type Backend = ();
+        type Error = TxCleanupError;
+        type TransactionId = u64;
+
+        fn transaction_id(&self) -> Option<u64> {
+            self.transaction_id
+        }
+    }
+
+    #[async_trait]
+    impl TransactionalDataSource for Store {
+        type AsyncConnection = ();
+        type Connection<'r> = &'r ();
+        type TxConnection<'r> = Store;
+
+        async fn query<'a, F, T, E>(&self, f: F) -> Result<T, E>
+        where
+            F: for<'r> FnOnce(&'r mut ()) -> ScopedBoxFuture<'a, 'r, Result<T, E>> + Send + 'a,
+            E: Debug + From<TxCleanupError> + Send + 'a,
+            T: Send + 'a,
+        {
+            f(&mut ()).await
+        }
+
+        async fn with_tx_connection<'a, F, T, E>(&self, f: F) -> Result<T, E>
+        where
+            F: for<'r> FnOnce(&'r mut ()) -> ScopedBoxFuture<'a, 'r, Result<T, E>> + Send + 'a,
+            E: Debug + From<TxCleanupError> + Send + 'a,
+            T: Send + 'a,
+        {
+            f(&mut ()).await
+        }
+
+        async fn tx_cleanup<F, E>(&self, f: F)
+        where
+            F: for<'r> TxCleanupFn<'r, (), E, u64>,
+            E: Into<TxCleanupError> + 'static,
+        {
+            self.cleanup_fns
+                .lock()
+                .unwrap()
+                .push(Box::new(|x| f(x).map_err(Into::into).boxed()));
+        }
+
+        async fn tx_commit<F>(&self, f: F)
+        where
+            F: TxCommitFn,
+        {
+            match self.transaction_id {
+                Some(_) => self.commit_fns.lock().unwrap().push(Box::new(f)),
+                None => f(),
+            }
+        }
+
+        async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
+        where
+            F: for<'r> TxFn<'a, Store, ScopedBoxFuture<'a, 'r, Result<T, E>>>,
+            E: Debug + From<TxCleanupError> + Send + 'a,
+            T: Send + 'a,
+            'life0: 'a,
+        {
+            let transaction_id = self.transaction_id.map(|id| id + 1).unwrap_or_default();
+            let tx_connection = Store {
+                transaction_id: Some(transaction_id),
+                ..Default::default()
+            };
+            let value = callback.call_tx_fn(tx_connection.clone()).await?;
+            let cleanup_fns = std::mem::take(&mut *tx_connection.cleanup_fns.lock().unwrap());
+            for cleanup_fn in cleanup_fns {
+                cleanup_fn(&DataSourceConnection::new(&mut (), Some(transaction_id))).await?;
+            }
+            let commit_fns = std::mem::take(&mut *tx_connection.commit_fns.lock().unwrap());
+            for commit_fn in commit_fns {
+                commit_fn();
+            }
+            Ok(value)
+        }
+    }
+
+    async fn owners(tuple_store: &InMemoryTupleStore<u64>, transaction_id: Option<u64>) -> Vec<String> {
+        let object = ObjectRef::new("cart.cart", "1");
+        let subjects = tuple_store.read(&object, "owner", transaction_id.as_ref()).await;
+        subjects.unwrap().iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn applies_tuples_written_within_transactions_once_they_are_committed() {
+        let tuple_store = InMemoryTupleStore::<u64>::default();
+        let write = |owner: &'static str, fail: bool| {
+            let tuple_store = tuple_store.clone();
+            futures::executor::block_on(Store::default().tx(move |tx| {
+                async move {
+                    let tuple = format!("cart.cart:1#owner@{owner}").parse().unwrap();
+                    tuple_store
+                        .write_tuples(&tx, vec![TupleChange::Write(tuple)])
+                        .await
+                        .unwrap();
+                    assert!(owners(&tuple_store, tx.transaction_id)
+                        .await
+                        .contains(&owner.to_string()));
+                    assert!(!owners(&tuple_store, None).await.contains(&owner.to_string()));
+                    match fail {
+                        true => Err(TxCleanupError::new("rolled back")),
+                        false => Ok(()),
+                    }
+                }
+                .scope_boxed()
+            }))
+        };
+        let owners = |transaction_id: Option<u64>| futures::executor::block_on(owners(&tuple_store, transaction_id));
+
+        write("1", false).unwrap();
+        assert_eq!(owners(None), vec!["1"]);
+        assert_eq!(owners(Some(0)), vec!["1"]);
+
+        write("2", true).unwrap_err();
+        assert_eq!(owners(None), vec!["1"]);
+        assert_eq!(owners(Some(0)), vec!["1"]);
+    }
+}
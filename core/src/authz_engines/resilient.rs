@@ -0,0 +1,432 @@
+use super::cached::{cache_key, Cache};
+use crate::{ActionType, Applicability, AuthzEngine, AuthzErrorKind, ClassifyAuthzError, Event, ObjectType, Tenant};
+use ::derivative::Derivative;
+use ::serde::Serialize;
+use ::std::collections::hash_map::RandomState;
+use ::std::collections::HashMap;
+use ::std::hash::BuildHasher;
+use ::std::sync::{Arc, Mutex};
+use ::std::time::{Duration, Instant};
+
+/// Wraps an [`AuthzEngine`], retrying checks which fail because the engine is unavailable (see
+/// [`AuthzErrorKind::Unavailable`]) and falling back to a configurable outcome once retries are exhausted.
+///
+/// - each check is bounded by a total time budget shared across all of its attempts
+/// - retries are delayed with exponential backoff and jitter
+/// - once a number of consecutive checks have failed, a circuit breaker opens and checks fail immediately
+///   without reaching the engine until the breaker's reset timeout has elapsed, after which checks
+///   are attempted again
+/// - checks which ultimately fail because the engine is unavailable are decided by the [`Fallback`]
+///   configured for the action type, which is [`Fallback::FailClosed`] unless otherwise specified
+///
+/// Errors which do not indicate that the engine is unavailable, including denials, are returned
+/// immediately and are never subject to a fallback.
+#[derive(Clone, Derivative)]
+#[derivative(Debug)]
+pub struct ResilientEngine<AE> {
+    engine: AE,
+    retries: usize,
+    backoff: Duration,
+    max_backoff: Duration,
+    budget: Duration,
+    failure_threshold: usize,
+    reset_timeout: Duration,
+    fallbacks: HashMap<&'static str, Fallback>,
+    last_known_ttl: Duration,
+    last_known_max_entries: usize,
+    #[derivative(Debug = "ignore")]
+    state: Arc<Mutex<ResilienceState>>,
+}
+
+/// How a check is decided when the engine is unavailable.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, IsVariant, PartialEq)]
+pub enum Fallback {
+    /// Return the error, rejecting the action.
+    #[default]
+    FailClosed,
+    /// Allow the action.
+    FailOpen,
+    /// Reuse the last outcome reached by the engine for an identical event if one is available,
+    /// otherwise return the error.
+    LastKnownDecision,
+}
+
+/// The outcome of a check made with a [`ResilientEngine`].
+#[derive(Clone, Debug, Eq, IsVariant, PartialEq, Unwrap)]
+pub enum Resilient<T> {
+    /// Outcome reached by the wrapped engine.
+    Decided(T),
+    /// The engine was unavailable and the action's fallback is [`Fallback::FailOpen`].
+    FailedOpen,
+    /// The engine was unavailable and the last outcome it reached for an identical event was reused.
+    LastKnown(T),
+}
+
+/// Error returned from a [`ResilientEngine`].
+#[derive(Clone, Debug, IsVariant, thiserror::Error)]
+pub enum ResilienceError<E> {
+    #[error("{0}")]
+    Engine(E),
+    /// The check's time budget was exhausted before the engine reached a decision.
+    #[error("authorization engine did not reach a decision within the time budget")]
+    Timeout,
+    /// The circuit breaker is open, the engine was not queried.
+    #[error("authorization engine circuit breaker is open")]
+    CircuitOpen,
+}
+
+impl<E: ClassifyAuthzError> ClassifyAuthzError for ResilienceError<E> {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Engine(err) => err.authz_error_kind(),
+            Self::Timeout | Self::CircuitOpen => AuthzErrorKind::Unavailable,
+        }
+    }
+}
+
+impl<E: Applicability> Applicability for ResilienceError<E> {
+    fn is_not_applicable(&self) -> bool {
+        match self {
+            Self::Engine(err) => err.is_not_applicable(),
+            Self::Timeout | Self::CircuitOpen => false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ResilienceState {
+    consecutive_failures: usize,
+    opened_at: Option<Instant>,
+    last_known: Cache,
+}
+
+impl<AE> ResilientEngine<AE> {
+    pub const DEFAULT_RETRIES: usize = 2;
+    pub const DEFAULT_BACKOFF: Duration = Duration::from_millis(50);
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(1);
+    pub const DEFAULT_BUDGET: Duration = Duration::from_secs(5);
+    pub const DEFAULT_FAILURE_THRESHOLD: usize = 5;
+    pub const DEFAULT_RESET_TIMEOUT: Duration = Duration::from_secs(30);
+    pub const DEFAULT_LAST_KNOWN_TTL: Duration = Duration::from_secs(300);
+    pub const DEFAULT_LAST_KNOWN_MAX_ENTRIES: usize = 10_000;
+
+    pub fn new(engine: AE) -> Self {
+        Self {
+            engine,
+            retries: Self::DEFAULT_RETRIES,
+            backoff: Self::DEFAULT_BACKOFF,
+            max_backoff: Self::DEFAULT_MAX_BACKOFF,
+            budget: Self::DEFAULT_BUDGET,
+            failure_threshold: Self::DEFAULT_FAILURE_THRESHOLD,
+            reset_timeout: Self::DEFAULT_RESET_TIMEOUT,
+            fallbacks: Default::default(),
+            last_known_ttl: Self::DEFAULT_LAST_KNOWN_TTL,
+            last_known_max_entries: Self::DEFAULT_LAST_KNOWN_MAX_ENTRIES,
+            state: Default::default(),
+        }
+    }
+
+    /// The maximum number of times a check is retried after its first attempt.
+    pub fn retries(mut self, retries: usize) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// The delay before the first retry, doubling with each subsequent retry up to `max_backoff`.
+    /// Each delay is randomly reduced by up to half to avoid synchronized retries.
+    pub fn backoff(mut self, backoff: Duration, max_backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// The total time a single check may take across all of its attempts and backoffs.
+    pub fn budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Opens the circuit breaker after `failure_threshold` consecutive checks fail because the engine
+    /// is unavailable, keeping it open for `reset_timeout`. A `failure_threshold` of 0 disables the breaker.
+    pub fn circuit_breaker(mut self, failure_threshold: usize, reset_timeout: Duration) -> Self {
+        self.failure_threshold = failure_threshold;
+        self.reset_timeout = reset_timeout;
+        self
+    }
+
+    /// Sets the fallback used for action `A` when the engine is unavailable.
+    pub fn fallback<A: ?Sized + ActionType>(mut self, fallback: Fallback) -> Self {
+        self.fallbacks.insert(A::TYPE, fallback);
+        self
+    }
+
+    /// How long outcomes are retained for use by [`Fallback::LastKnownDecision`] and the maximum
+    /// number retained at once.
+    pub fn last_known(mut self, ttl: Duration, max_entries: usize) -> Self {
+        self.last_known_ttl = ttl;
+        self.last_known_max_entries = max_entries;
+        self
+    }
+
+    /// The wrapped authorization engine.
+    pub fn engine(&self) -> &AE {
+        &self.engine
+    }
+
+    /// Whether the circuit breaker is currently open.
+    pub fn is_circuit_open(&self) -> bool {
+        self.lock()
+            .opened_at
+            .map(|opened_at| opened_at.elapsed() < self.reset_timeout)
+            .unwrap_or_default()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ResilienceState> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn fallback_for<A: ?Sized + ActionType>(&self) -> Fallback {
+        self.fallbacks.get(A::TYPE).copied().unwrap_or_default()
+    }
+
+    fn record_success(&self) {
+        let mut state = self.lock();
+        state.consecutive_failures = 0;
+        state.opened_at = None;
+    }
+
+    fn record_failure(&self) {
+        let mut state = self.lock();
+        state.consecutive_failures += 1;
+        if self.failure_threshold > 0 && state.consecutive_failures >= self.failure_threshold {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Delay before retry number `retry` (starting at 0), with up to half of it removed at random.
+    fn delay(&self, retry: usize) -> Duration {
+        let delay = self
+            .backoff
+            .saturating_mul(1 << retry.min(16) as u32)
+            .min(self.max_backoff);
+        let jitter = (RandomState::new().hash_one(retry) % 1_000) as u32;
+        delay - delay / 2 * jitter / 1_000
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, AE>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for ResilientEngine<AE>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Clone + Send + Serialize + Sync,
+    Action: ?Sized + ActionType + Sync,
+    Object: ?Sized + ObjectType + Sync,
+    Input: Serialize + Sync,
+    Context: Clone + Send + Serialize + Sync,
+    TransactionId: Clone + Send + Sync,
+    AE: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Send + Sync,
+    AE::Ok: Clone + Sync + 'static,
+    AE::Error: ClassifyAuthzError,
+{
+    type Ok = Resilient<AE::Ok>;
+    type Error = ResilienceError<AE::Error>;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let fallback = self.fallback_for::<Action>();
+        let key = match (fallback, &transaction_id) {
+            (Fallback::LastKnownDecision, None) => {
+                cache_key::<Subject, Action, Object, Input, Context>(&subject, input, &context, tenant.as_ref())
+            }
+            _ => None,
+        };
+
+        let err = if self.is_circuit_open() {
+            ResilienceError::CircuitOpen
+        } else {
+            let deadline = Instant::now() + self.budget;
+            let mut retry = 0;
+            let err = loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                let attempt = self.engine.can_act(
+                    subject.clone(),
+                    input,
+                    context.clone(),
+                    tenant.clone(),
+                    transaction_id.clone(),
+                );
+                let err = match tokio::time::timeout(remaining, attempt).await {
+                    Ok(Ok(ok)) => {
+                        self.record_success();
+                        if let Some(key) = &key {
+                            self.lock().last_known.insert(
+                                key,
+                                ok.clone(),
+                                self.last_known_ttl,
+                                self.last_known_max_entries,
+                            );
+                        }
+                        return Ok(Resilient::Decided(ok));
+                    }
+                    Ok(Err(err)) if err.authz_error_kind().is_unavailable() => ResilienceError::Engine(err),
+                    Ok(Err(err)) => {
+                        self.record_success();
+                        return Err(ResilienceError::Engine(err));
+                    }
+                    Err(_) => break ResilienceError::Timeout,
+                };
+                if retry >= self.retries {
+                    break err;
+                }
+                let delay = self.delay(retry);
+                if Instant::now() + delay >= deadline {
+                    break err;
+                }
+                tokio::time::sleep(delay).await;
+                retry += 1;
+            };
+            self.record_failure();
+            err
+        };
+
+        match fallback {
+            Fallback::FailClosed => Err(err),
+            Fallback::FailOpen => Ok(Resilient::FailedOpen),
+            Fallback::LastKnownDecision => key
+                .and_then(|key| self.lock().last_known.get::<AE::Ok>(&key, self.last_known_ttl))
+                .map(Resilient::LastKnown)
+                .ok_or(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Unavailable for the first `failures` checks, then allows every check.
+    #[derive(Default)]
+    struct FlakyEngine {
+        failures: usize,
+        calls: AtomicUsize,
+    }
+
+    #[derive(Debug)]
+    struct Unavailable;
+
+    impl ClassifyAuthzError for Unavailable {
+        fn authz_error_kind(&self) -> AuthzErrorKind {
+            AuthzErrorKind::Unavailable
+        }
+    }
+
+    #[async_trait]
+    impl<Action, Object> AuthzEngine<String, Action, Object, (), (), ()> for FlakyEngine
+    where
+        Action: ?Sized + Send + Sync,
+        Object: ?Sized + Send + Sync,
+    {
+        type Ok = usize;
+        type Error = Unavailable;
+
+        async fn can_act(
+            &self,
+            _: String,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<Self::Ok, Self::Error>
+        where
+            Action: 'async_trait,
+            Object: 'async_trait,
+        {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst);
+            if calls < self.failures {
+                Err(Unavailable)
+            } else {
+                Ok(calls)
+            }
+        }
+    }
+
+    struct Item;
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+    struct Read;
+    impl ActionType for Read {
+        const TYPE: &'static str = "read";
+    }
+    struct Delete;
+    impl ActionType for Delete {
+        const TYPE: &'static str = "delete";
+    }
+
+    fn can_act<A: ActionType + Send + Sync>(
+        engine: &ResilientEngine<FlakyEngine>,
+        tenant: Option<&str>,
+    ) -> Result<Resilient<usize>, ResilienceError<Unavailable>> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(AuthzEngine::<_, A, Item, _, _, _>::can_act(
+            engine,
+            "a".into(),
+            &(),
+            (),
+            tenant.map(Tenant::from),
+            None,
+        ))
+    }
+
+    #[test]
+    fn retries_and_falls_back() {
+        let flaky = |failures| FlakyEngine {
+            failures,
+            ..Default::default()
+        };
+        let engine = ResilientEngine::new(flaky(2)).backoff(Duration::ZERO, Duration::ZERO);
+        assert_eq!(can_act::<Read>(&engine, None).unwrap(), Resilient::Decided(2));
+
+        let engine = ResilientEngine::new(flaky(3))
+            .backoff(Duration::ZERO, Duration::ZERO)
+            .circuit_breaker(1, Duration::from_secs(60))
+            .fallback::<Read>(Fallback::FailOpen);
+        assert!(can_act::<Delete>(&engine, None).unwrap_err().is_engine());
+        assert!(engine.is_circuit_open());
+        assert!(can_act::<Delete>(&engine, None).unwrap_err().is_circuit_open());
+        assert!(can_act::<Read>(&engine, None).unwrap().is_failed_open());
+        assert_eq!(engine.engine().calls.load(Ordering::SeqCst), 3);
+
+        let engine = ResilientEngine::new(flaky(0))
+            .retries(0)
+            .circuit_breaker(0, Duration::ZERO)
+            .fallback::<Read>(Fallback::LastKnownDecision);
+        assert_eq!(can_act::<Read>(&engine, Some("acme")).unwrap(), Resilient::Decided(0));
+        let engine = ResilientEngine {
+            engine: flaky(2),
+            ..engine
+        };
+        assert!(can_act::<Read>(&engine, Some("globex")).unwrap_err().is_engine());
+        assert_eq!(can_act::<Read>(&engine, Some("acme")).unwrap(), Resilient::LastKnown(0));
+    }
+}
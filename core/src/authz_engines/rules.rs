@@ -0,0 +1,175 @@
+use crate::{
+    ActionType, Applicability, AuthzEngine, AuthzErrorKind, BatchAuthzEngine, BatchEvent, ClassifyAuthzError, Decision,
+    Event, ObjectType, Tenant,
+};
+use ::authzen_rules::{RuleKey, RulesEngine, RulesError};
+use ::serde::Serialize;
+use ::std::fmt::Debug;
+
+#[derive(Clone, Debug, Serialize)]
+struct RulesEvent<E, TransactionId> {
+    #[serde(flatten)]
+    event: E,
+    transaction_id: Option<TransactionId>,
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for RulesEngine
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Debug + Send + Serialize + Sync,
+    Action: ?Sized + ActionType + Send + Sync,
+    Object: ?Sized + ObjectType + Send + Sync,
+    Input: Debug + Serialize + Send + Sync,
+    Context: Debug + Send + Serialize + Sync,
+    TransactionId: Debug + Send + Serialize + Sync,
+{
+    type Ok = ();
+    type Error = RulesError;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        self.evaluate_serialize(
+            RuleKey::new(Object::SERVICE, Object::TYPE, Action::TYPE),
+            &RulesEvent {
+                event: Event {
+                    action: std::marker::PhantomData::<Action>,
+                    object: std::marker::PhantomData::<Object>,
+                    subject,
+                    input,
+                    context,
+                    tenant,
+                },
+                transaction_id,
+            },
+        )
+    }
+}
+
+#[async_trait]
+impl<TransactionId> BatchAuthzEngine<TransactionId> for RulesEngine
+where
+    TransactionId: Debug + Send + Serialize,
+{
+    type Ok = Decision;
+    type Error = RulesError;
+
+    async fn can_act_batch(
+        &self,
+        events: Vec<BatchEvent>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Vec<Self::Ok>, Self::Error>
+    where
+        TransactionId: 'async_trait,
+    {
+        let transaction_id =
+            serde_json::to_value(transaction_id).map_err(|err| RulesError::Serialization(err.into()))?;
+        Ok(events
+            .into_iter()
+            .map(
+                |BatchEvent {
+                     service,
+                     ty,
+                     action,
+                     mut value,
+                 }| {
+                    if let Some(value) = value.as_object_mut() {
+                        value.insert("transaction_id".into(), transaction_id.clone());
+                    }
+                    match self.evaluate(RuleKey::new(service, ty, action), &value) {
+                        Ok(()) => Decision::allow(),
+                        Err(err) => Decision::builder().allow(false).message(err.to_string()).build(),
+                    }
+                },
+            )
+            .collect())
+    }
+}
+
+impl Applicability for RulesError {
+    fn is_not_applicable(&self) -> bool {
+        matches!(self, Self::Unregistered(_))
+    }
+}
+
+impl ClassifyAuthzError for RulesError {
+    fn authz_error_kind(&self) -> AuthzErrorKind {
+        match self {
+            Self::Denied(_) | Self::Unregistered(_) => AuthzErrorKind::Denied,
+            Self::Serialization(_) => AuthzErrorKind::Misconfigured,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actions::{Delete, Update};
+    use ::serde_json::json;
+    use ::std::marker::PhantomData;
+
+    struct Item;
+
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+
+    fn batch_event<A: ActionType>(subject: &str) -> BatchEvent {
+        BatchEvent::new(&Event {
+            subject,
+            action: PhantomData::<A>,
+            object: PhantomData::<Item>,
+            input: json!([1]),
+            context: (),
+            tenant: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn evaluates_batch_in_order() {
+        let engine = RulesEngine::new()
+            .rule("cart", "item", "update", |event| event["subject"] != "guest")
+            .rule("cart", "item", "delete", |event| {
+                event["subject"] == "admin" && event["transaction_id"] == 1
+            });
+
+        let decisions = futures::executor::block_on(BatchAuthzEngine::can_act_batch(
+            &engine,
+            vec![
+                batch_event::<Update<Item>>("admin"),
+                batch_event::<Delete<Item>>("admin"),
+                batch_event::<Update<Item>>("guest"),
+                batch_event::<Delete<Item>>("user"),
+            ],
+            Some(1),
+        ))
+        .unwrap();
+        assert_eq!(
+            decisions.iter().map(|decision| decision.allow).collect::<Vec<_>>(),
+            vec![true, true, false, false],
+        );
+        assert!(decisions[2].message.is_some());
+
+        let decisions =
+            futures::executor::block_on(engine.can_act_batch(vec![batch_event::<Delete<Item>>("admin")], Some(2)))
+                .unwrap();
+        assert!(!decisions[0].allow);
+    }
+}
@@ -0,0 +1,498 @@
+use crate::{ActionType, AuthzEngine, AuthzErrorKind, ClassifyAuthzError, Event, ObjectType, Tenant};
+use ::futures::channel::oneshot;
+use ::std::sync::atomic::{AtomicU64, Ordering};
+use ::std::sync::Arc;
+
+/// Enforces the decisions of a `Primary` authorization engine while also evaluating every event with
+/// a `Candidate` engine, reporting any events for which the two disagree. Useful for safely rolling
+/// out new policies, e.g. when migrating to a new OPA package.
+///
+/// The candidate is evaluated in a task spawned onto the current tokio runtime with owned clones of the
+/// event, so it runs to completion without ever delaying the outcome of the primary, which is returned
+/// as soon as it is reached. Candidates which have not finished yet are counted (see [`ShadowEngine::pending`]).
+/// The candidate's outcome never affects the result.
+///
+/// Outcomes are compared by whether the event was allowed or denied (see [`AuthzErrorKind::Denied`]).
+/// Events for which either engine was unavailable or misconfigured are not compared, failures of the
+/// candidate are counted separately (see [`ShadowEngine::candidate_failures`]).
+/// Disagreements are counted (see [`ShadowEngine::disagreements`]) and, with feature `tracing`,
+/// emitted as `WARN` level events with target `authzen::shadow` including the serialized event.
+#[derive(Clone, Debug)]
+pub struct ShadowEngine<Primary, Candidate> {
+    primary: Primary,
+    candidate: Arc<Candidate>,
+    counters: Arc<ShadowCounters>,
+}
+
+#[derive(Debug, Default)]
+struct ShadowCounters {
+    evaluations: AtomicU64,
+    disagreements: AtomicU64,
+    candidate_failures: AtomicU64,
+    pending: AtomicU64,
+}
+
+/// Decrements [`ShadowCounters::pending`] once a spawned candidate evaluation ends, even if it is cancelled.
+struct Pending(Arc<ShadowCounters>);
+
+impl Pending {
+    fn new(counters: Arc<ShadowCounters>) -> Self {
+        counters.pending.fetch_add(1, Ordering::Relaxed);
+        Self(counters)
+    }
+}
+
+impl Drop for Pending {
+    fn drop(&mut self) {
+        self.0.pending.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Whether an engine allowed or denied an event, or failed to reach a decision.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Outcome {
+    Allowed,
+    Denied,
+    Failed,
+}
+
+impl Outcome {
+    fn of<T, E: ClassifyAuthzError>(result: &Result<T, E>) -> Self {
+        match result {
+            Ok(_) => Self::Allowed,
+            Err(err) => match err.authz_error_kind() {
+                AuthzErrorKind::Denied => Self::Denied,
+                AuthzErrorKind::Unavailable | AuthzErrorKind::Misconfigured => Self::Failed,
+            },
+        }
+    }
+}
+
+impl<Primary, Candidate> ShadowEngine<Primary, Candidate> {
+    pub fn new(primary: Primary, candidate: Candidate) -> Self {
+        Self {
+            primary,
+            candidate: Arc::new(candidate),
+            counters: Default::default(),
+        }
+    }
+
+    pub fn primary(&self) -> &Primary {
+        &self.primary
+    }
+
+    pub fn candidate(&self) -> &Candidate {
+        &self.candidate
+    }
+
+    /// Number of events for which both engines reached a decision which was compared.
+    pub fn evaluations(&self) -> u64 {
+        self.counters.evaluations.load(Ordering::Relaxed)
+    }
+
+    /// Number of events which the candidate allowed while the primary denied them or vice versa.
+    pub fn disagreements(&self) -> u64 {
+        self.counters.disagreements.load(Ordering::Relaxed)
+    }
+
+    /// Number of events for which the candidate was unavailable or misconfigured.
+    pub fn candidate_failures(&self) -> u64 {
+        self.counters.candidate_failures.load(Ordering::Relaxed)
+    }
+
+    /// Number of events which the candidate is still evaluating.
+    pub fn pending(&self) -> u64 {
+        self.counters.pending.load(Ordering::Relaxed)
+    }
+}
+
+#[async_trait]
+impl<Subject, Action, Object, Input, Context, TransactionId, Primary, Candidate>
+    AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> for ShadowEngine<Primary, Candidate>
+where
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Subject: Clone + Send + Sync + 'static,
+    Action: ?Sized + ActionType + Sync + 'static,
+    Object: ?Sized + ObjectType + Sync + 'static,
+    Input: Clone + Send + Sync + 'static,
+    Context: Clone + Send + Sync + 'static,
+    TransactionId: Clone + Send + Sync + 'static,
+    Primary: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+    Candidate: AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Send + Sync + 'static,
+    Primary::Error: ClassifyAuthzError,
+    Candidate::Error: ClassifyAuthzError,
+{
+    type Ok = Primary::Ok;
+    type Error = Primary::Error;
+
+    async fn can_act(
+        &self,
+        subject: Subject,
+        input: &Input,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Self::Ok, Self::Error>
+    where
+        Subject: 'async_trait,
+        Action: 'async_trait,
+        Object: 'async_trait,
+        Input: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait,
+    {
+        let candidate = self.candidate.clone();
+        let counters = self.counters.clone();
+        let pending = Pending::new(counters.clone());
+        let (primary_outcome_sender, primary_outcome) = oneshot::channel();
+        let shadowed = (
+            subject.clone(),
+            input.clone(),
+            context.clone(),
+            tenant.clone(),
+            transaction_id.clone(),
+        );
+        tokio::spawn(async move {
+            let _pending = pending;
+            let (subject, input, context, tenant, transaction_id) = shadowed;
+            let candidate = candidate
+                .can_act(subject.clone(), &input, context.clone(), tenant.clone(), transaction_id)
+                .await;
+            // the primary's outcome is never received if its evaluation was cancelled
+            if let Ok(primary_outcome) = primary_outcome.await {
+                compare::<_, Action, Object, _, _, _, _>(
+                    &counters,
+                    &subject,
+                    &input,
+                    &context,
+                    tenant.as_ref(),
+                    primary_outcome,
+                    &candidate,
+                );
+            }
+        });
+
+        let primary = self
+            .primary
+            .can_act(subject, input, context, tenant, transaction_id)
+            .await;
+        let _ = primary_outcome_sender.send(Outcome::of(&primary));
+        primary
+    }
+}
+
+fn compare<Subject, Action, Object, Input, Context, T, E>(
+    counters: &ShadowCounters,
+    subject: &Subject,
+    input: &Input,
+    context: &Context,
+    tenant: Option<&Tenant>,
+    primary_outcome: Outcome,
+    candidate: &Result<T, E>,
+) where
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+    E: ClassifyAuthzError + std::fmt::Debug,
+{
+    match (primary_outcome, Outcome::of(candidate)) {
+        (Outcome::Failed, _) => {}
+        (_, Outcome::Failed) => {
+            counters.candidate_failures.fetch_add(1, Ordering::Relaxed);
+            report_candidate_failure::<_, Action, Object, _, _>(
+                subject,
+                input,
+                context,
+                tenant,
+                candidate.as_ref().err(),
+            );
+        }
+        (primary_outcome, candidate_outcome) => {
+            counters.evaluations.fetch_add(1, Ordering::Relaxed);
+            if primary_outcome != candidate_outcome {
+                counters.disagreements.fetch_add(1, Ordering::Relaxed);
+                report_disagreement::<_, Action, Object, _, _>(
+                    subject,
+                    input,
+                    context,
+                    tenant,
+                    primary_outcome == Outcome::Allowed,
+                    candidate.as_ref().err(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn shadowed_event<Subject, Action, Object, Input, Context>(
+    subject: &Subject,
+    input: &Input,
+    context: &Context,
+    tenant: Option<&Tenant>,
+) -> serde_json::Value
+where
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+    use crate::decision_log::MaybeSerialize;
+
+    serde_json::json!({
+        "subject": subject.maybe_serialize(),
+        "action": Action::TYPE,
+        "object": { "service": Object::SERVICE, "type": Object::TYPE },
+        "input": input.maybe_serialize(),
+        "context": context.maybe_serialize(),
+        "tenant": tenant,
+    })
+}
+
+#[cfg(feature = "tracing")]
+fn report_disagreement<Subject, Action, Object, Input, Context>(
+    subject: &Subject,
+    input: &Input,
+    context: &Context,
+    tenant: Option<&Tenant>,
+    primary_allowed: bool,
+    candidate_error: Option<&impl std::fmt::Debug>,
+) where
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+    let candidate_error = candidate_error.map(|err| format!("{err:?}"));
+    let event = shadowed_event::<_, Action, Object, _, _>(subject, input, context, tenant);
+    warn!(
+        target: "authzen::shadow",
+        primary_allowed,
+        candidate_allowed = !primary_allowed,
+        candidate_error = candidate_error.as_deref(),
+        %event,
+        "shadow authorization engine disagreed with primary",
+    );
+}
+
+#[cfg(feature = "tracing")]
+fn report_candidate_failure<Subject, Action, Object, Input, Context>(
+    subject: &Subject,
+    input: &Input,
+    context: &Context,
+    tenant: Option<&Tenant>,
+    candidate_error: Option<&impl std::fmt::Debug>,
+) where
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+    let candidate_error = candidate_error.map(|err| format!("{err:?}"));
+    let event = shadowed_event::<_, Action, Object, _, _>(subject, input, context, tenant);
+    warn!(
+        target: "authzen::shadow",
+        candidate_error = candidate_error.as_deref(),
+        %event,
+        "shadow authorization engine failed to reach a decision",
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+fn report_disagreement<Subject, Action, Object, Input, Context>(
+    _: &Subject,
+    _: &Input,
+    _: &Context,
+    _: Option<&Tenant>,
+    _: bool,
+    _: Option<&impl std::fmt::Debug>,
+) where
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+}
+
+#[cfg(not(feature = "tracing"))]
+fn report_candidate_failure<Subject, Action, Object, Input, Context>(
+    _: &Subject,
+    _: &Input,
+    _: &Context,
+    _: Option<&Tenant>,
+    _: Option<&impl std::fmt::Debug>,
+) where
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::futures::future;
+
+    #[derive(Debug)]
+    struct Error(AuthzErrorKind);
+
+    impl ClassifyAuthzError for Error {
+        fn authz_error_kind(&self) -> AuthzErrorKind {
+            self.0
+        }
+    }
+
+    /// Allows subjects starting with "a", denies all others.
+    struct Policy;
+
+    /// Never reaches a decision.
+    struct Hung;
+
+    /// Always fails with the given kind.
+    struct Failing(AuthzErrorKind);
+
+    /// Yields to the runtime before deferring to the wrapped engine, so it finishes after an engine which does not.
+    struct Slow<AE>(AE);
+
+    #[async_trait]
+    impl AuthzEngine<&'static str, Read, Item, (), (), ()> for Policy {
+        type Ok = ();
+        type Error = Error;
+
+        async fn can_act(
+            &self,
+            subject: &'static str,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<Self::Ok, Self::Error> {
+            match subject.starts_with('a') {
+                true => Ok(()),
+                false => Err(Error(AuthzErrorKind::Denied)),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl AuthzEngine<&'static str, Read, Item, (), (), ()> for Hung {
+        type Ok = ();
+        type Error = Error;
+
+        async fn can_act(
+            &self,
+            _: &'static str,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<Self::Ok, Self::Error> {
+            future::pending().await
+        }
+    }
+
+    #[async_trait]
+    impl AuthzEngine<&'static str, Read, Item, (), (), ()> for Failing {
+        type Ok = ();
+        type Error = Error;
+
+        async fn can_act(
+            &self,
+            _: &'static str,
+            _: &(),
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<Self::Ok, Self::Error> {
+            Err(Error(self.0))
+        }
+    }
+
+    #[async_trait]
+    impl AuthzEngine<&'static str, Read, Item, (), (), ()> for Slow<Policy> {
+        type Ok = ();
+        type Error = Error;
+
+        async fn can_act(
+            &self,
+            subject: &'static str,
+            input: &(),
+            context: (),
+            tenant: Option<Tenant>,
+            transaction_id: Option<()>,
+        ) -> Result<Self::Ok, Self::Error> {
+            tokio::task::yield_now().await;
+            self.0.can_act(subject, input, context, tenant, transaction_id).await
+        }
+    }
+
+    struct Item;
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+    struct Read;
+    impl ActionType for Read {
+        const TYPE: &'static str = "read";
+    }
+
+    /// Authorizes each subject in turn and then waits for the candidate to evaluate all of them.
+    fn can_act<P, C>(engine: &ShadowEngine<P, C>, subjects: &[&'static str]) -> Vec<bool>
+    where
+        P: AuthzEngine<&'static str, Read, Item, (), (), (), Ok = (), Error = Error> + Sync,
+        C: AuthzEngine<&'static str, Read, Item, (), (), (), Ok = (), Error = Error> + Send + Sync + 'static,
+    {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(async {
+            let mut allowed = vec![];
+            for subject in subjects {
+                allowed.push(
+                    engine
+                        .can_act(subject, &(), (), Some(Tenant::from("acme")), None)
+                        .await
+                        .is_ok(),
+                );
+            }
+            // candidates which never finish are abandoned once the runtime is dropped
+            for _ in 0..100 {
+                if engine.pending() == 0 {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+            allowed
+        })
+    }
+
+    #[test]
+    fn counts_agreements_and_disagreements() {
+        let engine = ShadowEngine::new(Policy, Policy);
+        assert_eq!(can_act(&engine, &["a", "b"]), [true, false]);
+        assert_eq!((engine.evaluations(), engine.disagreements()), (2, 0));
+
+        let engine = ShadowEngine::new(Policy, Failing(AuthzErrorKind::Denied));
+        assert_eq!(can_act(&engine, &["a", "b"]), [true, false]);
+        assert_eq!((engine.evaluations(), engine.disagreements()), (2, 1));
+        assert_eq!(engine.pending(), 0);
+    }
+
+    #[test]
+    fn does_not_count_candidate_failures_as_disagreements() {
+        let engine = ShadowEngine::new(Policy, Failing(AuthzErrorKind::Unavailable));
+        assert_eq!(can_act(&engine, &["a"]), [true]);
+        let engine = ShadowEngine {
+            candidate: Arc::new(Failing(AuthzErrorKind::Misconfigured)),
+            ..engine
+        };
+        assert_eq!(can_act(&engine, &["b"]), [false]);
+        assert_eq!(engine.evaluations(), 0);
+        assert_eq!(engine.disagreements(), 0);
+        assert_eq!(engine.candidate_failures(), 2);
+    }
+
+    #[test]
+    fn compares_candidates_which_finish_after_the_primary() {
+        let engine = ShadowEngine::new(Policy, Slow(Policy));
+        assert_eq!(can_act(&engine, &["a", "b"]), [true, false]);
+        assert_eq!((engine.evaluations(), engine.disagreements()), (2, 0));
+        assert_eq!(engine.pending(), 0);
+    }
+
+    #[test]
+    fn does_not_wait_for_the_candidate() {
+        let engine = ShadowEngine::new(Policy, Hung);
+        assert_eq!(can_act(&engine, &["a", "b"]), [true, false]);
+        assert_eq!(engine.evaluations(), 0);
+        assert_eq!(engine.pending(), 0);
+    }
+}
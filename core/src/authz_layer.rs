@@ -0,0 +1,341 @@
+use crate::{ActionType, AuthzEngine, AuthzErrorKind, ClassifyAuthzError, Event, ObjectType, SubjectResolver, Tenant};
+use ::authzen_session::AccountSessionSubject;
+use ::futures::future::{BoxFuture, FutureExt};
+use ::http::{Request, Response, StatusCode};
+use ::std::fmt::Debug;
+use ::std::marker::PhantomData;
+use ::std::sync::Arc;
+use ::std::task::{Context, Poll};
+use ::tower_layer::Layer;
+use ::tower_service::Service;
+
+/// Authorizes requests before they reach the wrapped service, typically applied to individual routes.
+///
+/// The subject of each event is the [`AccountSessionSubject`] placed in the request's extensions by
+/// [`SessionLayer`](authzen_session::SessionLayer), so this layer must be applied inside of the session
+/// layer. The action and object are fixed by the layer's type parameters, while the input and context
+/// are produced from each request by the layer's extractor. Events are scoped to the [`Tenant`] found in
+/// the request's extensions, if any, and are queried without a transaction id.
+///
+/// As with the `try_*` and `can_*` methods, the subject can be enriched before it is passed to the
+/// authorization engine by setting a [`SubjectResolver`] with [`AuthzLayer::with_subject_resolver`];
+/// by default the session subject is passed as is.
+///
+/// Requests are rejected with an empty response body and
+/// - `401` if the request has no session subject
+/// - the status code returned by the extractor if it fails
+/// - `500` if the subject could not be resolved
+/// - `403`, `503` or `500` if the authorization engine returns an error classified as denied,
+///   unavailable or misconfigured respectively (see [`ClassifyAuthzError`])
+///
+/// Allowed requests are passed to the wrapped service with the engine's `Ok` value (e.g. a
+/// [`Decision`](crate::Decision) and its obligations) inserted into the request's extensions.
+/// ```rs
+/// Router::new().route(
+///     "/items/:id",
+///     get(get_item).route_layer(AuthzLayer::<Read<Item>, Item, Uuid, _, _>::new(opa_client, |req: &Request<Body>| {
+///         let id = req.uri().path().rsplit('/').next().and_then(|id| id.parse::<Uuid>().ok());
+///         Ok((vec![id.ok_or(StatusCode::BAD_REQUEST)?], ()))
+///     })),
+/// )
+/// ```
+pub struct AuthzLayer<Action: ?Sized, Object: ?Sized, AccountId, AE, F, SR = (), DS = ()> {
+    authz_engine: AE,
+    extract: Arc<F>,
+    subject_resolver: Arc<SR>,
+    data_source: DS,
+    _marker: PhantomData<fn(&Action, &Object) -> AccountId>,
+}
+
+pub struct AuthzService<I, Action: ?Sized, Object: ?Sized, AccountId, AE, F, SR = (), DS = ()> {
+    inner: I,
+    layer: AuthzLayer<Action, Object, AccountId, AE, F, SR, DS>,
+}
+
+impl<Action: ?Sized, Object: ?Sized, AccountId, AE, F> AuthzLayer<Action, Object, AccountId, AE, F> {
+    pub fn new(authz_engine: AE, extract: F) -> Self {
+        Self {
+            authz_engine,
+            extract: Arc::new(extract),
+            subject_resolver: Arc::new(()),
+            data_source: (),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Action: ?Sized, Object: ?Sized, AccountId, AE, F, SR, DS> AuthzLayer<Action, Object, AccountId, AE, F, SR, DS> {
+    /// Resolves the session subject of each request with `subject_resolver`, which is passed
+    /// `data_source`, before querying the authorization engine.
+    pub fn with_subject_resolver<SR2, DS2>(
+        self,
+        subject_resolver: SR2,
+        data_source: DS2,
+    ) -> AuthzLayer<Action, Object, AccountId, AE, F, SR2, DS2> {
+        AuthzLayer {
+            authz_engine: self.authz_engine,
+            extract: self.extract,
+            subject_resolver: Arc::new(subject_resolver),
+            data_source,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<Action: ?Sized, Object: ?Sized, AccountId, AE: Clone, F, SR, DS: Clone> Clone
+    for AuthzLayer<Action, Object, AccountId, AE, F, SR, DS>
+{
+    fn clone(&self) -> Self {
+        Self {
+            authz_engine: self.authz_engine.clone(),
+            extract: self.extract.clone(),
+            subject_resolver: self.subject_resolver.clone(),
+            data_source: self.data_source.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<I: Clone, Action: ?Sized, Object: ?Sized, AccountId, AE: Clone, F, SR, DS: Clone> Clone
+    for AuthzService<I, Action, Object, AccountId, AE, F, SR, DS>
+{
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            layer: self.layer.clone(),
+        }
+    }
+}
+
+impl<I, Action: ?Sized, Object: ?Sized, AccountId, AE: Clone, F, SR, DS: Clone> Layer<I>
+    for AuthzLayer<Action, Object, AccountId, AE, F, SR, DS>
+{
+    type Service = AuthzService<I, Action, Object, AccountId, AE, F, SR, DS>;
+    fn layer(&self, inner: I) -> Self::Service {
+        AuthzService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+impl<ReqBody, ResBody, I, Action, Object, AccountId, AE, F, SR, DS, Input, Ctx> Service<Request<ReqBody>>
+    for AuthzService<I, Action, Object, AccountId, AE, F, SR, DS>
+where
+    I: Clone + Service<Request<ReqBody>, Response = Response<ResBody>> + Send + 'static,
+    I::Error: Send,
+    I::Future: Send,
+    Action: ?Sized + ActionType + Send + Sync + 'static,
+    Object: ?Sized + ObjectType + Send + Sync + 'static,
+    AccountId: Clone + Send + Sync + 'static,
+    AE: AuthzEngine<SR::Subject, Action, Object, Input, Ctx, ()> + Clone + Send + Sync + 'static,
+    AE::Ok: Clone + Send + Sync + 'static,
+    AE::Error: ClassifyAuthzError,
+    F: Fn(&Request<ReqBody>) -> Result<(Input, Ctx), StatusCode> + Send + Sync + 'static,
+    SR: SubjectResolver<AccountSessionSubject<AccountId>, DS> + 'static,
+    SR::Subject: 'static,
+    DS: Clone + Send + Sync + 'static,
+    Event<SR::Subject, Action, Object, Input, Ctx>: Send + Sync,
+    Input: Send + Sync + 'static,
+    Ctx: Send + 'static,
+    ReqBody: Send + 'static,
+    ResBody: Default + Send + 'static,
+{
+    type Response = Response<ResBody>;
+    type Error = I::Error;
+    type Future = BoxFuture<'static, Result<Response<ResBody>, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        // the inner service has been driven to readiness, so take it and leave a clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let authz_engine = self.layer.authz_engine.clone();
+        let subject_resolver = self.layer.subject_resolver.clone();
+        let data_source = self.layer.data_source.clone();
+
+        let subject = match req.extensions().get::<Option<AccountSessionSubject<AccountId>>>() {
+            Some(Some(subject)) => subject.clone(),
+            _ => return rejected(StatusCode::UNAUTHORIZED),
+        };
+        let (input, context) = match (self.layer.extract)(&req) {
+            Ok(extracted) => extracted,
+            Err(status_code) => return rejected(status_code),
+        };
+
+        let tenant = req.extensions().get::<Tenant>().cloned();
+
+        async move {
+            let subject = match subject_resolver.resolve(subject, &data_source).await {
+                Ok(subject) => subject,
+                Err(err) => {
+                    report_rejection(StatusCode::INTERNAL_SERVER_ERROR, &err);
+                    return Ok(response(StatusCode::INTERNAL_SERVER_ERROR));
+                }
+            };
+            match authz_engine.can_act(subject, &input, context, tenant, None).await {
+                Ok(ok) => {
+                    req.extensions_mut().insert(ok);
+                    inner.call(req).await
+                }
+                Err(err) => {
+                    let status_code = match err.authz_error_kind() {
+                        AuthzErrorKind::Denied => StatusCode::FORBIDDEN,
+                        AuthzErrorKind::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+                        AuthzErrorKind::Misconfigured => StatusCode::INTERNAL_SERVER_ERROR,
+                    };
+                    report_rejection(status_code, &err);
+                    Ok(response(status_code))
+                }
+            }
+        }
+        .boxed()
+    }
+}
+
+fn response<ResBody: Default>(status_code: StatusCode) -> Response<ResBody> {
+    let mut response = Response::new(ResBody::default());
+    *response.status_mut() = status_code;
+    response
+}
+
+fn rejected<ResBody: Default + Send + 'static, E>(
+    status_code: StatusCode,
+) -> BoxFuture<'static, Result<Response<ResBody>, E>>
+where
+    E: Send + 'static,
+{
+    futures::future::ready(Ok(response(status_code))).boxed()
+}
+
+#[cfg(feature = "tracing")]
+fn report_rejection(status_code: StatusCode, err: &impl Debug) {
+    debug!(target: "authzen::layer", status_code = status_code.as_u16(), error = ?err, "rejected request");
+}
+
+#[cfg(not(feature = "tracing"))]
+fn report_rejection(_: StatusCode, _: &impl Debug) {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Decision, DecisionError, SubjectResolverError};
+    use ::std::convert::Infallible;
+
+    struct Item;
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+    struct Read;
+    impl ActionType for Read {
+        const TYPE: &'static str = "read";
+    }
+
+    /// Allows accounts to read items with ids matching their own.
+    #[derive(Clone)]
+    struct SameId;
+
+    #[async_trait]
+    impl AuthzEngine<AccountSessionSubject<u32>, Read, Item, u32, (), ()> for SameId {
+        type Ok = Decision;
+        type Error = DecisionError<Unreachable>;
+
+        async fn can_act(
+            &self,
+            subject: AccountSessionSubject<u32>,
+            input: &u32,
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<Self::Ok, Self::Error> {
+            Decision::from(subject.0 == *input).into_result()
+        }
+    }
+
+    #[derive(Debug)]
+    enum Unreachable {}
+
+    impl ClassifyAuthzError for Unreachable {
+        fn authz_error_kind(&self) -> AuthzErrorKind {
+            match *self {}
+        }
+    }
+
+    /// Responds with the status of the decision found in the request's extensions.
+    #[derive(Clone)]
+    struct Handler;
+
+    impl Service<Request<()>> for Handler {
+        type Response = Response<()>;
+        type Error = Infallible;
+        type Future = futures::future::Ready<Result<Response<()>, Infallible>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, req: Request<()>) -> Self::Future {
+            assert!(req.extensions().get::<Decision>().unwrap().allow);
+            futures::future::ready(Ok(response(StatusCode::OK)))
+        }
+    }
+
+    #[test]
+    fn authorizes_requests() {
+        let layer = AuthzLayer::<Read, Item, u32, _, _>::new(SameId, |req: &Request<()>| {
+            let id = req.uri().path().trim_start_matches('/').parse::<u32>();
+            Ok((id.map_err(|_| StatusCode::BAD_REQUEST)?, ()))
+        });
+        let mut service = layer.layer(Handler);
+        let mut status = |path: &str, subject: Option<u32>| {
+            let mut req = Request::get(path).body(()).unwrap();
+            req.extensions_mut().insert(subject.map(AccountSessionSubject));
+            futures::executor::block_on(service.call(req)).unwrap().status()
+        };
+        assert_eq!(status("/1", Some(1)), StatusCode::OK);
+        assert_eq!(status("/2", Some(1)), StatusCode::FORBIDDEN);
+        assert_eq!(status("/a", Some(1)), StatusCode::BAD_REQUEST);
+        assert_eq!(status("/1", None), StatusCode::UNAUTHORIZED);
+    }
+
+    /// Resolves accounts into the next account, failing to resolve the account `0`.
+    struct Next;
+
+    #[async_trait]
+    impl SubjectResolver<AccountSessionSubject<u32>, ()> for Next {
+        type Subject = AccountSessionSubject<u32>;
+
+        async fn resolve(
+            &self,
+            subject: AccountSessionSubject<u32>,
+            _: &(),
+        ) -> Result<Self::Subject, SubjectResolverError> {
+            match subject.0 {
+                0 => Err(SubjectResolverError::new(std::fmt::Error)),
+                id => Ok(AccountSessionSubject(id + 1)),
+            }
+        }
+    }
+
+    #[test]
+    fn resolves_subjects() {
+        let layer = AuthzLayer::<Read, Item, u32, _, _>::new(SameId, |req: &Request<()>| {
+            let id = req.uri().path().trim_start_matches('/').parse::<u32>();
+            Ok((id.map_err(|_| StatusCode::BAD_REQUEST)?, ()))
+        })
+        .with_subject_resolver(Next, ());
+        let mut service = layer.layer(Handler);
+        let mut status = |path: &str, subject: u32| {
+            let mut req = Request::get(path).body(()).unwrap();
+            req.extensions_mut().insert(Some(AccountSessionSubject(subject)));
+            futures::executor::block_on(service.call(req)).unwrap().status()
+        };
+        assert_eq!(status("/2", 1), StatusCode::OK);
+        assert_eq!(status("/1", 1), StatusCode::FORBIDDEN);
+        assert_eq!(status("/1", 0), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -15,6 +15,7 @@ use ::diesel_async::AsyncConnection;
 use ::serde::{de::DeserializeOwned, Serialize};
 use ::std::fmt::Debug;
 use ::std::hash::Hash;
+use ::std::time::SystemTime;
 
 impl<E, B> StorageObject<B> for E
 where
@@ -88,11 +89,11 @@ where
 }
 
 #[async_trait]
-impl<'query, 'v, E, B, I, C, O> StorageAction<C, I> for actions::Delete<O>
+impl<'query, 'v, E, B, I, C, O, TF, TG> StorageAction<C, I> for actions::Delete<O>
 where
     O: ?Sized + AsStorage<B, StorageObject = E>,
     E: DbDelete + Sync,
-    B: Backend,
+    B: Backend + 'static,
     I: IntoIterator<Item = E::Id> + Send,
     C: Db<Backend = B> + 'query,
     <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
@@ -101,11 +102,17 @@ where
     <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
     DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
 
-    E::Id: Debug + Send,
+    E::Id: Clone + Debug + Send,
     for<'a> &'a E::Raw: Identifiable<Id = &'a E::Id>,
     <E::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
 
-    E::Raw: Deletable<'query, C::AsyncConnection, E::Table, I, E::Id, E::DeletedAt, E::DeletePatch<'v>>,
+    E::Raw: Deletable<'query, C::AsyncConnection, E::Table, Vec<E::Id>, E::Id, E::DeletedAt, E::DeletePatch<'v>>,
+
+    // DbGet::check_tenant bounds
+    E::Table: FilterColumns<B> + 'static,
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = TF>,
+    TF: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = TG>,
+    TG: LoadQuery<'query, C::AsyncConnection, E::Raw> + Send + 'query,
 {
     type Ok = Vec<E>;
     type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
@@ -115,18 +122,187 @@ where
         C: 'async_trait,
         I: 'async_trait,
     {
-        Ok(E::delete(client, input).await?)
+        Ok(E::delete(client, input.into_iter().collect::<Vec<_>>()).await?)
+    }
+
+    async fn act_for_tenant(client: &C, input: I, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        let ids = input.into_iter().collect::<Vec<_>>();
+        E::check_tenant(client, ids.clone(), tenant.as_ref()).await?;
+        Ok(E::delete(client, ids).await?)
     }
 }
 
+/// The query type of a filter `F` which excludes the soft deleted records of `E`.
+type NotDeletedQuery<'query, F, C, E> = <F as IsNotDeleted<
+    'query,
+    <C as TransactionalDataSource>::AsyncConnection,
+    <E as DbEntity>::Raw,
+    <E as DbEntity>::Raw,
+>>::IsNotDeletedFilter;
+
+/// Lists the rows of the event's tenant if the table is scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]),
+/// so tables listed by page must implement [`FilterColumns`], e.g. with only their tenant column.
 #[async_trait]
-impl<E, B, I, C, F, O> StorageAction<C, I> for actions::Read<O>
+impl<'query, E, B, C, F, O> StorageAction<C, Page> for actions::List<O>
 where
     O: ?Sized + AsStorage<B, StorageObject = E>,
     E: DbEntity + Sync,
-    B: Backend,
+    B: Backend + 'static,
+    C: Db<Backend = B> + 'query,
+    <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
+
+    // DbEntity bounds
+    <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+    DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
+
+    // DbGet::get_page_filtered filter bounds
+    E::Table: FilterColumns<B> + 'static,
+    bool: ::diesel::serialize::ToSql<::diesel::sql_types::Bool, B>,
+    ht::And<BoxedFilter<'static, E::Table, B>, BoxedFilter<'static, E::Table, B>>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+    ht::Or<BoxedFilter<'static, E::Table, B>, BoxedFilter<'static, E::Table, B>>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+    ht::AsExprOf<bool, ::diesel::sql_types::Bool>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+
+    // DbGet::get_page_filtered query bounds
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = F>,
+    F: IsNotDeleted<'query, C::AsyncConnection, E::Raw, E::Raw>,
+    NotDeletedQuery<'query, F, C, E>: Paginate + Send,
+    <NotDeletedQuery<'query, F, C, E> as AsQuery>::Query: 'query,
+    Paginated<<NotDeletedQuery<'query, F, C, E> as AsQuery>::Query>:
+        Send + LoadQuery<'query, C::AsyncConnection, E::Raw>,
+{
+    type Ok = Vec<E>;
+    type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
+
+    async fn act(client: &C, input: Page) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+    {
+        Ok(E::get_page_filtered(client, input, &AuthzFilter::allow_all(), None).await?)
+    }
+
+    async fn act_for_tenant(client: &C, input: Page, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+    {
+        Ok(E::get_page_filtered(client, input, &AuthzFilter::allow_all(), tenant.as_ref()).await?)
+    }
+}
+
+/// Scopes the filter to the event's tenant if the table is scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]).
+#[async_trait]
+impl<'query, E, B, C, F, O> StorageAction<C, FilteredPage> for actions::List<O>
+where
+    O: ?Sized + AsStorage<B, StorageObject = E>,
+    E: DbEntity + Sync,
+    B: Backend + 'static,
+    C: Db<Backend = B> + 'query,
+    <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
+
+    // DbEntity bounds
+    <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+    DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
+
+    // DbGet::get_page_filtered filter bounds
+    E::Table: FilterColumns<B> + 'static,
+    bool: ::diesel::serialize::ToSql<::diesel::sql_types::Bool, B>,
+    ht::And<BoxedFilter<'static, E::Table, B>, BoxedFilter<'static, E::Table, B>>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+    ht::Or<BoxedFilter<'static, E::Table, B>, BoxedFilter<'static, E::Table, B>>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+    ht::AsExprOf<bool, ::diesel::sql_types::Bool>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+
+    // DbGet::get_page_filtered query bounds
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = F>,
+    F: IsNotDeleted<'query, C::AsyncConnection, E::Raw, E::Raw>,
+    NotDeletedQuery<'query, F, C, E>: Paginate + Send,
+    <NotDeletedQuery<'query, F, C, E> as AsQuery>::Query: 'query,
+    Paginated<<NotDeletedQuery<'query, F, C, E> as AsQuery>::Query>:
+        Send + LoadQuery<'query, C::AsyncConnection, E::Raw>,
+{
+    type Ok = Vec<E>;
+    type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
+
+    async fn act(client: &C, input: FilteredPage) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+    {
+        let FilteredPage { page, filter } = input;
+        Ok(E::get_page_filtered(client, page, &filter, None).await?)
+    }
+
+    async fn act_for_tenant(client: &C, input: FilteredPage, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+    {
+        let FilteredPage { page, filter } = input;
+        Ok(E::get_page_filtered(client, page, &filter, tenant.as_ref()).await?)
+    }
+}
+
+#[async_trait]
+impl<'query, 'v, E, B, I, C, O, TF, TG> StorageAction<C, I> for actions::Purge<O>
+where
+    O: ?Sized + AsStorage<B, StorageObject = E>,
+    E: DbPurge + Sync,
+    B: Backend + 'static,
     I: IntoIterator<Item = E::Id> + Send,
-    C: Db<Backend = B>,
+    C: Db<Backend = B> + 'query,
+    <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
+
+    // DbEntity bounds
+    <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+    DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
+
+    E::Id: Clone + Debug + Send,
+    for<'a> &'a E::Raw: Identifiable<Id = &'a E::Id>,
+    <E::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
+
+    E::Raw: Deletable<'query, C::AsyncConnection, E::Table, Vec<E::Id>, E::Id, E::DeletedAt, E::DeletePatch<'v>>,
+
+    // DbGet::check_tenant bounds
+    E::Table: FilterColumns<B> + 'static,
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = TF>,
+    TF: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = TG>,
+    TG: LoadQuery<'query, C::AsyncConnection, E::Raw> + Send + 'query,
+{
+    type Ok = Vec<E>;
+    type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
+
+    async fn act(client: &C, input: I) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        Ok(E::purge(client, input.into_iter().collect::<Vec<_>>()).await?)
+    }
+
+    async fn act_for_tenant(client: &C, input: I, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        let ids = input.into_iter().collect::<Vec<_>>();
+        E::check_tenant(client, ids.clone(), tenant.as_ref()).await?;
+        Ok(E::purge(client, ids).await?)
+    }
+}
+
+#[async_trait]
+impl<'query, E, B, I, C, F, O, TF, TG> StorageAction<C, I> for actions::Read<O>
+where
+    O: ?Sized + AsStorage<B, StorageObject = E>,
+    E: DbEntity + Sync,
+    B: Backend + 'static,
+    I: IntoIterator<Item = E::Id> + Send,
+    C: Db<Backend = B> + 'query,
     <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
 
     // DbEntity bounds
@@ -134,11 +310,17 @@ where
     DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
 
     // DbGet::get bounds
-    E::Id: Debug + Send,
+    E::Id: Clone + Debug + Send,
     for<'a> &'a E::Raw: Identifiable<Id = &'a E::Id>,
     <E::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
     E::Table: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = F>,
-    F: for<'query> IsNotDeleted<'query, C::AsyncConnection, E::Raw, E::Raw>,
+    F: for<'q> IsNotDeleted<'q, C::AsyncConnection, E::Raw, E::Raw>,
+
+    // DbGet::check_tenant bounds
+    E::Table: FilterColumns<B> + 'static,
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = TF>,
+    TF: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = TG>,
+    TG: LoadQuery<'query, C::AsyncConnection, E::Raw> + Send + 'query,
 {
     type Ok = Vec<E>;
     type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
@@ -150,14 +332,84 @@ where
     {
         Ok(E::get(client, input).await?)
     }
+
+    async fn act_for_tenant(client: &C, input: I, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        let ids = input.into_iter().collect::<Vec<_>>();
+        E::check_tenant(client, ids.clone(), tenant.as_ref()).await?;
+        Ok(E::get(client, ids).await?)
+    }
+}
+
+#[async_trait]
+impl<'query, E, B, I, C, F, O, TF, TG> StorageAction<C, I> for actions::Restore<O>
+where
+    O: ?Sized + AsStorage<B, StorageObject = E>,
+    E: DbRestore + Sync,
+    E::Raw: SoftDeletable,
+    <E::Raw as SoftDeletable>::DeletedAt: ::diesel::Column<Table = E::Table> + 'query,
+    B: Backend + 'static,
+    I: IntoIterator<Item = E::Id> + Send,
+    C: Db<Backend = B> + 'query,
+    <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
+
+    // DbEntity bounds
+    <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+    DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
+    <E::Raw as TryInto<E>>::Error: Send,
+
+    // Id bounds
+    E::Id: Clone + Debug + Send,
+    <E::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
+    <E::Table as QuerySource>::FromClause: Send,
+
+    // UpdateStatement bounds
+    E::Table: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = F>,
+    F: IntoUpdateTarget + HasTable<Table = E::Table> + Send + 'query,
+    <F as IntoUpdateTarget>::WhereClause: Send + 'query,
+    ht::Update<F, ht::Eq<<E::Raw as SoftDeletable>::DeletedAt, Option<SystemTime>>>:
+        AsQuery + LoadQuery<'query, C::AsyncConnection, E::Raw> + Send,
+
+    // Audit bounds
+    E::Raw: MaybeAudit<'query, C::AsyncConnection>,
+
+    // DbGet::check_tenant bounds
+    E::Table: FilterColumns<B> + 'static,
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = TF>,
+    TF: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = TG>,
+    TG: LoadQuery<'query, C::AsyncConnection, E::Raw> + Send + 'query,
+{
+    type Ok = Vec<E>;
+    type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
+
+    async fn act(client: &C, input: I) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        Ok(E::restore(client, input).await?)
+    }
+
+    async fn act_for_tenant(client: &C, input: I, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        let ids = input.into_iter().collect::<Vec<_>>();
+        E::check_tenant(client, ids.clone(), tenant.as_ref()).await?;
+        Ok(E::restore(client, ids).await?)
+    }
 }
 
 #[async_trait]
-impl<'query, 'v, E, B, I, C, F, O> StorageAction<C, I> for actions::Update<O>
+impl<'query, 'v, E, B, I, C, F, O, TF, TG> StorageAction<C, I> for actions::Update<O>
 where
     O: ?Sized + AsStorage<B, StorageObject = E>,
     E: DbUpdate + Sync,
-    B: Backend,
+    B: Backend + 'static,
     I: IntoIterator<Item = E::PatchHelper<'v>> + Send + 'v,
     C: Db<Backend = B> + 'query,
     <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
@@ -169,7 +421,7 @@ where
     DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
 
     // Id bounds
-    E::Id: Clone + Hash + Eq + Send + Sync,
+    E::Id: Clone + Debug + Hash + Eq + Send + Sync,
     for<'a> &'a E::Raw: Identifiable<Id = &'a E::Id>,
     <E::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
     <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
@@ -192,6 +444,12 @@ where
 
     // Audit bounds
     E::Raw: MaybeAudit<'query, C::AsyncConnection>,
+
+    // DbGet::check_tenant bounds
+    E::Table: FilterColumns<B> + 'static,
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = TF>,
+    TF: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = TG>,
+    TG: LoadQuery<'query, C::AsyncConnection, E::Raw> + Send + 'query,
 {
     type Ok = Vec<E>;
     type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
@@ -203,4 +461,118 @@ where
     {
         Ok(E::update(client, input).await?)
     }
+
+    /// Patches are converted before the rows they address are checked, so they are applied with
+    /// [`Db::update`] rather than [`DbUpdate::update`].
+    async fn act_for_tenant(client: &C, input: I, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        let patches = input.into_iter().map(Into::into).collect::<Vec<E::Patch<'v>>>();
+        let ids = patches.iter().map(|patch| Identifiable::id(patch).clone()).collect();
+        E::check_tenant(client, ids, tenant.as_ref()).await?;
+        client
+            .update(patches)
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(DbEntityError::conversion)
+    }
+}
+
+#[async_trait]
+impl<'query, 'v, E, B, I, C, O, TF, TG> StorageAction<C, I> for actions::Upsert<O>
+where
+    O: ?Sized + AsStorage<B, StorageObject = E>,
+    E: DbUpsert + Sync,
+    B: Backend + 'static,
+    I: IntoIterator<Item = E::PostHelper<'v>> + Send + 'v,
+    C: Db<Backend = B> + 'query,
+    <C as TransactionalDataSource>::AsyncConnection: AsyncConnection<Backend = B>,
+
+    'v: 'query,
+
+    // DbEntity bounds
+    <<E::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+    DbEntityError<<E::Raw as TryInto<E>>::Error>: Debug + Send,
+    <E::Raw as TryInto<E>>::Error: Send,
+
+    // Id bounds
+    E::Id: Clone + Debug + Hash + Eq + Send + Sync,
+    for<'a> &'a E::Raw: Identifiable<Id = &'a E::Id>,
+    for<'a> &'a E::Post<'v>: Identifiable<Id = &'a E::Id>,
+    <E::Table as Table>::PrimaryKey: ::diesel::Column + ExpressionMethods,
+
+    // Insertable bounds
+    E::Post<'v>: Insertable<E::Table> + Clone,
+    <E::Post<'v> as Insertable<E::Table>>::Values: Send,
+    <E::Table as QuerySource>::FromClause: Send,
+
+    // Changeset bounds
+    E::Post<'v>: Into<E::Patch<'v>>,
+    <E::Patch<'v> as AsChangeset>::Changeset: Send,
+
+    // Upsert bounds
+    InsertStatement<
+        E::Table,
+        OnConflictPrimaryKey<
+            <E::Post<'v> as Insertable<E::Table>>::Values,
+            <E::Table as Table>::PrimaryKey,
+            <E::Patch<'v> as AsChangeset>::Changeset,
+            BoxedFilter<'static, E::Table, B>,
+        >,
+    >: LoadQuery<'query, C::AsyncConnection, E::Raw>,
+
+    // Guard bounds for existing records
+    E::Raw: MaybeNotDeleted<E::Table, B>,
+    bool: ::diesel::serialize::ToSql<::diesel::sql_types::Bool, B>,
+    ht::And<BoxedFilter<'static, E::Table, B>, BoxedFilter<'static, E::Table, B>>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+    ht::AsExprOf<bool, ::diesel::sql_types::Bool>:
+        ::diesel::expression::BoxableExpression<E::Table, B, SqlType = ::diesel::sql_types::Bool>,
+
+    // Audit bounds
+    E::Raw: MaybeAudit<'query, C::AsyncConnection>,
+
+    // DbGet::check_tenant bounds, also used for records which are not returned by the insert statement
+    E::Table: FilterColumns<B> + 'static,
+    E::Table: FilterDsl<BoxedFilter<'static, E::Table, B>, Output = TF>,
+    TF: FilterDsl<ht::EqAny<<E::Table as Table>::PrimaryKey, Vec<E::Id>>, Output = TG>,
+    TG: LoadQuery<'query, C::AsyncConnection, E::Raw> + Send + 'query,
+{
+    type Ok = Vec<E>;
+    type Error = DbEntityError<<E::Raw as TryInto<E>>::Error>;
+
+    async fn act(client: &C, input: I) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        Ok(E::upsert(client, input).await?)
+    }
+
+    /// Posts are converted before the existing rows they address are checked, so they are upserted
+    /// with [`Db::upsert`] rather than [`DbUpsert::upsert`], which also guards the update of existing
+    /// rows by tenant.
+    async fn act_for_tenant(client: &C, input: I, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        C: 'async_trait,
+        I: 'async_trait,
+    {
+        let posts = input.into_iter().map(Into::into).collect::<Vec<E::Post<'v>>>();
+        if posts.is_empty() {
+            return Ok(vec![]);
+        }
+        let ids = posts.iter().map(|post| Identifiable::id(post).clone()).collect();
+        E::check_tenant(client, ids, tenant.as_ref()).await?;
+        client
+            .upsert::<_, _, E::Patch<'v>, _, _, _, _, _>(posts, tenant.as_ref())
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, _>>()
+            .map_err(DbEntityError::conversion)
+    }
 }
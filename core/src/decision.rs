@@ -0,0 +1,220 @@
+use ::serde::{Deserialize, Serialize};
+use ::serde_json::{Map, Value};
+use ::typed_builder::TypedBuilder;
+
+/// The outcome of an authorization decision.
+///
+/// Authorization engines which are capable of explaining their decisions can return a `Decision`
+/// so that callers can determine *why* an action was rejected, as well as any obligations the
+/// caller is expected to fulfill when an action is allowed (e.g. redacting a field before responding).
+///
+/// A `Decision` deserializes from either a boolean or an object of the form
+/// ```json
+/// {
+///   "allow": true,
+///   "reasons": ["<reason-code>", ...],
+///   "message": "<human readable message>",
+///   "obligations": [{ "type": "<obligation-type>", ... }, ...]
+/// }
+/// ```
+/// where all fields are optional; an object without `allow` is a denial. For backwards compatibility,
+/// `response` is accepted in place of `allow`, i.e. the output `{ "response": true }` is also supported.
+#[skip_serializing_none]
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize, TypedBuilder)]
+#[serde(from = "DecisionRepr")]
+pub struct Decision {
+    /// whether the action is allowed
+    pub allow: bool,
+    /// machine readable codes identifying the policies which produced this decision
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reasons: Vec<String>,
+    /// human readable explanation of this decision
+    #[builder(default, setter(into, strip_option))]
+    pub message: Option<String>,
+    /// requirements which must be carried out by the caller if the action is allowed
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub obligations: Vec<Obligation>,
+}
+
+/// A requirement attached to a [`Decision`] which the policy enforcement point must carry out,
+/// e.g. `{ "type": "redact", "field": "email" }`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Obligation {
+    #[serde(rename = "type")]
+    pub ty: String,
+    #[serde(default, flatten)]
+    pub params: Map<String, Value>,
+}
+
+impl Decision {
+    /// Reason given when no policy applied to the event, see [`Applicability`](crate::Applicability).
+    pub const NOT_APPLICABLE: &'static str = "not_applicable";
+
+    pub fn allow() -> Self {
+        Self {
+            allow: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn deny() -> Self {
+        Self::default()
+    }
+
+    /// A denial indicating that no policy applied to the event.
+    pub fn not_applicable() -> Self {
+        Self {
+            reasons: vec![Self::NOT_APPLICABLE.into()],
+            ..Default::default()
+        }
+    }
+
+    /// Converts this decision into a result, returning the decision as an error if it does not allow the action.
+    pub fn into_result<E>(self) -> Result<Self, DecisionError<E>> {
+        if self.allow {
+            Ok(self)
+        } else {
+            Err(DecisionError::Denied(self))
+        }
+    }
+
+    /// Returns the obligations of the specified type.
+    pub fn obligations_of<'a>(&'a self, ty: &'a str) -> impl Iterator<Item = &'a Obligation> + 'a {
+        self.obligations.iter().filter(move |obligation| obligation.ty == ty)
+    }
+}
+
+impl From<bool> for Decision {
+    fn from(allow: bool) -> Self {
+        Self {
+            allow,
+            ..Default::default()
+        }
+    }
+}
+
+impl std::fmt::Display for Decision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(if self.allow { "allowed" } else { "denied" })?;
+        if let Some(message) = &self.message {
+            write!(f, ": {message}")?;
+        }
+        if !self.reasons.is_empty() {
+            write!(f, " ({})", self.reasons.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DecisionRepr {
+    Bool(bool),
+    Object {
+        /// objects which do not specify whether the action is allowed are treated as a denial;
+        /// `response` is accepted for policies written against the previously documented `{"response": bool}` output
+        #[serde(default, alias = "response")]
+        allow: bool,
+        #[serde(default)]
+        reasons: Vec<String>,
+        #[serde(default)]
+        message: Option<String>,
+        #[serde(default)]
+        obligations: Vec<Obligation>,
+    },
+}
+
+impl From<DecisionRepr> for Decision {
+    fn from(repr: DecisionRepr) -> Self {
+        match repr {
+            DecisionRepr::Bool(allow) => allow.into(),
+            DecisionRepr::Object {
+                allow,
+                reasons,
+                message,
+                obligations,
+            } => Self {
+                allow,
+                reasons,
+                message,
+                obligations,
+            },
+        }
+    }
+}
+
+/// Error returned from authorization engines which produce [`Decision`]s.
+#[derive(Clone, Debug, IsVariant, Unwrap, thiserror::Error)]
+pub enum DecisionError<E> {
+    /// The engine reached a decision which did not allow the action.
+    #[error("{0}")]
+    Denied(Decision),
+    /// The engine was unable to reach a decision.
+    #[error("{0}")]
+    Engine(E),
+}
+
+impl<E> DecisionError<E> {
+    /// Returns the decision if the engine denied the action.
+    pub fn decision(&self) -> Option<&Decision> {
+        match self {
+            Self::Denied(decision) => Some(decision),
+            Self::Engine(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::serde_json::json;
+
+    #[test]
+    fn deserializes_from_bool_or_object() {
+        assert_eq!(
+            serde_json::from_value::<Decision>(json!(true)).unwrap(),
+            Decision::allow()
+        );
+        assert_eq!(
+            serde_json::from_value::<Decision>(json!(false)).unwrap(),
+            Decision::deny()
+        );
+
+        let decision: Decision = serde_json::from_value(json!({
+            "allow": false,
+            "reasons": ["not_owner"],
+            "message": "only the owner may update this item",
+            "obligations": [{ "type": "redact", "field": "email" }],
+        }))
+        .unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["not_owner".to_string()]);
+        assert_eq!(
+            decision.obligations_of("redact").next().unwrap().params["field"],
+            "email"
+        );
+        assert_eq!(
+            decision.to_string(),
+            "denied: only the owner may update this item (not_owner)"
+        );
+    }
+
+    #[test]
+    fn deserializes_legacy_and_incomplete_objects() {
+        assert_eq!(
+            serde_json::from_value::<Decision>(json!({ "response": true })).unwrap(),
+            Decision::allow()
+        );
+        assert_eq!(
+            serde_json::from_value::<Decision>(json!({ "response": false })).unwrap(),
+            Decision::deny()
+        );
+        assert_eq!(serde_json::from_value::<Decision>(json!({})).unwrap(), Decision::deny());
+
+        let decision: Decision = serde_json::from_value(json!({ "reasons": ["no_policy"] })).unwrap();
+        assert!(!decision.allow);
+        assert_eq!(decision.reasons, vec!["no_policy".to_string()]);
+    }
+}
@@ -0,0 +1,64 @@
+use super::{DecisionLog, DecisionLogError, DecisionRecord};
+use ::authzen_data_sources::diesel::connection::Db;
+use ::derivative::Derivative;
+use ::diesel::associations::HasTable;
+use ::diesel::backend::Backend;
+use ::diesel::query_builder::{InsertStatement, QueryId};
+use ::diesel::query_source::QuerySource;
+use ::diesel::{Insertable, Table};
+use ::diesel_async::{methods::ExecuteDsl, AsyncConnection, RunQueryDsl};
+use ::std::marker::PhantomData;
+
+/// Inserts each decision as a row of table `T`, where `R` is an insertable row type which can be
+/// constructed from a [`DecisionRecord`].
+/// ```rs
+/// #[derive(Insertable)]
+/// #[diesel(table_name = decision_log)]
+/// pub struct DbDecision {
+///     pub subject: serde_json::Value,
+///     pub action: String,
+///     pub allowed: bool,
+///     ...
+/// }
+///
+/// impl From<DecisionRecord> for DbDecision { ... }
+///
+/// let decision_log = DieselDecisionLog::<_, decision_log::table, DbDecision>::new(pool);
+/// ```
+/// Decisions should be recorded using a connection pool rather than a connection within a transaction
+/// so that they are persisted even if the transaction is later rolled back.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "D: Clone"), Debug(bound = "D: std::fmt::Debug"))]
+pub struct DieselDecisionLog<D, T, R> {
+    db: D,
+    #[derivative(Debug = "ignore")]
+    row: PhantomData<fn() -> (T, R)>,
+}
+
+impl<D, T, R> DieselDecisionLog<D, T, R> {
+    pub fn new(db: D) -> Self {
+        Self { db, row: PhantomData }
+    }
+}
+
+#[async_trait]
+impl<D, T, R> DecisionLog for DieselDecisionLog<D, T, R>
+where
+    D: Db + Send + Sync,
+    D::Backend: Backend,
+    D::AsyncConnection: AsyncConnection<Backend = D::Backend>,
+    T: Table + HasTable<Table = T> + QueryId + Send + 'static,
+    <T as QuerySource>::FromClause: Send,
+    R: From<DecisionRecord> + Insertable<T> + Send + 'static,
+    <R as Insertable<T>>::Values: Send + 'static,
+    InsertStatement<T, <R as Insertable<T>>::Values>: ExecuteDsl<D::AsyncConnection>,
+{
+    async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError> {
+        let row = R::from(record.clone());
+        self.db
+            .query(move |conn| Box::pin(::diesel::insert_into(T::table()).values(row).execute(conn)))
+            .await
+            .map(|_| ())
+            .map_err(DecisionLogError::new)
+    }
+}
@@ -0,0 +1,268 @@
+#[cfg(feature = "diesel-data-source")]
+mod diesel;
+
+#[cfg(feature = "diesel-data-source")]
+pub use self::diesel::*;
+
+use ::derivative::Derivative;
+use ::serde::Serialize;
+use ::serde_json::Value;
+use ::serde_with::{DurationMilliSecondsWithFrac, TimestampMilliSeconds};
+use ::std::fs::{File, OpenOptions};
+use ::std::io::Write;
+use ::std::path::Path;
+use ::std::sync::{Arc, Mutex};
+use ::std::time::{Duration, SystemTime};
+
+/// A record of a single authorization decision, made when an action is attempted with
+/// [`TryAct::try_act`](crate::TryAct::try_act).
+///
+/// With feature `decision-log-payloads`, the subject, input, context and transaction id are recorded
+/// in their serialized form and values whose types do not implement [`Serialize`] are recorded as `null`.
+/// Without it, they are always recorded as `null`.
+#[serde_as]
+#[skip_serializing_none]
+#[derive(Clone, Debug, Serialize)]
+pub struct DecisionRecord {
+    /// when the decision was requested, serialized as milliseconds since the unix epoch
+    #[serde_as(as = "TimestampMilliSeconds<i64>")]
+    pub timestamp: SystemTime,
+    pub subject: Value,
+    pub action: &'static str,
+    pub object: DecisionRecordObject,
+    pub input: Value,
+    pub context: Value,
+    pub tenant: Option<crate::Tenant>,
+    pub transaction_id: Option<Value>,
+    /// whether the authorization engine allowed the action
+    pub allowed: bool,
+    /// time taken by the authorization engine to reach a decision, serialized in milliseconds
+    #[serde_as(as = "DurationMilliSecondsWithFrac<f64>")]
+    pub latency: Duration,
+    /// debug representation of the error returned by the authorization engine, if any
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct DecisionRecordObject {
+    pub service: &'static str,
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+}
+
+/// Error returned from a [`DecisionLog`] which was unable to record a decision.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("unable to record authorization decision: {0}")]
+pub struct DecisionLogError(pub Arc<dyn std::error::Error + Send + Sync>);
+
+impl DecisionLogError {
+    pub fn new(err: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self(Arc::new(err))
+    }
+}
+
+/// A sink which records every authorization decision made when attempting an action.
+///
+/// If a decision cannot be recorded, the action is not performed and
+/// [`ActionError::DecisionLog`](crate::ActionError::DecisionLog) is returned instead, so that no
+/// action is ever carried out without a record of it having been authorized.
+#[async_trait]
+pub trait DecisionLog: Send + Sync {
+    /// Whether records should be produced at all; used to skip serializing events when no sink is configured.
+    fn enabled(&self) -> bool {
+        true
+    }
+
+    async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError>;
+}
+
+#[async_trait]
+impl DecisionLog for () {
+    fn enabled(&self) -> bool {
+        false
+    }
+
+    async fn log(&self, _: &DecisionRecord) -> Result<(), DecisionLogError> {
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: ?Sized + DecisionLog> DecisionLog for &T {
+    fn enabled(&self) -> bool {
+        (**self).enabled()
+    }
+
+    async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError> {
+        (**self).log(record).await
+    }
+}
+
+#[async_trait]
+impl<T: ?Sized + DecisionLog> DecisionLog for Arc<T> {
+    fn enabled(&self) -> bool {
+        (**self).enabled()
+    }
+
+    async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError> {
+        (**self).log(record).await
+    }
+}
+
+/// Writes each decision as a single line of json, e.g. to an append only file.
+///
+/// Writes are performed synchronously and flushed after every record.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct JsonLinesDecisionLog<W = File> {
+    #[derivative(Debug = "ignore")]
+    writer: Mutex<W>,
+}
+
+impl JsonLinesDecisionLog<File> {
+    /// Opens the file at `path` for appending, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(OpenOptions::new().create(true).append(true).open(path)?))
+    }
+}
+
+impl<W: Write> JsonLinesDecisionLog<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer.into_inner().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> DecisionLog for JsonLinesDecisionLog<W> {
+    async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError> {
+        let mut line = serde_json::to_vec(record).map_err(DecisionLogError::new)?;
+        line.push(b'\n');
+        let mut writer = self.writer.lock().unwrap_or_else(|err| err.into_inner());
+        writer.write_all(&line).map_err(DecisionLogError::new)?;
+        writer.flush().map_err(DecisionLogError::new)
+    }
+}
+
+/// Emits each decision as a `tracing` event with target `authzen::decision`;
+/// allowed actions are emitted at the `INFO` level and denied actions at the `WARN` level.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingDecisionLog;
+
+#[cfg(feature = "tracing")]
+#[async_trait]
+impl DecisionLog for TracingDecisionLog {
+    async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError> {
+        let DecisionRecord {
+            subject,
+            action,
+            object,
+            transaction_id,
+            allowed,
+            latency,
+            error,
+            ..
+        } = record;
+        let latency_ms = latency.as_secs_f64() * 1000.;
+        let transaction_id = transaction_id.as_ref().map(|x| x.to_string());
+        if *allowed {
+            info!(
+                target: "authzen::decision",
+                %subject, action, service = object.service, object = object.ty, transaction_id = transaction_id.as_deref(), latency_ms,
+                "allowed",
+            );
+        } else {
+            warn!(
+                target: "authzen::decision",
+                %subject, action, service = object.service, object = object.ty, transaction_id = transaction_id.as_deref(), latency_ms,
+                error = error.as_deref(),
+                "denied",
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Serializes values whose types implement [`Serialize`], allowing decisions to be recorded
+/// without requiring every subject, input and context type to be serializable.
+///
+/// Relies on specialization, so values are only serialized with feature `decision-log-payloads`
+/// and are otherwise always recorded as `null`.
+pub(crate) trait MaybeSerialize {
+    fn maybe_serialize(&self) -> Value;
+}
+
+cfg_if! {
+    if #[cfg(feature = "decision-log-payloads")] {
+        impl<T: ?Sized> MaybeSerialize for T {
+            default fn maybe_serialize(&self) -> Value {
+                Value::Null
+            }
+        }
+
+        impl<T: ?Sized + Serialize> MaybeSerialize for T {
+            fn maybe_serialize(&self) -> Value {
+                serde_json::to_value(self).unwrap_or_default()
+            }
+        }
+    } else {
+        impl<T: ?Sized> MaybeSerialize for T {
+            fn maybe_serialize(&self) -> Value {
+                Value::Null
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn writes_json_lines() {
+        let record = DecisionRecord {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(1_000),
+            subject: "a".into(),
+            action: "read",
+            object: DecisionRecordObject {
+                service: "cart",
+                ty: "item",
+            },
+            input: Value::Null,
+            context: Value::Null,
+            tenant: None,
+            transaction_id: None,
+            allowed: true,
+            latency: Duration::from_micros(1_500),
+            error: None,
+        };
+        let sink = JsonLinesDecisionLog::new(vec![]);
+        futures::executor::block_on(sink.log(&record)).unwrap();
+        futures::executor::block_on(sink.log(&record)).unwrap();
+        let line = r#"{"timestamp":1000,"subject":"a","action":"read","object":{"service":"cart","type":"item"},"input":null,"context":null,"allowed":true,"latency":1.5}"#;
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            format!("{line}\n{line}\n")
+        );
+    }
+
+    #[cfg(feature = "decision-log-payloads")]
+    #[test]
+    fn serializes_if_possible() {
+        struct Unserializable;
+        assert_eq!(Unserializable.maybe_serialize(), Value::Null);
+        assert_eq!("a".maybe_serialize(), Value::from("a"));
+    }
+
+    #[cfg(not(feature = "decision-log-payloads"))]
+    #[test]
+    fn omits_payloads() {
+        assert_eq!("a".maybe_serialize(), Value::Null);
+    }
+}
@@ -1,17 +1,107 @@
-use crate::ActionError;
+use crate::{ActionError, AuthzErrorKind, ClassifyAuthzError, SequenceError};
 
 impl<AuthzEngineError, StorageError, TransactionCacheError>
     From<ActionError<AuthzEngineError, StorageError, TransactionCacheError>> for authzen_service_util::Error
 where
-    AuthzEngineError: std::fmt::Display,
+    AuthzEngineError: ClassifyAuthzError + std::fmt::Display,
     StorageError: Into<authzen_service_util::Error>,
     TransactionCacheError: Into<authzen_service_util::Error>,
 {
     fn from(value: ActionError<AuthzEngineError, StorageError, TransactionCacheError>) -> Self {
         match value {
-            ActionError::Authz(err) => Self::bad_request_details(err),
+            ActionError::Authz(err) => match err.authz_error_kind() {
+                AuthzErrorKind::Denied => Self::forbidden_details(err),
+                AuthzErrorKind::Unavailable => Self::service_unavailable_details(err),
+                AuthzErrorKind::Misconfigured => Self::default_details(err),
+            },
             ActionError::DataSource(err) => err.into(),
             ActionError::TransactionCache(err) => err.into(),
+            ActionError::DecisionLog(err) => Self::default_details(err),
+            ActionError::Transaction(err) => Self::default_details(err),
+            ActionError::SubjectResolver(err) => Self::default_details(err),
         }
     }
 }
+
+impl<E1, E2> From<SequenceError<E1, E2>> for authzen_service_util::Error
+where
+    E1: Into<authzen_service_util::Error>,
+    E2: Into<authzen_service_util::Error>,
+{
+    fn from(value: SequenceError<E1, E2>) -> Self {
+        match value {
+            SequenceError::First(err) => err.into(),
+            SequenceError::Second(err) => err.into(),
+            SequenceError::TransactionRequired => {
+                Self::default_details("actions must be performed within a transaction")
+            }
+        }
+    }
+}
+
+/// Denials map to `PERMISSION_DENIED`, engine outages to `UNAVAILABLE` and misconfigured engines to
+/// `INTERNAL`; all other errors are converted in the same way as [`authzen_service_util::Error`].
+#[cfg(feature = "grpc")]
+impl<AuthzEngineError, StorageError, TransactionCacheError>
+    From<ActionError<AuthzEngineError, StorageError, TransactionCacheError>> for tonic::Status
+where
+    AuthzEngineError: ClassifyAuthzError + std::fmt::Display,
+    StorageError: Into<authzen_service_util::Error>,
+    TransactionCacheError: Into<authzen_service_util::Error>,
+{
+    fn from(value: ActionError<AuthzEngineError, StorageError, TransactionCacheError>) -> Self {
+        match value {
+            ActionError::Authz(err) => {
+                let code = match err.authz_error_kind() {
+                    AuthzErrorKind::Denied => tonic::Code::PermissionDenied,
+                    AuthzErrorKind::Unavailable => tonic::Code::Unavailable,
+                    AuthzErrorKind::Misconfigured => tonic::Code::Internal,
+                };
+                tonic::Status::new(code, err.to_string())
+            }
+            value => authzen_service_util::Error::from(value).into(),
+        }
+    }
+}
+
+#[cfg(all(test, feature = "grpc"))]
+mod test {
+    use super::*;
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("{0:?}")]
+    struct EngineError(AuthzErrorKind);
+
+    impl ClassifyAuthzError for EngineError {
+        fn authz_error_kind(&self) -> AuthzErrorKind {
+            self.0
+        }
+    }
+
+    #[test]
+    fn converts_action_errors_into_statuses() {
+        let code = |err: ActionError<EngineError, authzen_service_util::Error, authzen_service_util::Error>| {
+            tonic::Status::from(err).code()
+        };
+        assert_eq!(
+            code(ActionError::Authz(EngineError(AuthzErrorKind::Denied))),
+            tonic::Code::PermissionDenied
+        );
+        assert_eq!(
+            code(ActionError::Authz(EngineError(AuthzErrorKind::Unavailable))),
+            tonic::Code::Unavailable
+        );
+        assert_eq!(
+            code(ActionError::Authz(EngineError(AuthzErrorKind::Misconfigured))),
+            tonic::Code::Internal
+        );
+        assert_eq!(
+            code(ActionError::DataSource(authzen_service_util::Error::bad_request())),
+            tonic::Code::Unknown
+        );
+        assert_eq!(
+            code(ActionError::DataSource(authzen_service_util::Error::default())),
+            tonic::Code::Internal
+        );
+    }
+}
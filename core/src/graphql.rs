@@ -0,0 +1,276 @@
+use crate::{
+    can_act, ActionType, AuthorizationContext, AuthzEngine, ClassifyAuthzError, Event, ObjectType, SubjectResolver,
+};
+use ::async_graphql::{Context, Guard};
+use ::authzen_data_sources::DataSource;
+use ::std::fmt::Display;
+use ::std::marker::PhantomData;
+
+/// Builds the [`AuthorizationContext`] used by an [`AuthzGuard`] from the GraphQL context of the field being resolved.
+///
+/// The built context may borrow from the GraphQL context, e.g. an account session provided with `data`,
+/// and must be `Copy`, e.g. a struct of references.
+pub trait FromGraphQLContext<AE, DS, TC> {
+    type Ctx<'a>: AuthorizationContext<AE, DS, TC> + Copy + Send + Sync;
+
+    fn from_graphql_context<'a>(ctx: &'a Context<'_>) -> async_graphql::Result<Self::Ctx<'a>>;
+}
+
+type CtxOf<'a, C, AE, DS, TC> = <C as FromGraphQLContext<AE, DS, TC>>::Ctx<'a>;
+type SubjectOf<'a, C, AE, DS, TC> = <CtxOf<'a, C, AE, DS, TC> as AuthorizationContext<AE, DS, TC>>::Subject<'a>;
+type ContextOf<'a, C, AE, DS, TC> = <CtxOf<'a, C, AE, DS, TC> as AuthorizationContext<AE, DS, TC>>::Context<'a>;
+type SubjectResolverOf<'a, C, AE, DS, TC> =
+    <CtxOf<'a, C, AE, DS, TC> as AuthorizationContext<AE, DS, TC>>::SubjectResolver;
+type ResolvedSubjectOf<'a, C, AE, DS, TC> =
+    <SubjectResolverOf<'a, C, AE, DS, TC> as SubjectResolver<SubjectOf<'a, C, AE, DS, TC>, DS>>::Subject;
+
+/// An [`async_graphql::Guard`] which authorizes a field in the same manner as the `can_*` methods
+/// generated by the [`action`](authzen_proc_macros::action) macro, e.g. `can_read` for action `Read`:
+/// the subject is resolved with the context's [`SubjectResolver`] and the decision is recorded in its
+/// [`DecisionLog`](crate::DecisionLog).
+///
+/// The [`AuthorizationContext`] is built from the GraphQL context with `C`, an implementor of
+/// [`FromGraphQLContext`]. The input of the event is provided when the guard is constructed,
+/// typically from the field's arguments.
+/// ```rs
+/// struct AppCtx;
+///
+/// impl FromGraphQLContext<AE, Db, ()> for AppCtx {
+///     type Ctx<'a> = Ctx<'a, Db>;
+///
+///     fn from_graphql_context<'a>(ctx: &'a async_graphql::Context<'_>) -> async_graphql::Result<Ctx<'a, Db>> {
+///         Ok(Ctx { session: ctx.data()?, ... })
+///     }
+/// }
+///
+/// #[Object]
+/// impl Query {
+///     #[graphql(guard = "AuthzGuard::<Read<Item>, Item, AppCtx, _, _, _, _>::new(vec![id])")]
+///     async fn item(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Item> {
+///         ...
+///     }
+/// }
+/// ```
+/// Unauthorized fields resolve to an error with a `status` extension of `403`, `503` or `500`,
+/// depending on whether the authorization engine denied the event, was unavailable or was misconfigured.
+pub struct AuthzGuard<Action: ?Sized, Object: ?Sized, C, AE, DS, TC, I> {
+    input: I,
+    _marker: PhantomData<fn(&Action, &Object) -> (C, AE, DS, TC)>,
+}
+
+impl<Action: ?Sized, Object: ?Sized, C, AE, DS, TC, I> AuthzGuard<Action, Object, C, AE, DS, TC, I> {
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<Action, Object, C, AE, DS, TC, I> Guard for AuthzGuard<Action, Object, C, AE, DS, TC, I>
+where
+    Action: ?Sized + ActionType + Send + Sync,
+    Object: ?Sized + ObjectType + Send + Sync,
+    C: FromGraphQLContext<AE, DS, TC>,
+    AE: Sync,
+    DS: DataSource,
+    I: Send + Sync,
+    for<'a> SubjectResolverOf<'a, C, AE, DS, TC>: SubjectResolver<SubjectOf<'a, C, AE, DS, TC>, DS>,
+    for<'a> AE: AuthzEngine<
+        ResolvedSubjectOf<'a, C, AE, DS, TC>,
+        Action,
+        Object,
+        I,
+        ContextOf<'a, C, AE, DS, TC>,
+        DS::TransactionId,
+    >,
+    for<'a> Event<ResolvedSubjectOf<'a, C, AE, DS, TC>, Action, Object, I, ContextOf<'a, C, AE, DS, TC>>: Send + Sync,
+    for<'a> <AE as AuthzEngine<
+        ResolvedSubjectOf<'a, C, AE, DS, TC>,
+        Action,
+        Object,
+        I,
+        ContextOf<'a, C, AE, DS, TC>,
+        DS::TransactionId,
+    >>::Error: ClassifyAuthzError + Display,
+{
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        let ctx = C::from_graphql_context(ctx)?;
+        can_act::<_, _, _, _, Action, Object, _, _>(
+            ctx.authz_engine(),
+            ctx.data_source(),
+            ctx.decision_log(),
+            ctx.subject_resolver(),
+            ctx.subject(),
+            &self.input,
+            ctx.context(),
+            ctx.tenant(),
+        )
+        .await
+        .map_err(|err| authzen_service_util::Error::from(err).graphql())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DecisionLog, DecisionLogError, DecisionRecord, SubjectResolverError, Tenant};
+    use ::async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+    use ::std::sync::{Arc, Mutex};
+
+    struct Item;
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "test";
+        const TYPE: &'static str = "item";
+    }
+    struct Read;
+    impl ActionType for Read {
+        const TYPE: &'static str = "read";
+    }
+
+    #[derive(Clone, Copy, Debug)]
+    struct Store;
+
+    impl DataSource for Store {
+        type Backend = ();
+        type Error = authzen_service_util::Error;
+        type TransactionId = ();
+
+        fn transaction_id(&self) -> Option<()> {
+            None
+        }
+    }
+
+    /// Allows subjects to read items with ids matching their own.
+    struct SameId;
+
+    #[async_trait]
+    impl AuthzEngine<u32, Read, Item, u32, (), ()> for SameId {
+        type Ok = ();
+        type Error = authzen_service_util::Error;
+
+        async fn can_act(
+            &self,
+            subject: u32,
+            input: &u32,
+            _: (),
+            _: Option<Tenant>,
+            _: Option<()>,
+        ) -> Result<(), Self::Error> {
+            match subject == *input {
+                true => Ok(()),
+                false => Err(authzen_service_util::Error::forbidden_details("denied")),
+            }
+        }
+    }
+
+    /// Resolves account ids into the ids of the accounts they act on behalf of.
+    struct Delegate;
+
+    #[async_trait]
+    impl<'a> SubjectResolver<&'a u32, Store> for Delegate {
+        type Subject = u32;
+
+        async fn resolve(&self, subject: &'a u32, _: &Store) -> Result<u32, SubjectResolverError> {
+            Ok(subject * 10)
+        }
+    }
+
+    /// Records whether each decision allowed the event.
+    #[derive(Default)]
+    struct Decisions(Mutex<Vec<bool>>);
+
+    #[async_trait]
+    impl DecisionLog for Decisions {
+        async fn log(&self, record: &DecisionRecord) -> Result<(), DecisionLogError> {
+            self.0.lock().unwrap().push(record.allowed);
+            Ok(())
+        }
+    }
+
+    struct Session(u32);
+
+    /// Borrows the session and decision log provided to the schema.
+    #[derive(Clone, Copy)]
+    struct Ctx<'a> {
+        session: &'a Session,
+        decisions: &'a Decisions,
+    }
+
+    impl<'a> AuthorizationContext<SameId, Store, ()> for Ctx<'a> {
+        type Context<'b>
+            = ()
+        where
+            Self: 'b;
+        type Subject<'b>
+            = &'a u32
+        where
+            Self: 'b;
+        type SubjectResolver = Delegate;
+
+        fn context(&self) -> Self::Context<'_> {}
+        fn subject(&self) -> Self::Subject<'_> {
+            &self.session.0
+        }
+        fn authz_engine(&self) -> &SameId {
+            &SameId
+        }
+        fn data_source(&self) -> &Store {
+            &Store
+        }
+        fn transaction_cache(&self) -> &() {
+            &()
+        }
+        fn decision_log(&self) -> &dyn DecisionLog {
+            self.decisions
+        }
+        fn subject_resolver(&self) -> &Delegate {
+            &Delegate
+        }
+    }
+
+    struct AppCtx;
+
+    impl FromGraphQLContext<SameId, Store, ()> for AppCtx {
+        type Ctx<'a> = Ctx<'a>;
+
+        fn from_graphql_context<'a>(ctx: &'a Context<'_>) -> async_graphql::Result<Ctx<'a>> {
+            Ok(Ctx {
+                session: ctx.data()?,
+                decisions: ctx.data::<Arc<Decisions>>()?,
+            })
+        }
+    }
+
+    struct Query;
+
+    #[Object]
+    impl Query {
+        #[graphql(guard = "AuthzGuard::<Read, Item, AppCtx, SameId, Store, (), u32>::new(id)")]
+        async fn item(&self, id: u32) -> u32 {
+            id
+        }
+    }
+
+    #[test]
+    fn authorizes_fields_with_resolved_subjects_and_records_decisions() {
+        let decisions = Arc::new(Decisions::default());
+        let schema = Schema::build(Query, EmptyMutation, EmptySubscription)
+            .data(Session(1))
+            .data(decisions.clone())
+            .finish();
+        let execute = |query: &str| futures::executor::block_on(schema.execute(query));
+
+        assert!(execute("{ item(id: 10) }").errors.is_empty());
+        let denied = execute("{ item(id: 1) }");
+        assert_eq!(denied.errors.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&denied.errors[0]).unwrap()["extensions"]["status"],
+            403
+        );
+
+        assert_eq!(*decisions.0.lock().unwrap(), vec![true, false]);
+    }
+}
@@ -1,4 +1,6 @@
 #![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
+#![cfg_attr(feature = "decision-log-payloads", allow(incomplete_features))]
+#![cfg_attr(feature = "decision-log-payloads", feature(specialization))]
 
 #[macro_use]
 extern crate async_trait;
@@ -14,20 +16,51 @@ extern crate serde_with;
 extern crate tracing;
 
 mod authz_engines;
+#[cfg(feature = "authz-layer")]
+mod authz_layer;
 mod data_sources;
+mod decision;
+mod decision_log;
+mod sequence;
+mod subject_resolver;
 
 /// Helper traits for implementing a policy information point.
 #[cfg(feature = "policy-information-point")]
 pub mod policy_information_point;
 
+/// Link-time registry of the object and action types defined by an application.
+#[cfg(feature = "registry")]
+pub mod registry;
+
 /// Implementations of common transaction cache clients.
 pub mod transaction_caches;
 
 #[cfg(feature = "extra-traits")]
 mod extra_traits;
 
+#[cfg(feature = "graphql")]
+mod graphql;
+
+#[cfg(feature = "schema")]
+mod schema;
+
+pub use authz_engines::*;
+#[cfg(feature = "authz-layer")]
+pub use authz_layer::*;
+pub use decision::*;
+pub use decision_log::*;
+#[cfg(feature = "graphql")]
+pub use graphql::*;
+#[cfg(feature = "schema")]
+pub use schema::*;
+pub use sequence::*;
+pub use subject_resolver::*;
+
+pub use ::authzen_data_sources::{AsTenant, Tenant};
+
 use ::authzen_data_sources::*;
 use ::derive_getters::{Dissolve, Getters};
+use ::scoped_futures::ScopedFutureExt;
 use ::serde::{de::DeserializeOwned, Deserialize, Serialize};
 use ::std::borrow::Borrow;
 use ::std::collections::HashMap;
@@ -36,6 +69,8 @@ use ::std::future::Future;
 use ::std::hash::Hash;
 use ::std::marker::PhantomData;
 use ::std::pin::Pin;
+use ::std::sync::Arc;
+use ::std::time::{Instant, SystemTime};
 use ::typed_builder::TypedBuilder;
 
 /// Compile time information about an action.
@@ -44,14 +79,21 @@ pub trait ActionType {
     const TYPE: &'static str;
 }
 
+/// The object type an action is performed on, implemented by actions generated with the
+/// [`action`](authzen_proc_macros::action) macro.
+pub trait ObjectAction: ActionType {
+    type Object: ?Sized + ObjectType;
+}
+
 /// An action which requires authorization.
 #[doc(hidden)]
 #[async_trait]
-pub trait TryAct<DS, AE, Subject, Object, Input, Context, TC>:
+pub trait TryAct<DS, AE, SR, Subject, Object, Input, Context, TC>:
     Into<Event<Subject, Self::Action, Object, Input, Context>>
 where
     DS: ?Sized + DataSource + Send + Sync,
-    AE: ?Sized + AuthzEngine<Subject, Self::Action, Object, Input, Context, DS::TransactionId> + Sync,
+    AE: ?Sized + AuthzEngine<SR::Subject, Self::Action, Object, Input, Context, DS::TransactionId> + Sync,
+    SR: ?Sized + SubjectResolver<Subject, DS>,
     Subject: Send + Sync,
     Object: ?Sized + Send + ObjectType + Sync,
     Input: Send + Sync,
@@ -66,10 +108,12 @@ where
         authz_engine: &AE,
         data_source: &DS,
         transaction_cache: &TC,
+        decision_log: &dyn DecisionLog,
+        subject_resolver: &SR,
     ) -> Result<
         <Self::Action as StorageAction<DS, Input>>::Ok,
         ActionError<
-            <AE as AuthzEngine<Subject, Self::Action, Object, Input, Context, DS::TransactionId>>::Error,
+            <AE as AuthzEngine<SR::Subject, Self::Action, Object, Input, Context, DS::TransactionId>>::Error,
             <Self::Action as StorageAction<DS, Input>>::Error,
             TC::Error,
         >,
@@ -77,19 +121,30 @@ where
     where
         AE: 'async_trait,
         DS: 'async_trait,
+        SR: 'async_trait,
         TC: 'async_trait,
         Input: 'async_trait,
     {
         let event = self.into();
-        authz_engine
-            .can_act(event.subject, &event.input, event.context, data_source.transaction_id())
+        let subject = subject_resolver
+            .resolve(event.subject, data_source)
             .await
-            .map_err(ActionError::authz)?;
-        let ok = Self::Action::act(data_source, event.input)
+            .map_err(ActionError::SubjectResolver)?;
+        authorize::<_, _, Self::Action, Object, _, _, _, _, _>(
+            authz_engine,
+            subject,
+            &event.input,
+            event.context,
+            event.tenant.clone(),
+            data_source.transaction_id(),
+            decision_log,
+        )
+        .await?;
+        let ok = Self::Action::act_for_tenant(data_source, event.input, event.tenant.clone())
             .await
             .map_err(ActionError::DataSource)?;
         transaction_cache
-            .handle_success(data_source, &ok)
+            .handle_success(data_source, event.tenant, &ok)
             .await
             .map_err(ActionError::transaction_cache)?;
         Ok(ok)
@@ -97,11 +152,12 @@ where
 }
 
 #[async_trait]
-impl<DS, AE, Subject, A, Object, Input, Context, TC> TryAct<DS, AE, Subject, Object, Input, Context, TC>
+impl<DS, AE, SR, Subject, A, Object, Input, Context, TC> TryAct<DS, AE, SR, Subject, Object, Input, Context, TC>
     for Event<Subject, A, Object, Input, Context>
 where
     DS: ?Sized + DataSource + Send + Sync,
-    AE: ?Sized + AuthzEngine<Subject, A, Object, Input, Context, DS::TransactionId> + Sync,
+    AE: ?Sized + AuthzEngine<SR::Subject, A, Object, Input, Context, DS::TransactionId> + Sync,
+    SR: ?Sized + SubjectResolver<Subject, DS>,
     Subject: Send + Sync,
     Object: ?Sized + ObjectType + Send + Sync,
     Input: Send + Sync,
@@ -113,6 +169,253 @@ where
     type Action = A;
 }
 
+/// An action which is authorized against its own results rather than its input.
+///
+/// The action is performed first, the transaction cache is updated with its results and only then
+/// is the authorization engine queried, with the results of the action as the event's input. This
+/// allows policies to depend on values which are only known once an object has been written, e.g.
+/// ids, column defaults or values set by triggers.
+///
+/// The action is performed within a new transaction opened with [`TransactionalDataSource::tx`]
+/// (a nested transaction if the data source is already in one), which is rolled back if the action
+/// fails or is denied and committed only once the action has been authorized.
+#[doc(hidden)]
+#[async_trait]
+pub trait TryActVerified<DS, AE, SR, Subject, Object, Input, Context, TC>:
+    Into<Event<Subject, Self::Action, Object, Input, Context>>
+where
+    DS: ?Sized + TransactionalDataSource + Send + Sync,
+    DS::Error: std::error::Error + Send + Sync + 'static,
+    AE: ?Sized
+        + AuthzEngine<
+            SR::Subject,
+            Self::Action,
+            Object,
+            <Self::Action as StorageAction<DS, Input>>::Ok,
+            Context,
+            DS::TransactionId,
+        > + Sync,
+    SR: ?Sized + SubjectResolver<Subject, DS>,
+    Subject: Send + Sync,
+    Object: ?Sized + Send + ObjectType + Sync,
+    Input: Send + Sync,
+    Context: Send + Sync,
+    TC: Send + Sync + TransactionCache + TransactionCacheAction<Self::Action, DS, Input>,
+{
+    /// Action to be performed and authorized.
+    type Action: ActionType
+        + StorageAction<DS, Input>
+        + for<'r> StorageAction<
+            DS::TxConnection<'r>,
+            Input,
+            Ok = <Self::Action as StorageAction<DS, Input>>::Ok,
+            Error = <Self::Action as StorageAction<DS, Input>>::Error,
+        > + Send
+        + Sync;
+
+    async fn try_act_verified(
+        self,
+        authz_engine: &AE,
+        data_source: &DS,
+        transaction_cache: &TC,
+        decision_log: &dyn DecisionLog,
+        subject_resolver: &SR,
+    ) -> Result<
+        <Self::Action as StorageAction<DS, Input>>::Ok,
+        ActionError<
+            <AE as AuthzEngine<
+                SR::Subject,
+                Self::Action,
+                Object,
+                <Self::Action as StorageAction<DS, Input>>::Ok,
+                Context,
+                DS::TransactionId,
+            >>::Error,
+            <Self::Action as StorageAction<DS, Input>>::Error,
+            TC::Error,
+        >,
+    >
+    where
+        AE: 'async_trait,
+        DS: 'async_trait,
+        SR: 'async_trait,
+        TC: 'async_trait,
+        Input: 'async_trait,
+    {
+        let event = self.into();
+        let subject = subject_resolver
+            .resolve(event.subject, data_source)
+            .await
+            .map_err(ActionError::SubjectResolver)?;
+        let (context, tenant, input) = (event.context, event.tenant, event.input);
+
+        // the transaction is rolled back by returning an error from its callback, the actual
+        // reason for which is handed back out through `failure`
+        let mut failure = None;
+        let failure_ref = &mut failure;
+        let result = data_source
+            .tx(move |tx_connection: DS::TxConnection<'_>| {
+                async move {
+                    let transaction_id = tx_connection.transaction_id();
+                    let result = async {
+                        let ok = <Self::Action as StorageAction<DS::TxConnection<'_>, Input>>::act_for_tenant(
+                            &tx_connection,
+                            input,
+                            tenant.clone(),
+                        )
+                        .await
+                        .map_err(ActionError::DataSource)?;
+                        if let Some(transaction_id) = transaction_id.clone() {
+                            transaction_cache
+                                .manage_cache(transaction_id, tenant.clone(), &ok)
+                                .await
+                                .map_err(ActionError::transaction_cache)?;
+                        }
+                        authorize::<_, _, Self::Action, Object, _, _, _, _, _>(
+                            authz_engine,
+                            subject,
+                            &ok,
+                            context,
+                            tenant,
+                            transaction_id,
+                            decision_log,
+                        )
+                        .await?;
+                        Ok(ok)
+                    }
+                    .await;
+                    result.map_err(|err| {
+                        *failure_ref = Some(err);
+                        anyhow::Error::msg("action was not performed")
+                    })
+                }
+                .scope_boxed()
+            })
+            .await;
+        match result {
+            Ok(ok) => Ok(ok),
+            Err(err) => Err(failure.unwrap_or_else(|| ActionError::Transaction(TransactionError::new(err)))),
+        }
+    }
+}
+
+#[async_trait]
+impl<DS, AE, SR, Subject, A, Object, Input, Context, TC> TryActVerified<DS, AE, SR, Subject, Object, Input, Context, TC>
+    for Event<Subject, A, Object, Input, Context>
+where
+    DS: ?Sized + TransactionalDataSource + Send + Sync,
+    DS::Error: std::error::Error + Send + Sync + 'static,
+    AE: ?Sized
+        + AuthzEngine<SR::Subject, A, Object, <A as StorageAction<DS, Input>>::Ok, Context, DS::TransactionId>
+        + Sync,
+    SR: ?Sized + SubjectResolver<Subject, DS>,
+    Subject: Send + Sync,
+    Object: ?Sized + ObjectType + Send + Sync,
+    Input: Send + Sync,
+    Context: Send + Sync,
+    TC: Send + Sync + TransactionCache + TransactionCacheAction<A, DS, Input>,
+
+    A: ActionType
+        + StorageAction<DS, Input>
+        + for<'r> StorageAction<
+            DS::TxConnection<'r>,
+            Input,
+            Ok = <A as StorageAction<DS, Input>>::Ok,
+            Error = <A as StorageAction<DS, Input>>::Error,
+        > + Send
+        + Sync,
+{
+    type Action = A;
+}
+
+/// Resolves the subject of an event and queries the authorization engine with it, recording the
+/// decision if the decision log is enabled; used by the `can_*` methods generated by the
+/// [`action`](authzen_proc_macros::action) macro.
+#[doc(hidden)]
+#[allow(clippy::too_many_arguments)]
+pub async fn can_act<AE, DS, SR, Subject, Action, Object, Input, Context>(
+    authz_engine: &AE,
+    data_source: &DS,
+    decision_log: &dyn DecisionLog,
+    subject_resolver: &SR,
+    subject: Subject,
+    input: &Input,
+    context: Context,
+    tenant: Option<Tenant>,
+) -> Result<AE::Ok, ActionError<AE::Error, std::convert::Infallible, std::convert::Infallible>>
+where
+    AE: ?Sized + AuthzEngine<SR::Subject, Action, Object, Input, Context, DS::TransactionId> + Sync,
+    DS: ?Sized + DataSource,
+    SR: ?Sized + SubjectResolver<Subject, DS>,
+    Event<SR::Subject, Action, Object, Input, Context>: Send + Sync,
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+    let subject = subject_resolver
+        .resolve(subject, data_source)
+        .await
+        .map_err(ActionError::SubjectResolver)?;
+    authorize::<_, _, Action, Object, _, _, _, _, _>(
+        authz_engine,
+        subject,
+        input,
+        context,
+        tenant,
+        data_source.transaction_id(),
+        decision_log,
+    )
+    .await
+}
+
+/// Queries the authorization engine, recording the decision if the decision log is enabled.
+async fn authorize<AE, Subject, Action, Object, Input, Context, TransactionId, E2, E3>(
+    authz_engine: &AE,
+    subject: Subject,
+    input: &Input,
+    context: Context,
+    tenant: Option<Tenant>,
+    transaction_id: Option<TransactionId>,
+    decision_log: &dyn DecisionLog,
+) -> Result<AE::Ok, ActionError<AE::Error, E2, E3>>
+where
+    AE: ?Sized + AuthzEngine<Subject, Action, Object, Input, Context, TransactionId> + Sync,
+    Event<Subject, Action, Object, Input, Context>: Send + Sync,
+    Action: ?Sized + ActionType,
+    Object: ?Sized + ObjectType,
+{
+    let record = decision_log.enabled().then(|| DecisionRecord {
+        timestamp: SystemTime::now(),
+        subject: subject.maybe_serialize(),
+        action: Action::TYPE,
+        object: DecisionRecordObject {
+            service: Object::SERVICE,
+            ty: Object::TYPE,
+        },
+        input: input.maybe_serialize(),
+        context: context.maybe_serialize(),
+        tenant: tenant.clone(),
+        transaction_id: transaction_id.as_ref().map(MaybeSerialize::maybe_serialize),
+        allowed: false,
+        latency: Default::default(),
+        error: None,
+    });
+    let start = Instant::now();
+    let result = authz_engine
+        .can_act(subject, input, context, tenant, transaction_id)
+        .await;
+    if let Some(mut record) = record {
+        record.latency = start.elapsed();
+        record.allowed = result.is_ok();
+        record.error = result.as_ref().err().map(|err| format!("{err:?}"));
+        let logged = decision_log.log(&record).await;
+        let ok = result.map_err(ActionError::authz)?;
+        logged.map_err(ActionError::DecisionLog)?;
+        Ok(ok)
+    } else {
+        result.map_err(ActionError::authz)
+    }
+}
+
 /// Compile time information about an object.
 pub trait ObjectType {
     /// The service this object belongs to.
@@ -149,6 +452,9 @@ pub struct Event<Subject, Action: ?Sized, Object: ?Sized, Input, Context = ()> {
     /// any additional data which should or must be provided in order to fulfill the
     /// authorization decision; use this for any data which is not referring to objects being acted on
     pub context: Context,
+    /// the tenant which the event is scoped to, if any; omitted from the serialized event when absent
+    #[builder(default)]
+    pub tenant: Option<Tenant>,
 }
 
 impl<Subject, Action, Object, Input, Context> Debug for Event<Subject, Action, Object, Input, Context>
@@ -165,6 +471,7 @@ where
             .field("action", &Action::TYPE)
             .field("object", &fmt::DebugObject(self.object))
             .field("subject", &self.subject)
+            .field("tenant", &self.tenant)
             .finish()
     }
 }
@@ -209,6 +516,19 @@ where
     where
         Client: 'async_trait,
         Input: 'async_trait;
+
+    /// Carries out the intended action on behalf of an event scoped to `tenant` (see [`Event::tenant`]).
+    /// The default implementation ignores the tenant, so implementations addressing objects which belong to
+    /// a tenant should override it, e.g. the diesel actions which address rows by id fail if any of them
+    /// belongs to another tenant.
+    async fn act_for_tenant(client: &Client, input: Input, tenant: Option<Tenant>) -> Result<Self::Ok, Self::Error>
+    where
+        Client: 'async_trait,
+        Input: 'async_trait + Send,
+    {
+        let _ = tenant;
+        Self::act(client, input).await
+    }
 }
 
 pub trait StorageError {
@@ -231,6 +551,7 @@ where
         subject: Subject,
         input: &Input,
         context: Context,
+        tenant: Option<Tenant>,
         transaction_id: Option<TransactionId>,
     ) -> Result<Self::Ok, Self::Error>
     where
@@ -262,6 +583,7 @@ where
         subject: Subject,
         input: &Input,
         context: Context,
+        tenant: Option<Tenant>,
         transaction_id: Option<TransactionId>,
     ) -> Result<Self::Ok, Self::Error>
     where
@@ -277,17 +599,139 @@ where
             subject,
             input,
             context,
+            tenant,
             transaction_id,
         )
         .await
     }
 }
 
+/// A type erased [`Event`] which has already been serialized, allowing events with differing
+/// subjects, actions, objects, inputs and contexts to be authorized together in a single call to
+/// [`BatchAuthzEngine::can_act_batch`].
+#[derive(Clone, Debug, Serialize)]
+#[serde(transparent)]
+pub struct BatchEvent {
+    #[serde(skip)]
+    pub service: &'static str,
+    #[serde(skip)]
+    pub ty: &'static str,
+    #[serde(skip)]
+    pub action: &'static str,
+    pub value: serde_json::Value,
+}
+
+impl BatchEvent {
+    pub fn new<Subject, Action, Object, Input, Context>(
+        event: &Event<Subject, Action, Object, Input, Context>,
+    ) -> Result<Self, serde_json::Error>
+    where
+        Subject: Serialize,
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+        Input: Serialize,
+        Context: Serialize,
+    {
+        Ok(Self {
+            service: Object::SERVICE,
+            ty: Object::TYPE,
+            action: Action::TYPE,
+            value: serde_json::to_value(event)?,
+        })
+    }
+}
+
+/// A policy decision point which is capable of evaluating many events at once, e.g. by sending
+/// them to the authorization engine in a single request rather than one request per event.
+///
+/// This is a separate trait from [`AuthzEngine`] because an implementation of [`AuthzEngine`] is
+/// specific to a single subject, action, object, input and context type, so a batch method on it
+/// could only evaluate events which all share those types. Events are instead type erased into
+/// [`BatchEvent`]s, so that e.g. a list page can check whether each of its rows may be updated and
+/// deleted with one call:
+/// ```rs
+/// let events = items
+///     .iter()
+///     .flat_map(|item| {
+///         [
+///             BatchEvent::new(&Event::<_, Update<Item>, Item, _>::builder().subject(subject).input([item]).context(()).build()),
+///             BatchEvent::new(&Event::<_, Delete<Item>, Item, _>::builder().subject(subject).input([item.id]).context(()).build()),
+///         ]
+///     })
+///     .collect::<Result<Vec<_>, _>>()?;
+/// let decisions = ctx.authz_engine().can_act_batch(events, ctx.data_source().transaction_id()).await?;
+/// ```
+#[async_trait]
+pub trait BatchAuthzEngine<TransactionId> {
+    /// The outcome of evaluating a single event.
+    type Ok: Debug + Send;
+    type Error: Debug + Send;
+
+    /// Evaluates all provided events, returning one outcome per event in the same order as `events`.
+    /// An error is only returned if the batch as a whole could not be evaluated.
+    async fn can_act_batch(
+        &self,
+        events: Vec<BatchEvent>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Vec<Self::Ok>, Self::Error>
+    where
+        TransactionId: 'async_trait;
+}
+
+#[async_trait]
+impl<TransactionId, T> BatchAuthzEngine<TransactionId> for &T
+where
+    TransactionId: Send,
+    T: ?Sized + BatchAuthzEngine<TransactionId> + Sync,
+{
+    type Ok = T::Ok;
+    type Error = T::Error;
+    async fn can_act_batch(
+        &self,
+        events: Vec<BatchEvent>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<Vec<Self::Ok>, Self::Error>
+    where
+        TransactionId: 'async_trait,
+    {
+        (*self).can_act_batch(events, transaction_id).await
+    }
+}
+
+/// A policy decision point which can partially evaluate policies, producing an [`AuthzFilter`]
+/// describing all objects of type `Object` which the subject is allowed to perform `Action` on.
+/// Filters can then be pushed down into data sources (e.g. with
+/// [`DbGet::get_page_filtered`](authzen_data_sources::diesel::operations::DbGet::get_page_filtered))
+/// rather than fetching objects and authorizing them one by one.
+#[async_trait]
+pub trait PartialAuthzEngine<Subject, Context, TransactionId> {
+    type Error: Debug + Send;
+
+    async fn partial_filter<Action, Object>(
+        &self,
+        subject: Subject,
+        context: Context,
+        tenant: Option<Tenant>,
+        transaction_id: Option<TransactionId>,
+    ) -> Result<AuthzFilter, Self::Error>
+    where
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+        Subject: 'async_trait,
+        Context: 'async_trait,
+        TransactionId: 'async_trait;
+}
+
 pub trait Identifiable {
     type Id: Clone + DeserializeOwned + Eq + Hash + Send + Serialize + Sync + 'static;
     fn id(&self) -> &Self::Id;
 }
 
+/// Caches the entities written within a transaction so that they are visible to a policy information point
+/// before the transaction is committed.
+///
+/// Entities are keyed by transaction id and by the tenant of the event which wrote them; reads made with a
+/// tenant only return entities written for that tenant, while reads made without one are not scoped by tenant.
 pub trait TransactionCache {
     type Error: Debug + Send;
 
@@ -295,6 +739,7 @@ pub trait TransactionCache {
     fn get_entities<'life0, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
     ) -> Pin<Box<dyn Future<Output = Result<HashMap<T::Id, TxCacheEntity<T, T::Id>>, Self::Error>> + Send + 'async_trait>>
     where
         'life0: 'async_trait,
@@ -309,6 +754,7 @@ pub trait TransactionCache {
     fn get_by_ids<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         ids: &'life1 [T::Id],
     ) -> Pin<Box<dyn Future<Output = Result<Vec<T>, Self::Error>> + Send + 'async_trait>>
     where
@@ -324,6 +770,7 @@ pub trait TransactionCache {
     fn upsert<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         entities: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -338,6 +785,7 @@ pub trait TransactionCache {
     fn mark_deleted<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         entities: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -356,6 +804,7 @@ impl TransactionCache for () {
     fn get_entities<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         _: TransactionId,
+        _: Option<Tenant>,
     ) -> Pin<Box<dyn Future<Output = Result<HashMap<T::Id, TxCacheEntity<T, T::Id>>, Self::Error>> + Send + 'async_trait>>
     where
         'life0: 'async_trait,
@@ -372,6 +821,7 @@ impl TransactionCache for () {
     fn get_by_ids<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         _: TransactionId,
+        _: Option<Tenant>,
         _: &'life1 [T::Id],
     ) -> Pin<Box<dyn Future<Output = Result<Vec<T>, Self::Error>> + Send + 'async_trait>>
     where
@@ -390,6 +840,7 @@ impl TransactionCache for () {
     fn upsert<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         _: TransactionId,
+        _: Option<Tenant>,
         _: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -407,6 +858,7 @@ impl TransactionCache for () {
     fn mark_deleted<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         _: TransactionId,
+        _: Option<Tenant>,
         _: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -428,6 +880,7 @@ impl<TC: TransactionCache> TransactionCache for &TC {
     fn get_entities<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
     ) -> Pin<Box<dyn Future<Output = Result<HashMap<T::Id, TxCacheEntity<T, T::Id>>, Self::Error>> + Send + 'async_trait>>
     where
         'life0: 'async_trait,
@@ -438,12 +891,13 @@ impl<TC: TransactionCache> TransactionCache for &TC {
         T::Id: Clone,
         TransactionId: Send + Serialize,
     {
-        (*self).get_entities::<O, T, TransactionId>(transaction_id)
+        (*self).get_entities::<O, T, TransactionId>(transaction_id, tenant)
     }
 
     fn get_by_ids<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         ids: &'life1 [T::Id],
     ) -> Pin<Box<dyn Future<Output = Result<Vec<T>, Self::Error>> + Send + 'async_trait>>
     where
@@ -456,12 +910,13 @@ impl<TC: TransactionCache> TransactionCache for &TC {
         T::Id: Clone,
         TransactionId: Send + Serialize,
     {
-        (*self).get_by_ids::<O, T, TransactionId>(transaction_id, ids)
+        (*self).get_by_ids::<O, T, TransactionId>(transaction_id, tenant, ids)
     }
 
     fn upsert<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         entities: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -473,12 +928,13 @@ impl<TC: TransactionCache> TransactionCache for &TC {
         T: Identifiable + Serialize,
         TransactionId: Clone + Send + Serialize,
     {
-        (*self).upsert::<O, T, TransactionId>(transaction_id, entities)
+        (*self).upsert::<O, T, TransactionId>(transaction_id, tenant, entities)
     }
 
     fn mark_deleted<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         entities: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -490,7 +946,7 @@ impl<TC: TransactionCache> TransactionCache for &TC {
         T: Identifiable + Serialize,
         TransactionId: Clone + Send + Serialize,
     {
-        (*self).mark_deleted::<O, T, TransactionId>(transaction_id, entities)
+        (*self).mark_deleted::<O, T, TransactionId>(transaction_id, tenant, entities)
     }
 }
 
@@ -502,6 +958,7 @@ where
     fn handle_success<'life0, 'life1, 'life2, 'async_trait>(
         &'life0 self,
         data_source: &'life1 DS,
+        tenant: Option<Tenant>,
         ok: &'life2 A::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), Self::Error>> + Send + 'async_trait>>
     where
@@ -512,7 +969,7 @@ where
         'life2: 'async_trait,
     {
         if let Some(transaction_id) = data_source.transaction_id() {
-            self.manage_cache(transaction_id, ok)
+            self.manage_cache(transaction_id, tenant, ok)
         } else {
             Box::pin(async { Ok(()) })
         }
@@ -521,6 +978,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
         ok: &'life1 A::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -539,6 +997,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         _: DS::TransactionId,
+        _: Option<Tenant>,
         _: &'life1 A::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -561,6 +1020,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
         ok: &'life1 A::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -570,7 +1030,7 @@ where
         'life0: 'async_trait,
         'life1: 'async_trait,
     {
-        (*self).manage_cache(transaction_id, ok)
+        (*self).manage_cache(transaction_id, tenant, ok)
     }
 }
 
@@ -610,17 +1070,31 @@ pub trait AuthorizationContext<AE, DS, TC> {
     type Subject<'a>: Send + Sync
     where
         Self: 'a;
+    /// resolves the subject of attempted actions before they are authorized (see [`SubjectResolver`]);
+    /// derived contexts without a field marked `#[subject_resolver]` use `()`, which leaves the subject as is
+    type SubjectResolver: Send + Sync;
 
     fn context(&self) -> Self::Context<'_>;
     fn subject(&self) -> Self::Subject<'_>;
+    /// The tenant which all actions attempted with this context are scoped to; derived contexts
+    /// use the field marked `#[tenant]` (see [`AsTenant`]), and by default actions are not scoped to a tenant.
+    fn tenant(&self) -> Option<Tenant> {
+        None
+    }
     fn authz_engine(&self) -> &AE;
     fn data_source(&self) -> &DS;
     fn transaction_cache(&self) -> &TC;
+    /// Sink which records every authorization decision made using this context;
+    /// by default decisions are not recorded.
+    fn decision_log(&self) -> &dyn DecisionLog {
+        &()
+    }
+    fn subject_resolver(&self) -> &Self::SubjectResolver;
 }
 
 /// Represents the possible sources of error when performing
 /// an action which requires authorization.
-#[derive(Clone, Copy, Debug, Error, IsVariant, Unwrap)]
+#[derive(Clone, Debug, Error, IsVariant, Unwrap)]
 pub enum ActionError<E1, E2, E3> {
     /// Wraps an error returned from a [`AuthzEngine`] when the subject is either not authorized to
     /// perform an action or some other issue occurs while communicating with the
@@ -634,6 +1108,26 @@ pub enum ActionError<E1, E2, E3> {
     /// Wraps an error returned from a [`TransactionCache`] when updating the transaction
     /// cache after a successful performance of the action.
     TransactionCache(E3),
+    /// Wraps an error returned from a [`DecisionLog`] when the decision made by the
+    /// [`AuthzEngine`] could not be recorded; the action is not performed in this case.
+    DecisionLog(DecisionLogError),
+    /// Wraps an error returned from a [`TransactionalDataSource`] when the transaction in which
+    /// an action authorized against its results is performed could not be opened or committed.
+    Transaction(TransactionError),
+    /// Wraps an error returned from a [`SubjectResolver`] when the subject could not be resolved
+    /// before querying the [`AuthzEngine`]; the action is not performed in this case.
+    SubjectResolver(SubjectResolverError),
+}
+
+/// Error returned from a [`TransactionalDataSource`] which was unable to open or commit a transaction.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("unable to perform action within a transaction: {0}")]
+pub struct TransactionError(pub Arc<dyn std::error::Error + Send + Sync>);
+
+impl TransactionError {
+    pub fn new(err: anyhow::Error) -> Self {
+        Self(Arc::from(Box::<dyn std::error::Error + Send + Sync>::from(err)))
+    }
 }
 
 impl<E1, E2, E3> ActionError<E1, E2, E3> {
@@ -648,6 +1142,32 @@ impl<E1, E2, E3> ActionError<E1, E2, E3> {
     }
 }
 
+impl<E1: ClassifyAuthzError, E2, E3> ActionError<E1, E2, E3> {
+    /// Classifies the error returned from the [`AuthzEngine`], if this error originated there.
+    pub fn authz_error_kind(&self) -> Option<AuthzErrorKind> {
+        match self {
+            Self::Authz(err) => Some(err.authz_error_kind()),
+            _ => None,
+        }
+    }
+}
+
+/// Registers an object type, emitted by the [`AuthzObject`](authzen_proc_macros::AuthzObject) derive.
+#[cfg(not(feature = "registry"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __authzen_register_object {
+    ($($tt:tt)*) => {};
+}
+
+/// Registers an action type, emitted by the [`action`](authzen_proc_macros::action) macro.
+#[cfg(not(feature = "registry"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __authzen_register_action {
+    ($($tt:tt)*) => {};
+}
+
 /// Standard actions which are useful across many applications.
 ///
 /// Custom actions can be generated using the [`action`](authzen_proc_macros::action) macro.
@@ -657,8 +1177,12 @@ pub mod actions {
 
     action!(__authzen_internal, Create);
     action!(__authzen_internal, Delete);
+    action!(__authzen_internal, List);
+    action!(__authzen_internal, Purge);
     action!(__authzen_internal, Read);
+    action!(__authzen_internal, Restore);
     action!(__authzen_internal, Update);
+    action!(__authzen_internal, Upsert);
 }
 
 #[doc(hidden)]
@@ -807,3 +1331,263 @@ pub mod fmt {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actions::Create;
+    use ::scoped_futures::ScopedBoxFuture;
+    use ::std::sync::Mutex;
+
+    struct Item;
+
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "test";
+        const TYPE: &'static str = "item";
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("store error")]
+    struct StoreError;
+
+    impl StorageError for StoreError {
+        fn not_found() -> Self {
+            Self
+        }
+    }
+
+    /// Rolls back every change made within a transaction whose callback returns an error.
+    #[derive(Clone, Debug, Default)]
+    struct Store {
+        items: Arc<Mutex<Vec<u32>>>,
+        transaction_id: Option<u64>,
+    }
+
+    impl DataSource for Store {
+        type Backend = ();
+        type Error = StoreError;
+        type TransactionId = u64;
+
+        fn transaction_id(&self) -> Option<u64> {
+            self.transaction_id
+        }
+    }
+
+    #[async_trait]
+    impl TransactionalDataSource for Store {
+        type AsyncConnection = ();
+        type Connection<'r> = &'r ();
+        type TxConnection<'r> = Store;
+
+        async fn query<'a, F, T, E>(&self, f: F) -> Result<T, E>
+        where
+            F: for<'r> FnOnce(&'r mut ()) -> ScopedBoxFuture<'a, 'r, Result<T, E>> + Send + 'a,
+            E: Debug + From<StoreError> + Send + 'a,
+            T: Send + 'a,
+        {
+            f(&mut ()).await
+        }
+
+        async fn with_tx_connection<'a, F, T, E>(&self, f: F) -> Result<T, E>
+        where
+            F: for<'r> FnOnce(&'r mut ()) -> ScopedBoxFuture<'a, 'r, Result<T, E>> + Send + 'a,
+            E: Debug + From<StoreError> + Send + 'a,
+            T: Send + 'a,
+        {
+            f(&mut ()).await
+        }
+
+        async fn tx_cleanup<F, E>(&self, _: F)
+        where
+            F: for<'r> TxCleanupFn<'r, (), E, u64>,
+            E: Into<TxCleanupError> + 'static,
+        {
+        }
+
+        async fn tx_commit<F>(&self, _: F)
+        where
+            F: TxCommitFn,
+        {
+        }
+
+        async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
+        where
+            F: for<'r> TxFn<'a, Store, ScopedBoxFuture<'a, 'r, Result<T, E>>>,
+            E: Debug + From<StoreError> + From<TxCleanupError> + Send + 'a,
+            T: Send + 'a,
+            'life0: 'a,
+        {
+            let snapshot = self.items.lock().unwrap().clone();
+            let tx_connection = Store {
+                items: self.items.clone(),
+                transaction_id: Some(self.transaction_id.map(|id| id + 1).unwrap_or_default()),
+            };
+            let result = callback.call_tx_fn(tx_connection).await;
+            if result.is_err() {
+                *self.items.lock().unwrap() = snapshot;
+            }
+            result
+        }
+    }
+
+    #[async_trait]
+    impl StorageAction<Store, Vec<u32>> for Create<Item> {
+        type Ok = Vec<u32>;
+        type Error = StoreError;
+
+        async fn act(client: &Store, input: Vec<u32>) -> Result<Self::Ok, Self::Error> {
+            client.items.lock().unwrap().extend(input.iter().copied());
+            Ok(input)
+        }
+    }
+
+    /// Denies any event whose input contains the id `0` and records the subjects and transaction ids it is queried with.
+    #[derive(Default)]
+    struct Engine {
+        queries: Mutex<Vec<(u32, Option<u64>)>>,
+    }
+
+    #[async_trait]
+    impl AuthzEngine<u32, Create<Item>, Item, Vec<u32>, (), u64> for Engine {
+        type Ok = ();
+        type Error = &'static str;
+
+        async fn can_act(
+            &self,
+            subject: u32,
+            input: &Vec<u32>,
+            _: (),
+            _: Option<Tenant>,
+            transaction_id: Option<u64>,
+        ) -> Result<(), &'static str> {
+            self.queries.lock().unwrap().push((subject, transaction_id));
+            match input.contains(&0) {
+                true => Err("denied"),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[derive(Deserialize, Serialize)]
+    struct DbItem {
+        id: u32,
+    }
+
+    impl Identifiable for DbItem {
+        type Id = u32;
+        fn id(&self) -> &u32 {
+            &self.id
+        }
+    }
+
+    impl StorageObject<()> for DbItem {}
+
+    impl AsStorage<()> for Item {
+        type Constructor<'a> = Item;
+        type StorageObject = DbItem;
+    }
+
+    /// Resolves account ids into the ids of the accounts they act on behalf of.
+    struct Delegate;
+
+    #[async_trait]
+    impl SubjectResolver<u32, Store> for Delegate {
+        type Subject = u32;
+
+        async fn resolve(&self, subject: u32, _: &Store) -> Result<u32, SubjectResolverError> {
+            Ok(subject * 10)
+        }
+    }
+
+    struct Ctx {
+        engine: Engine,
+        store: Store,
+        decision_log: JsonLinesDecisionLog<Vec<u8>>,
+    }
+
+    impl Default for Ctx {
+        fn default() -> Self {
+            Self {
+                engine: Default::default(),
+                store: Default::default(),
+                decision_log: JsonLinesDecisionLog::new(vec![]),
+            }
+        }
+    }
+
+    impl AuthorizationContext<Engine, Store, ()> for Ctx {
+        type Context<'a> = ();
+        type Subject<'a> = u32;
+        type SubjectResolver = Delegate;
+
+        fn context(&self) -> Self::Context<'_> {}
+        fn subject(&self) -> Self::Subject<'_> {
+            1
+        }
+        fn authz_engine(&self) -> &Engine {
+            &self.engine
+        }
+        fn data_source(&self) -> &Store {
+            &self.store
+        }
+        fn transaction_cache(&self) -> &() {
+            &()
+        }
+        fn decision_log(&self) -> &dyn DecisionLog {
+            &self.decision_log
+        }
+        fn subject_resolver(&self) -> &Self::SubjectResolver {
+            &Delegate
+        }
+    }
+
+    #[test]
+    fn queries_with_resolved_subjects_and_records_decisions() {
+        use crate::actions::TryCreate;
+
+        let ctx = Ctx::default();
+        let can_create = |input: Vec<u32>| futures::executor::block_on(Item::can_create(&ctx, &input));
+        assert!(can_create(vec![1]).is_ok());
+        assert!(matches!(can_create(vec![0]), Err(ActionError::Authz("denied"))));
+
+        assert!(ctx.store.items.lock().unwrap().is_empty());
+        assert_eq!(*ctx.engine.queries.lock().unwrap(), vec![(10, None), (10, None)]);
+        let decisions = String::from_utf8(ctx.decision_log.into_inner()).unwrap();
+        let allowed = decisions
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["allowed"].clone())
+            .collect::<Vec<_>>();
+        assert_eq!(allowed, vec![true, false]);
+    }
+
+    #[test]
+    fn rolls_back_denied_actions_authorized_against_their_results() {
+        use crate::actions::TryCreate;
+
+        let try_create_verified =
+            |ctx: &Ctx, input: Vec<u32>| futures::executor::block_on(Item::try_create_verified(ctx, input));
+
+        let ctx = Ctx::default();
+        assert_eq!(try_create_verified(&ctx, vec![1, 2]).unwrap(), vec![1, 2]);
+        assert!(matches!(
+            try_create_verified(&ctx, vec![3, 0]),
+            Err(ActionError::Authz("denied"))
+        ));
+        assert_eq!(*ctx.store.items.lock().unwrap(), vec![1, 2]);
+
+        let ctx = Ctx {
+            store: Store {
+                transaction_id: Some(1),
+                ..ctx.store
+            },
+            ..ctx
+        };
+        assert!(try_create_verified(&ctx, vec![0]).is_err());
+        assert_eq!(*ctx.store.items.lock().unwrap(), vec![1, 2]);
+
+        assert_eq!(
+            *ctx.engine.queries.lock().unwrap(),
+            vec![(10, Some(0)), (10, Some(0)), (10, Some(2))]
+        );
+    }
+}
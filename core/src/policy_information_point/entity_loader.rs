@@ -0,0 +1,66 @@
+use crate::policy_information_point::*;
+use ::authzen_cedar::{entity_id, EntityLoader, EntityLoaderError};
+use ::derivative::Derivative;
+use ::serde::de::DeserializeOwned;
+use ::serde_json::{json, Value};
+use ::std::marker::PhantomData;
+
+/// Loads the entities passed to a [`CedarEngine`](authzen_cedar::CedarEngine) with the same [`Query`]
+/// served by a policy information point, so that resources reflect the changes made within the
+/// transaction of the event being authorized (see [`ObjectQuery::fetch_with_tx_data`]).
+///
+/// Queries are deserialized from `{ "service": ..., "type": ..., "ids": [...] }`, the same structure
+/// sent by `data.util.fetch` in OPA policies, and are provided a context constructed from the
+/// loader's clients and the transaction id of the event.
+/// ```rs
+/// let cedar_engine = CedarEngine::new(policies, "accounts::account")?
+///     .loader(QueryEntityLoader::<Request, Ctx, _>::new(clients));
+/// ```
+#[derive(Derivative)]
+#[derivative(Clone(bound = "Clients: Clone"), Debug(bound = ""))]
+pub struct QueryEntityLoader<Q, Ctx, Clients> {
+    #[derivative(Debug = "ignore")]
+    clients: Clients,
+    _marker: PhantomData<fn() -> (Q, Ctx)>,
+}
+
+impl<Q, Ctx, Clients> QueryEntityLoader<Q, Ctx, Clients> {
+    pub fn new(clients: Clients) -> Self {
+        Self {
+            clients,
+            _marker: PhantomData,
+        }
+    }
+}
+
+#[async_trait]
+impl<Q, Ctx, Clients, Id> EntityLoader<Id> for QueryEntityLoader<Q, Ctx, Clients>
+where
+    Q: DeserializeOwned + Query<Ctx> + Send,
+    Ctx: Send + Sync,
+    Clients: Clone + Send + Sync,
+    Id: Clone + Sync,
+    (Clients, Option<Id>): Into<Ctx>,
+{
+    async fn load(
+        &self,
+        service: &'static str,
+        ty: &'static str,
+        ids: &[Value],
+        transaction_id: Option<&Id>,
+    ) -> Result<HashMap<String, Value>, EntityLoaderError> {
+        let query: Q = serde_json::from_value(json!({ "service": service, "type": ty, "ids": ids }))
+            .map_err(EntityLoaderError::invalid)?;
+        let ctx: Ctx = (self.clients.clone(), transaction_id.cloned()).into();
+        let response = query
+            .fetch(&ctx)
+            .await
+            .map_err(|err| EntityLoaderError::unavailable(format!("{err:?}")))?;
+        let values: HashMap<String, Value> =
+            serde_json::from_slice(&response.values).map_err(EntityLoaderError::invalid)?;
+        Ok(values
+            .into_values()
+            .filter_map(|value| Some((entity_id(value.get("id")?), value)))
+            .collect())
+    }
+}
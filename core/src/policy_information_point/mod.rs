@@ -1,7 +1,11 @@
+#[cfg(feature = "cedar-authz-engine")]
+mod entity_loader;
 #[cfg(feature = "policy-information-point-server")]
 mod server;
 mod transaction_cache;
 
+#[cfg(feature = "cedar-authz-engine")]
+pub use entity_loader::*;
 #[cfg(feature = "policy-information-point-server")]
 pub use server::*;
 pub use transaction_cache::*;
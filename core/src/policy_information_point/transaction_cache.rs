@@ -37,7 +37,8 @@ where
         Ctx: 'async_trait,
     {
         if let Some(transaction_id) = AsRef::<DS>::as_ref(ctx).transaction_id() {
-            <TC as TransactionCache>::get_entities::<T, T, DS::TransactionId>(ctx.as_ref(), transaction_id).boxed()
+            <TC as TransactionCache>::get_entities::<T, T, DS::TransactionId>(ctx.as_ref(), transaction_id, None)
+                .boxed()
         } else {
             async move { Ok(Default::default()) }.boxed()
         }
@@ -0,0 +1,155 @@
+use ::serde::Serialize;
+use ::std::collections::BTreeSet;
+
+#[doc(hidden)]
+pub use ::inventory;
+
+/// An object type registered by deriving [`AuthzObject`](authzen_proc_macros::AuthzObject).
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ObjectRegistration {
+    pub service: &'static str,
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+    /// Schemas of the object's inner type and id, only available for objects whose sole generic
+    /// parameter is the lifetime of their inner [`Cow`](std::borrow::Cow).
+    #[cfg(feature = "schema")]
+    #[serde(skip)]
+    pub schema: Option<crate::ObjectSchema>,
+}
+
+/// An action type registered by the [`action`](authzen_proc_macros::action) macro.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct ActionRegistration {
+    #[serde(rename = "type")]
+    pub ty: &'static str,
+}
+
+inventory::collect!(ObjectRegistration);
+inventory::collect!(ActionRegistration);
+
+/// All object and action types registered in the running binary, sorted and deduplicated.
+///
+/// Objects are registered by deriving [`AuthzObject`](authzen_proc_macros::AuthzObject) and actions are
+/// registered by the [`action`](authzen_proc_macros::action) macro, including authzen's own
+/// [`actions`](crate::actions). Only types which are linked into the running binary can be found, so a
+/// binary which enumerates the types of an application must depend on the crates which define them.
+/// ```rs
+/// println!("{}", serde_json::to_string_pretty(&Registry::collect())?);
+/// ```
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Registry {
+    pub objects: Vec<ObjectRegistration>,
+    pub actions: Vec<ActionRegistration>,
+}
+
+impl Registry {
+    pub fn collect() -> Self {
+        Self::from_registrations(
+            inventory::iter::<ObjectRegistration>.into_iter().copied(),
+            inventory::iter::<ActionRegistration>.into_iter().copied(),
+        )
+    }
+
+    fn from_registrations(
+        objects: impl IntoIterator<Item = ObjectRegistration>,
+        actions: impl IntoIterator<Item = ActionRegistration>,
+    ) -> Self {
+        let mut seen_objects = BTreeSet::new();
+        let mut objects = objects
+            .into_iter()
+            .filter(|object| seen_objects.insert((object.service, object.ty)))
+            .collect::<Vec<_>>();
+        objects.sort_by_key(|object| (object.service, object.ty));
+
+        let actions = actions
+            .into_iter()
+            .map(|action| action.ty)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(|ty| ActionRegistration { ty })
+            .collect();
+
+        Self { objects, actions }
+    }
+}
+
+/// Registers an object type, emitted by the [`AuthzObject`](authzen_proc_macros::AuthzObject) derive.
+#[cfg(feature = "schema")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __authzen_register_object {
+    ($service:expr, $ty:expr $(,)?) => {
+        $crate::registry::inventory::submit! {
+            $crate::registry::ObjectRegistration { service: $service, ty: $ty, schema: None }
+        }
+    };
+    ($service:expr, $ty:expr, $inner_ty:ty $(,)?) => {
+        $crate::registry::inventory::submit! {
+            $crate::registry::ObjectRegistration {
+                service: $service,
+                ty: $ty,
+                schema: Some($crate::ObjectSchema {
+                    schema: $crate::schemars::gen::SchemaGenerator::subschema_for::<$inner_ty>,
+                    id_schema: $crate::schemars::gen::SchemaGenerator::subschema_for::<<$inner_ty as $crate::Identifiable>::Id>,
+                }),
+            }
+        }
+    };
+}
+
+/// Registers an object type, emitted by the [`AuthzObject`](authzen_proc_macros::AuthzObject) derive.
+#[cfg(not(feature = "schema"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __authzen_register_object {
+    ($service:expr, $ty:expr $(, $inner_ty:ty)? $(,)?) => {
+        $crate::registry::inventory::submit! {
+            $crate::registry::ObjectRegistration { service: $service, ty: $ty }
+        }
+    };
+}
+
+/// Registers an action type, emitted by the [`action`](authzen_proc_macros::action) macro.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __authzen_register_action {
+    ($ty:expr $(,)?) => {
+        $crate::registry::inventory::submit! {
+            $crate::registry::ActionRegistration { ty: $ty }
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sorts_and_deduplicates_registrations() {
+        let object = |service, ty| ObjectRegistration {
+            service,
+            ty,
+            #[cfg(feature = "schema")]
+            schema: None,
+        };
+        let registry = Registry::from_registrations(
+            [object("cart", "item"), object("cart", "cart"), object("cart", "item")],
+            ["read", "create", "read"].map(|ty| ActionRegistration { ty }),
+        );
+        assert_eq!(
+            serde_json::to_value(&registry).unwrap(),
+            serde_json::json!({
+                "objects": [{ "service": "cart", "type": "cart" }, { "service": "cart", "type": "item" }],
+                "actions": [{ "type": "create" }, { "type": "read" }],
+            }),
+        );
+    }
+
+    #[test]
+    fn collects_standard_actions() {
+        let registry = Registry::collect();
+        for action in ["create", "delete", "read", "update"] {
+            assert!(registry.actions.iter().any(|registration| registration.ty == action));
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use crate::registry::{inventory, ActionRegistration, ObjectRegistration};
+use crate::{ActionType, ObjectType};
+use ::schemars::gen::{SchemaGenerator, SchemaSettings};
+use ::schemars::schema::{RootSchema, Schema, SchemaObject};
+use ::schemars::JsonSchema;
+use ::serde_json::json;
+use ::std::collections::{BTreeMap, BTreeSet};
+use ::std::marker::PhantomData;
+use ::std::path::Path;
+
+pub use ::schemars;
+
+/// Schemas of an object registered by deriving [`AuthzObject`](authzen_proc_macros::AuthzObject).
+///
+/// Only objects whose sole generic parameter is the lifetime of their inner [`Cow`](std::borrow::Cow)
+/// have schemas; objects with additional generic parameters can be included in an
+/// [`EventSchemas`] with [`EventSchemas::input`].
+#[derive(Clone, Copy, Debug)]
+pub struct ObjectSchema {
+    /// schema of the object's inner type
+    pub schema: fn(&mut SchemaGenerator) -> Schema,
+    /// schema of the object's id
+    pub id_schema: fn(&mut SchemaGenerator) -> Schema,
+}
+
+/// Generates a JSON Schema for each event an application can send to an authorization engine, i.e. for
+/// every combination of object registered by [`AuthzObject`](authzen_proc_macros::AuthzObject) and action
+/// registered by the [`action`](authzen_proc_macros::action) macro, describing the `subject`, `action`,
+/// `object`, `input` and `context` of the event, its `tenant` (omitted from events which are not scoped to a
+/// tenant) as well as the `transaction_id` sent alongside it to OPA.
+///
+/// The input of each event is described as an array whose items are either the object or its id, which
+/// matches the inputs of the standard actions provided by authzen's data sources other than `List` and `Update`;
+/// use [`EventSchemas::input`] to describe the input of any other events.
+/// ```rs
+/// EventSchemas::<Uuid, (), Uuid>::new()
+///     .input::<Update<Item<'static>>, Item<'static>, Vec<DbItemPatch<'static>>>()
+///     .write("policies/schemas")?;
+/// ```
+pub struct EventSchemas<Subject, Context = (), TransactionId = ()> {
+    settings: SchemaSettings,
+    inputs: BTreeMap<EventKey, InputSchema>,
+    _marker: PhantomData<(Subject, Context, TransactionId)>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct EventKey {
+    service: &'static str,
+    ty: &'static str,
+    action: &'static str,
+}
+
+#[derive(Clone, Copy)]
+enum InputSchema {
+    /// an array of either objects or their ids
+    Objects(ObjectSchema),
+    Custom(fn(&mut SchemaGenerator) -> Schema),
+}
+
+impl<Subject, Context, TransactionId> Default for EventSchemas<Subject, Context, TransactionId>
+where
+    Subject: JsonSchema,
+    Context: JsonSchema,
+    TransactionId: JsonSchema,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Subject, Context, TransactionId> EventSchemas<Subject, Context, TransactionId>
+where
+    Subject: JsonSchema,
+    Context: JsonSchema,
+    TransactionId: JsonSchema,
+{
+    pub fn new() -> Self {
+        Self {
+            settings: SchemaSettings::draft07(),
+            inputs: Default::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Settings used to generate each schema, draft 7 by default.
+    pub fn settings(mut self, settings: SchemaSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Describes the input of events with the specified action and object, including the event
+    /// even if either the action or object is not registered.
+    pub fn input<Action, Object, Input>(mut self) -> Self
+    where
+        Action: ?Sized + ActionType,
+        Object: ?Sized + ObjectType,
+        Input: JsonSchema,
+    {
+        self.inputs.insert(
+            EventKey {
+                service: Object::SERVICE,
+                ty: Object::TYPE,
+                action: Action::TYPE,
+            },
+            InputSchema::Custom(SchemaGenerator::subschema_for::<Input>),
+        );
+        self
+    }
+
+    /// Generates the schema of each event, keyed by `{service}.{type}.{action}`.
+    pub fn generate(&self) -> BTreeMap<String, RootSchema> {
+        let actions = inventory::iter::<ActionRegistration>
+            .into_iter()
+            .map(|action| action.ty)
+            .collect::<BTreeSet<_>>();
+
+        let mut events = BTreeMap::<EventKey, InputSchema>::new();
+        for object in inventory::iter::<ObjectRegistration> {
+            let Some(schema) = object.schema else { continue };
+            for &action in &actions {
+                let key = EventKey {
+                    service: object.service,
+                    ty: object.ty,
+                    action,
+                };
+                events.insert(key, InputSchema::Objects(schema));
+            }
+        }
+        events.extend(self.inputs.iter().map(|(key, input)| (*key, *input)));
+
+        events
+            .into_iter()
+            .map(|(key, input)| {
+                let name = format!("{}.{}.{}", key.service, key.ty, key.action);
+                let schema = self.event_schema(&name, key, input);
+                (name, schema)
+            })
+            .collect()
+    }
+
+    /// Writes the schema of each event to `{dir}/{service}.{type}.{action}.json`.
+    pub fn write(&self, dir: impl AsRef<Path>) -> std::io::Result<()> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+        for (name, schema) in self.generate() {
+            let mut contents = serde_json::to_string_pretty(&schema)?;
+            contents.push('\n');
+            std::fs::write(dir.join(format!("{name}.json")), contents)?;
+        }
+        Ok(())
+    }
+
+    fn event_schema(&self, title: &str, key: EventKey, input: InputSchema) -> RootSchema {
+        let mut gen = self.settings.clone().into_generator();
+        let schema = json!({
+            "title": title,
+            "type": "object",
+            "properties": {
+                "subject": gen.subschema_for::<Subject>(),
+                "action": { "const": key.action },
+                "object": {
+                    "type": "object",
+                    "properties": {
+                        "service": { "const": key.service },
+                        "type": { "const": key.ty },
+                    },
+                    "required": ["service", "type"],
+                },
+                "input": input.subschema(&mut gen),
+                "context": gen.subschema_for::<Context>(),
+                "tenant": { "type": "string" },
+                "transaction_id": gen.subschema_for::<Option<TransactionId>>(),
+            },
+            "required": ["subject", "action", "object", "input", "context", "transaction_id"],
+        });
+        RootSchema {
+            meta_schema: gen.settings().meta_schema.clone(),
+            schema: serde_json::from_value::<SchemaObject>(schema).expect("event schema is a valid schema object"),
+            definitions: gen.take_definitions(),
+        }
+    }
+}
+
+impl InputSchema {
+    fn subschema(&self, gen: &mut SchemaGenerator) -> Schema {
+        match self {
+            Self::Objects(schema) => {
+                let items = json!({ "anyOf": [(schema.schema)(gen), (schema.id_schema)(gen)] });
+                serde_json::from_value(json!({ "type": "array", "items": items }))
+                    .expect("input schema is a valid schema")
+            }
+            Self::Custom(subschema) => subschema(gen),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actions::{Read, Update};
+
+    struct Item;
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "cart";
+        const TYPE: &'static str = "item";
+    }
+
+    #[test]
+    fn generates_schemas_for_described_inputs() {
+        let schemas = EventSchemas::<u32>::new()
+            .input::<Read<Item>, Item, Vec<u32>>()
+            .input::<Update<Item>, Item, Vec<(u32, String)>>()
+            .generate();
+        assert_eq!(
+            schemas.keys().collect::<Vec<_>>(),
+            ["cart.item.read", "cart.item.update"]
+        );
+
+        let schema = serde_json::to_value(&schemas["cart.item.read"]).unwrap();
+        assert_eq!(schema["properties"]["action"]["const"], "read");
+        assert_eq!(schema["properties"]["object"]["properties"]["service"]["const"], "cart");
+        assert_eq!(schema["properties"]["input"]["items"]["type"], "integer");
+        assert_eq!(schema["properties"]["context"]["type"], "null");
+        assert_eq!(schema["properties"]["tenant"]["type"], "string");
+        assert!(!schema["required"].as_array().unwrap().contains(&"tenant".into()));
+    }
+}
@@ -0,0 +1,296 @@
+use crate::*;
+use ::authzen_data_sources::DataSource;
+use ::std::marker::PhantomData;
+
+/// Two actions performed one after the other, where the result of the first action is mapped into
+/// the input of the second action, e.g. `Sequence<Create<CartItem>, Delete<CartItem>>`.
+///
+/// Each action is authorized as its own event, with the subject, context and tenant of the context
+/// the sequence is attempted with: the first action is authorized against its input and performed,
+/// then the second action is authorized against the input mapped from the first action's result and
+/// performed. Both actions must be performed within the same transaction, so that returning the
+/// error from the transaction callback when the second action is denied or fails rolls back the
+/// first action as well. The [`sequence`](crate::sequence!) macro opens that transaction.
+pub struct Sequence<A: ?Sized, B: ?Sized>(PhantomData<A>, PhantomData<B>);
+
+/// Represents the possible sources of error when performing a [`Sequence`] of actions.
+#[derive(Clone, Debug, Error, IsVariant, Unwrap)]
+pub enum SequenceError<E1, E2> {
+    /// Wraps an error returned while authorizing or performing the first action.
+    First(E1),
+    /// Wraps an error returned while authorizing or performing the second action;
+    /// the first action has already been performed in this case.
+    Second(E2),
+    /// Returned when a sequence is attempted outside of a transaction, in which case neither action is performed.
+    TransactionRequired,
+}
+
+/// Error returned from [`Sequence::try_act`] for context `Ctx` borrowed for `'a`.
+pub type SequenceActionError<'a, Ctx, AE, DS, TC, A, B, IA, IB> =
+    SequenceError<StepError<'a, Ctx, AE, DS, TC, A, IA>, StepError<'a, Ctx, AE, DS, TC, B, IB>>;
+
+/// Error returned from a single step of a [`Sequence`].
+pub type StepError<'a, Ctx, AE, DS, TC, A, I> = ActionError<
+    <AE as AuthzEngine<
+        ResolvedSubject<'a, Ctx, AE, DS, TC>,
+        A,
+        <A as ObjectAction>::Object,
+        I,
+        <Ctx as AuthorizationContext<AE, DS, TC>>::Context<'a>,
+        <DS as DataSource>::TransactionId,
+    >>::Error,
+    <A as StorageAction<DS, I>>::Error,
+    <TC as TransactionCache>::Error,
+>;
+
+type ResolvedSubject<'a, Ctx, AE, DS, TC> =
+    <<Ctx as AuthorizationContext<AE, DS, TC>>::SubjectResolver as SubjectResolver<
+        <Ctx as AuthorizationContext<AE, DS, TC>>::Subject<'a>,
+        DS,
+    >>::Subject;
+
+impl<A, B> Sequence<A, B>
+where
+    A: ObjectAction + Send + Sync,
+    B: ObjectAction + Send + Sync,
+    A::Object: Send + Sync,
+    B::Object: Send + Sync,
+{
+    /// Authorizes and performs the first action with `input`, then authorizes and performs the
+    /// second action with the input returned from `then`. The context's data source must be in a
+    /// transaction, otherwise [`SequenceError::TransactionRequired`] is returned before either action
+    /// is authorized.
+    pub async fn try_act<'a, Ctx, AE, DS, TC, IA, IB, F>(
+        ctx: &'a Ctx,
+        input: IA,
+        then: F,
+    ) -> Result<<B as StorageAction<DS, IB>>::Ok, SequenceActionError<'a, Ctx, AE, DS, TC, A, B, IA, IB>>
+    where
+        Ctx: AuthorizationContext<AE, DS, TC> + Sync,
+        Ctx::Subject<'a>: Clone,
+        Ctx::Context<'a>: Clone,
+        Ctx::SubjectResolver: SubjectResolver<Ctx::Subject<'a>, DS>,
+        AE: AuthzEngine<ResolvedSubject<'a, Ctx, AE, DS, TC>, A, A::Object, IA, Ctx::Context<'a>, DS::TransactionId>
+            + AuthzEngine<ResolvedSubject<'a, Ctx, AE, DS, TC>, B, B::Object, IB, Ctx::Context<'a>, DS::TransactionId>
+            + Sync,
+        DS: DataSource + Send + Sync,
+        TC: Send + Sync + TransactionCache + TransactionCacheAction<A, DS, IA> + TransactionCacheAction<B, DS, IB>,
+        A: StorageAction<DS, IA>,
+        B: StorageAction<DS, IB>,
+        IA: Send + Sync,
+        IB: Send + Sync,
+        F: FnOnce(<A as StorageAction<DS, IA>>::Ok) -> IB + Send,
+    {
+        if ctx.data_source().transaction_id().is_none() {
+            return Err(SequenceError::TransactionRequired);
+        }
+
+        let first = Event {
+            subject: ctx.subject(),
+            action: PhantomData::<A>,
+            object: PhantomData::<A::Object>,
+            input,
+            context: ctx.context(),
+            tenant: ctx.tenant(),
+        };
+        let subject = first.subject.clone();
+        let context = first.context.clone();
+        let tenant = first.tenant.clone();
+        let ok = first
+            .try_act(
+                ctx.authz_engine(),
+                ctx.data_source(),
+                ctx.transaction_cache(),
+                ctx.decision_log(),
+                ctx.subject_resolver(),
+            )
+            .await
+            .map_err(SequenceError::First)?;
+
+        let second = Event {
+            subject,
+            action: PhantomData::<B>,
+            object: PhantomData::<B::Object>,
+            input: then(ok),
+            context,
+            tenant,
+        };
+        second
+            .try_act(
+                ctx.authz_engine(),
+                ctx.data_source(),
+                ctx.transaction_cache(),
+                ctx.decision_log(),
+                ctx.subject_resolver(),
+            )
+            .await
+            .map_err(SequenceError::Second)
+    }
+}
+
+/// Performs a [`Sequence`] of two actions within a new transaction of a context which implements
+/// [`TransactionalDataSource`](authzen_data_sources::TransactionalDataSource), rolling back the
+/// first action if the second action is denied or fails.
+/// ```rs
+/// let deleted_cart_items: Result<_, Error> = sequence!(
+///     &ctx,
+///     Create<CartItem> => Delete<CartItem>,
+///     [db_cart_item],
+///     |cart_items: Vec<DbCartItem>| cart_items.into_iter().map(|cart_item| cart_item.id).collect::<Vec<_>>(),
+/// );
+/// ```
+/// The error type of the returned result must be convertible from [`SequenceError`] as well as
+/// from the errors returned from opening the transaction, and must be inferrable where the macro is used.
+#[macro_export]
+macro_rules! sequence {
+    ($ctx:expr, $first:ty => $second:ty, $input:expr, $then:expr $(,)?) => {{
+        use authzen::data_sources::proc_macros_core::reexports::scoped_futures::ScopedFutureExt;
+        let input = $input;
+        let then = $then;
+        authzen::data_sources::TransactionalDataSource::tx($ctx, move |ctx| {
+            async move { Ok(authzen::Sequence::<$first, $second>::try_act(&ctx, input, then).await?) }.scope_boxed()
+        })
+        .await
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::actions::{Create, Delete};
+    use ::std::sync::{Arc, Mutex};
+
+    struct Item;
+
+    impl ObjectType for Item {
+        const SERVICE: &'static str = "test";
+        const TYPE: &'static str = "item";
+    }
+
+    #[derive(Clone, Debug, Default)]
+    struct Store {
+        items: Arc<Mutex<Vec<u32>>>,
+        transaction_id: Option<u64>,
+    }
+
+    impl DataSource for Store {
+        type Backend = ();
+        type Error = ();
+        type TransactionId = u64;
+
+        fn transaction_id(&self) -> Option<u64> {
+            self.transaction_id
+        }
+    }
+
+    #[derive(Debug)]
+    struct NotFound;
+
+    impl StorageError for NotFound {
+        fn not_found() -> Self {
+            Self
+        }
+    }
+
+    #[async_trait]
+    impl StorageAction<Store, Vec<u32>> for Create<Item> {
+        type Ok = Vec<u32>;
+        type Error = NotFound;
+
+        async fn act(client: &Store, input: Vec<u32>) -> Result<Self::Ok, Self::Error> {
+            client.items.lock().unwrap().extend(input.iter().copied());
+            Ok(input)
+        }
+    }
+
+    #[async_trait]
+    impl StorageAction<Store, Vec<u32>> for Delete<Item> {
+        type Ok = Vec<u32>;
+        type Error = NotFound;
+
+        async fn act(client: &Store, input: Vec<u32>) -> Result<Self::Ok, Self::Error> {
+            client.items.lock().unwrap().retain(|id| !input.contains(id));
+            Ok(input)
+        }
+    }
+
+    /// Allows every event except deletions of the id `0`.
+    struct Engine;
+
+    #[async_trait]
+    impl<A: ActionType + Send + Sync> AuthzEngine<u32, A, Item, Vec<u32>, (), u64> for Engine {
+        type Ok = ();
+        type Error = &'static str;
+
+        async fn can_act(
+            &self,
+            _: u32,
+            input: &Vec<u32>,
+            _: (),
+            _: Option<Tenant>,
+            _: Option<u64>,
+        ) -> Result<(), &'static str> {
+            match A::TYPE == "delete" && input.contains(&0) {
+                true => Err("denied"),
+                false => Ok(()),
+            }
+        }
+    }
+
+    struct Ctx {
+        store: Store,
+    }
+
+    impl AuthorizationContext<Engine, Store, ()> for Ctx {
+        type Context<'a> = ();
+        type Subject<'a> = u32;
+        type SubjectResolver = ();
+
+        fn context(&self) -> Self::Context<'_> {}
+        fn subject(&self) -> Self::Subject<'_> {
+            1
+        }
+        fn authz_engine(&self) -> &Engine {
+            &Engine
+        }
+        fn data_source(&self) -> &Store {
+            &self.store
+        }
+        fn transaction_cache(&self) -> &() {
+            &()
+        }
+        fn subject_resolver(&self) -> &Self::SubjectResolver {
+            &()
+        }
+    }
+
+    #[test]
+    fn authorizes_each_step() {
+        let try_act = |ctx: &Ctx, input: Vec<u32>| {
+            futures::executor::block_on(Sequence::<Create<Item>, Delete<Item>>::try_act(ctx, input, |ids| {
+                ids.into_iter().filter(|id| id % 2 == 0).collect()
+            }))
+        };
+
+        let ctx = Ctx {
+            store: Store::default(),
+        };
+        assert!(try_act(&ctx, vec![1]).unwrap_err().is_transaction_required());
+        assert!(ctx.store.items.lock().unwrap().is_empty());
+
+        let ctx = Ctx {
+            store: Store {
+                transaction_id: Some(1),
+                ..Default::default()
+            },
+        };
+        assert_eq!(try_act(&ctx, vec![1, 2, 3]).unwrap(), vec![2]);
+        assert_eq!(*ctx.store.items.lock().unwrap(), vec![1, 3]);
+
+        assert!(matches!(
+            try_act(&ctx, vec![0, 4]),
+            Err(SequenceError::Second(ActionError::Authz("denied")))
+        ));
+        assert_eq!(*ctx.store.items.lock().unwrap(), vec![1, 3, 0, 4]);
+    }
+}
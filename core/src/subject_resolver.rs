@@ -0,0 +1,151 @@
+use ::derivative::Derivative;
+use ::std::borrow::Borrow;
+use ::std::collections::HashMap;
+use ::std::hash::Hash;
+use ::std::sync::{Arc, Mutex};
+
+/// Error returned from a [`SubjectResolver`] which was unable to resolve a subject.
+#[derive(Clone, Debug, thiserror::Error)]
+#[error("unable to resolve authorization subject: {0}")]
+pub struct SubjectResolverError(pub Arc<dyn std::error::Error + Send + Sync>);
+
+impl SubjectResolverError {
+    pub fn new(err: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self(Arc::new(err))
+    }
+}
+
+/// Enriches the subject of an event before it is passed to an authorization engine when attempting
+/// or querying an action with the `try_*` and `can_*` methods, e.g. loading the roles, group memberships
+/// or tenant of an account from the data source so that policies do not need to query for them.
+///
+/// If a subject cannot be resolved, the action is not performed and
+/// [`ActionError::SubjectResolver`](crate::ActionError::SubjectResolver) is returned instead.
+#[async_trait]
+pub trait SubjectResolver<Subject, DS: ?Sized>: Send + Sync {
+    /// The subject passed to the authorization engine.
+    type Subject: Send + Sync;
+
+    async fn resolve(&self, subject: Subject, data_source: &DS) -> Result<Self::Subject, SubjectResolverError>
+    where
+        Subject: 'async_trait;
+}
+
+/// Passes subjects to the authorization engine as is.
+#[async_trait]
+impl<Subject: Send + Sync, DS: ?Sized + Sync> SubjectResolver<Subject, DS> for () {
+    type Subject = Subject;
+
+    async fn resolve(&self, subject: Subject, _: &DS) -> Result<Self::Subject, SubjectResolverError>
+    where
+        Subject: 'async_trait,
+    {
+        Ok(subject)
+    }
+}
+
+#[async_trait]
+impl<Subject, DS, T> SubjectResolver<Subject, DS> for &T
+where
+    Subject: Send,
+    DS: ?Sized + Sync,
+    T: SubjectResolver<Subject, DS>,
+{
+    type Subject = T::Subject;
+
+    async fn resolve(&self, subject: Subject, data_source: &DS) -> Result<Self::Subject, SubjectResolverError>
+    where
+        Subject: 'async_trait,
+    {
+        T::resolve(self, subject, data_source).await
+    }
+}
+
+/// Memoizes the subjects resolved by a [`SubjectResolver`] so that each subject is only resolved once,
+/// regardless of how many actions are attempted with it.
+///
+/// Resolved subjects are never invalidated, so a memoized resolver should be constructed per request
+/// (typically as a field of a request's [`AuthorizationContext`](crate::AuthorizationContext) marked with
+/// `#[subject_resolver]`) rather than shared across requests. Subjects are memoized by their borrowed
+/// form `Key`, e.g. subjects of type `&Uuid` can be memoized by `Uuid`.
+#[derive(Derivative)]
+#[derivative(Debug)]
+pub struct MemoizedSubjectResolver<R, Key, Resolved> {
+    resolver: R,
+    #[derivative(Debug = "ignore")]
+    resolved: Mutex<HashMap<Key, Resolved>>,
+}
+
+impl<R, Key, Resolved> MemoizedSubjectResolver<R, Key, Resolved> {
+    pub fn new(resolver: R) -> Self {
+        Self {
+            resolver,
+            resolved: Default::default(),
+        }
+    }
+
+    pub fn resolver(&self) -> &R {
+        &self.resolver
+    }
+}
+
+#[async_trait]
+impl<Subject, DS, R, Key, Resolved> SubjectResolver<Subject, DS> for MemoizedSubjectResolver<R, Key, Resolved>
+where
+    Subject: Borrow<Key> + Send,
+    DS: ?Sized + Sync,
+    R: SubjectResolver<Subject, DS, Subject = Resolved>,
+    Key: Clone + Eq + Hash + Send,
+    Resolved: Clone + Send + Sync,
+{
+    type Subject = Resolved;
+
+    async fn resolve(&self, subject: Subject, data_source: &DS) -> Result<Self::Subject, SubjectResolverError>
+    where
+        Subject: 'async_trait,
+    {
+        let key = subject.borrow().clone();
+        if let Some(resolved) = self.resolved.lock().unwrap_or_else(|err| err.into_inner()).get(&key) {
+            return Ok(resolved.clone());
+        }
+        let resolved = self.resolver.resolve(subject, data_source).await?;
+        self.resolved
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(key, resolved.clone());
+        Ok(resolved)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ::std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Resolves account ids into their roles, counting how many times it is called.
+    #[derive(Default)]
+    struct Roles(AtomicUsize);
+
+    #[async_trait]
+    impl<'a> SubjectResolver<&'a u32, ()> for Roles {
+        type Subject = (u32, Vec<&'static str>);
+
+        async fn resolve(&self, subject: &'a u32, _: &()) -> Result<Self::Subject, SubjectResolverError>
+        where
+            &'a u32: 'async_trait,
+        {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok((*subject, if *subject == 0 { vec!["admin"] } else { vec![] }))
+        }
+    }
+
+    #[test]
+    fn memoizes_resolved_subjects() {
+        let resolver = MemoizedSubjectResolver::<_, u32, _>::new(Roles::default());
+        let resolve = |subject: u32| futures::executor::block_on(resolver.resolve(&subject, &())).unwrap();
+        assert_eq!(resolve(0), (0, vec!["admin"]));
+        assert_eq!(resolve(1), (1, vec![]));
+        assert_eq!(resolve(0), (0, vec!["admin"]));
+        assert_eq!(resolver.resolver().0.load(Ordering::Relaxed), 2);
+    }
+}
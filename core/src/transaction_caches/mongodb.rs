@@ -16,6 +16,9 @@ pub struct TxEntityFull {
     pub transaction_id: Bson,
     pub service_name: &'static str,
     pub object_type: &'static str,
+    /// tenant of the event which performed the action, so that entities cached for one tenant
+    /// are never read on behalf of another
+    pub tenant: Option<Tenant>,
     pub edited_at: Bson,
     #[serde(flatten)]
     pub entity: Bson,
@@ -29,7 +32,11 @@ struct Group<T, Id> {
 pub type MongodbTxCollection = mongodb::Collection<TxEntityFull>;
 
 impl TxEntityFull {
-    fn try_from<TransactionId, O, T>(transaction_id: TransactionId, entity: &T) -> Result<Self, bson::ser::Error>
+    fn try_from<TransactionId, O, T>(
+        transaction_id: TransactionId,
+        tenant: Option<Tenant>,
+        entity: &T,
+    ) -> Result<Self, bson::ser::Error>
     where
         TransactionId: Serialize,
         O: ?Sized + ObjectType,
@@ -39,6 +46,7 @@ impl TxEntityFull {
             transaction_id: bson::to_bson(&transaction_id)?,
             service_name: O::SERVICE,
             object_type: O::TYPE,
+            tenant,
             edited_at: Bson::DateTime(bson::DateTime::from(Utc::now())),
             entity: bson::to_bson(&TxCacheEntity {
                 exists: true,
@@ -51,6 +59,7 @@ impl TxEntityFull {
 
 fn group_pipeline<'a, TransactionId, O, T>(
     transaction_id: TransactionId,
+    tenant: Option<Tenant>,
     ids: impl Into<Option<&'a [T::Id]>>,
 ) -> Result<[Document; 3], bson::ser::Error>
 where
@@ -58,27 +67,21 @@ where
     O: ?Sized + ObjectType,
     T: Identifiable,
 {
-    let match_document = match ids.into() {
-        Some(ids) => doc! {
-            "$match": {
-                "transaction_id": bson::to_bson(&transaction_id)?,
-                "service_name": O::SERVICE,
-                "object_type": O::TYPE,
-                "id": {
-                    "$in": bson::to_bson(ids)?,
-                },
-            },
-        },
-        None => doc! {
-            "$match": {
-                "transaction_id": bson::to_bson(&transaction_id)?,
-                "service_name": O::SERVICE,
-                "object_type": O::TYPE,
-            },
-        },
+    let mut filter = doc! {
+        "transaction_id": bson::to_bson(&transaction_id)?,
+        "service_name": O::SERVICE,
+        "object_type": O::TYPE,
     };
+    if let Some(ids) = ids.into() {
+        filter.insert("id", doc! { "$in": bson::to_bson(ids)? });
+    }
+    // entities are only read on behalf of the tenant they were cached for; reads made without a
+    // tenant (e.g. by a policy information point) are scoped by transaction id alone
+    if let Some(tenant) = tenant {
+        filter.insert("tenant", tenant.0);
+    }
     Ok([
-        match_document,
+        doc! { "$match": filter },
         doc! {
             "$sort": {
                 "edited_at": -1,
@@ -143,6 +146,7 @@ impl TransactionCache for MongodbTxCollection {
     fn get_entities<'life0, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
     ) -> BoxFuture<'async_trait, Result<HashMap<T::Id, TxCacheEntity<T, T::Id>>, Self::Error>>
     where
         'life0: 'async_trait,
@@ -156,7 +160,7 @@ impl TransactionCache for MongodbTxCollection {
         Box::pin(async move {
             instrument_field!("service_name", O::SERVICE);
             instrument_field!("object_type", O::TYPE);
-            let pipeline = group_pipeline::<_, O, T>(transaction_id, None).map_err(Error::default_details)?;
+            let pipeline = group_pipeline::<_, O, T>(transaction_id, tenant, None).map_err(Error::default_details)?;
             let mut cursor = self.aggregate(pipeline, None).await.map_err(Error::default_details)?;
 
             let mut entities = Vec::<TxCacheEntity<T, T::Id>>::default();
@@ -172,6 +176,7 @@ impl TransactionCache for MongodbTxCollection {
     fn get_by_ids<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         ids: &'life1 [T::Id],
     ) -> BoxFuture<'async_trait, Result<Vec<T>, Self::Error>>
     where
@@ -187,7 +192,7 @@ impl TransactionCache for MongodbTxCollection {
         Box::pin(async move {
             instrument_field!("service_name", O::SERVICE);
             instrument_field!("object_type", O::TYPE);
-            let pipeline = group_pipeline::<_, O, T>(transaction_id, ids).map_err(Error::default_details)?;
+            let pipeline = group_pipeline::<_, O, T>(transaction_id, tenant, ids).map_err(Error::default_details)?;
             let mut cursor = self.aggregate(pipeline, None).await.map_err(Error::default_details)?;
 
             let mut entities = Vec::<TxCacheEntity<T, T::Id>>::default();
@@ -203,6 +208,7 @@ impl TransactionCache for MongodbTxCollection {
     fn upsert<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         entities: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> BoxFuture<'async_trait, Result<(), Self::Error>>
     where
@@ -219,7 +225,9 @@ impl TransactionCache for MongodbTxCollection {
             instrument_field!("object_type", O::TYPE);
             let entity_fulls = entities
                 .into_iter()
-                .map(|entity| TxEntityFull::try_from::<_, O, T>(transaction_id.clone(), entity.borrow()))
+                .map(|entity| {
+                    TxEntityFull::try_from::<_, O, T>(transaction_id.clone(), tenant.clone(), entity.borrow())
+                })
                 .collect::<Result<Vec<TxEntityFull>, _>>()
                 .map_err(Error::default_details)?;
             self.insert_many(entity_fulls, None)
@@ -232,6 +240,7 @@ impl TransactionCache for MongodbTxCollection {
     fn mark_deleted<'life0, 'life1, 'async_trait, O, T, TransactionId>(
         &'life0 self,
         transaction_id: TransactionId,
+        tenant: Option<Tenant>,
         entities: impl IntoIterator<Item = impl Borrow<T> + Send> + Send + 'life1,
     ) -> BoxFuture<'async_trait, Result<(), Self::Error>>
     where
@@ -248,7 +257,9 @@ impl TransactionCache for MongodbTxCollection {
             instrument_field!("object_type", O::TYPE);
             let entity_fulls = entities
                 .into_iter()
-                .map(|entity| TxEntityFull::try_from::<_, O, T>(transaction_id.clone(), entity.borrow()))
+                .map(|entity| {
+                    TxEntityFull::try_from::<_, O, T>(transaction_id.clone(), tenant.clone(), entity.borrow())
+                })
                 .collect::<Result<Vec<TxEntityFull>, _>>()
                 .map_err(Error::default_details)?;
             self.insert_many(entity_fulls, None)
@@ -270,6 +281,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
         ok: &'life1 <Create<O> as StorageAction<DS, I>>::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -279,7 +291,7 @@ where
         'life0: 'async_trait,
         'life1: 'async_trait,
     {
-        <Self as TransactionCache>::upsert::<O, T, DS::TransactionId>(self, transaction_id, ok)
+        <Self as TransactionCache>::upsert::<O, T, DS::TransactionId>(self, transaction_id, tenant, ok)
     }
 }
 
@@ -294,6 +306,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
         ok: &'life1 <Delete<O> as StorageAction<DS, I>>::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -303,7 +316,55 @@ where
         'life0: 'async_trait,
         'life1: 'async_trait,
     {
-        <Self as TransactionCache>::mark_deleted::<O, T, DS::TransactionId>(self, transaction_id, ok)
+        <Self as TransactionCache>::mark_deleted::<O, T, DS::TransactionId>(self, transaction_id, tenant, ok)
+    }
+}
+
+impl<O, DS, I> TransactionCacheAction<List<O>, DS, I> for MongodbTxCollection
+where
+    O: ?Sized + ObjectType,
+    DS: ?Sized + DataSource + Send + Sync,
+    List<O>: StorageAction<DS, I> + Send,
+{
+    fn manage_cache<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        _: DS::TransactionId,
+        _: Option<Tenant>,
+        _: &'life1 <List<O> as StorageAction<DS, I>>::Ok,
+    ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
+    where
+        Self: Sync,
+        Self: 'async_trait,
+        DS::TransactionId: 'async_trait,
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+    {
+        Box::pin(async { Ok(()) })
+    }
+}
+
+impl<O, DS, I, T> TransactionCacheAction<Purge<O>, DS, I> for MongodbTxCollection
+where
+    O: ?Sized + ObjectType,
+    DS: ?Sized + DataSource + Send + Sync,
+    Purge<O>: StorageAction<DS, I> + Send,
+    for<'a> &'a <Purge<O> as StorageAction<DS, I>>::Ok: IntoIterator<Item = &'a T>,
+    T: Identifiable + Serialize + Sync + 'static,
+{
+    fn manage_cache<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
+        ok: &'life1 <Purge<O> as StorageAction<DS, I>>::Ok,
+    ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
+    where
+        Self: Sync,
+        Self: 'async_trait,
+        DS::TransactionId: 'async_trait,
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+    {
+        <Self as TransactionCache>::mark_deleted::<O, T, DS::TransactionId>(self, transaction_id, tenant, ok)
     }
 }
 
@@ -316,6 +377,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         _: DS::TransactionId,
+        _: Option<Tenant>,
         _: &'life1 <Read<O> as StorageAction<DS, I>>::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -329,6 +391,31 @@ where
     }
 }
 
+impl<O, DS, I, T> TransactionCacheAction<Restore<O>, DS, I> for MongodbTxCollection
+where
+    O: ?Sized + ObjectType,
+    DS: ?Sized + DataSource + Send + Sync,
+    Restore<O>: StorageAction<DS, I> + Send,
+    for<'a> &'a <Restore<O> as StorageAction<DS, I>>::Ok: IntoIterator<Item = &'a T>,
+    T: Identifiable + Serialize + Sync + 'static,
+{
+    fn manage_cache<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
+        ok: &'life1 <Restore<O> as StorageAction<DS, I>>::Ok,
+    ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
+    where
+        Self: Sync,
+        Self: 'async_trait,
+        DS::TransactionId: 'async_trait,
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+    {
+        <Self as TransactionCache>::upsert::<O, T, DS::TransactionId>(self, transaction_id, tenant, ok)
+    }
+}
+
 impl<O, DS, I, T> TransactionCacheAction<Update<O>, DS, I> for MongodbTxCollection
 where
     O: ?Sized + ObjectType,
@@ -340,6 +427,7 @@ where
     fn manage_cache<'life0, 'life1, 'async_trait>(
         &'life0 self,
         transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
         ok: &'life1 <Update<O> as StorageAction<DS, I>>::Ok,
     ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
     where
@@ -349,7 +437,32 @@ where
         'life0: 'async_trait,
         'life1: 'async_trait,
     {
-        <Self as TransactionCache>::upsert::<O, T, DS::TransactionId>(self, transaction_id, ok)
+        <Self as TransactionCache>::upsert::<O, T, DS::TransactionId>(self, transaction_id, tenant, ok)
+    }
+}
+
+impl<O, DS, I, T> TransactionCacheAction<Upsert<O>, DS, I> for MongodbTxCollection
+where
+    O: ?Sized + ObjectType,
+    DS: ?Sized + DataSource + Send + Sync,
+    Upsert<O>: StorageAction<DS, I> + Send,
+    for<'a> &'a <Upsert<O> as StorageAction<DS, I>>::Ok: IntoIterator<Item = &'a T>,
+    T: Identifiable + Serialize + Sync + 'static,
+{
+    fn manage_cache<'life0, 'life1, 'async_trait>(
+        &'life0 self,
+        transaction_id: DS::TransactionId,
+        tenant: Option<Tenant>,
+        ok: &'life1 <Upsert<O> as StorageAction<DS, I>>::Ok,
+    ) -> Pin<Box<dyn Future<Output = Result<(), <Self as TransactionCache>::Error>> + Send + 'async_trait>>
+    where
+        Self: Sync,
+        Self: 'async_trait,
+        DS::TransactionId: 'async_trait,
+        'life0: 'async_trait,
+        'life1: 'async_trait,
+    {
+        <Self as TransactionCache>::upsert::<O, T, DS::TransactionId>(self, transaction_id, tenant, ok)
     }
 }
 
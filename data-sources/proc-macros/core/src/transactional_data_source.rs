@@ -236,6 +236,13 @@ pub fn derive_transactional_data_source(item: TokenStream) -> Result<TokenStream
                 self.#data_source_field_accessor.tx_cleanup(f).await
             }
 
+            async fn tx_commit<F>(&self, f: F)
+            where
+                F: ::authzen::data_sources::TxCommitFn
+            {
+                self.#data_source_field_accessor.tx_commit(f).await
+            }
+
             async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
             where
                 F: for<'r> ::authzen::data_sources::TxFn<'a, Self::TxConnection<'r>, ::authzen::data_sources::proc_macros_core::reexports::scoped_futures::ScopedBoxFuture<'a, 'r, Result<T, E>>>,
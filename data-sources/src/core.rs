@@ -21,6 +21,8 @@ pub struct DataSourceConnection<AC, C, TransactionId> {
     pub(crate) connection: Arc<RwLock<C>>,
     #[derivative(Debug = "ignore")]
     pub(crate) tx_cleanup: TxCleanup<AC, TransactionId>,
+    #[derivative(Debug = "ignore")]
+    pub(crate) tx_commit: TxCommit,
     pub(crate) tx_id: Option<TransactionId>,
 }
 
@@ -29,11 +31,25 @@ impl<AC, C, TransactionId: Clone> Clone for DataSourceConnection<AC, C, Transact
         Self {
             connection: self.connection.clone(),
             tx_cleanup: self.tx_cleanup.clone(),
+            tx_commit: self.tx_commit.clone(),
             tx_id: self.tx_id.clone(),
         }
     }
 }
 
+impl<AC, C, TransactionId> DataSourceConnection<AC, C, TransactionId> {
+    /// Wraps a connection with no pending cleanup functions, e.g. so that implementors of
+    /// [`TransactionalDataSource`] outside of this crate can run the cleanup functions of their transactions.
+    pub fn new(connection: C, tx_id: Option<TransactionId>) -> Self {
+        Self {
+            connection: Arc::new(RwLock::new(connection)),
+            tx_cleanup: Default::default(),
+            tx_commit: Default::default(),
+            tx_id,
+        }
+    }
+}
+
 pub type DataSourceConnRef<'a, C, TransactionId> = DataSourceConnection<C, &'a mut C, TransactionId>;
 
 #[derive(Derivative, thiserror::Error)]
@@ -85,6 +101,10 @@ pub trait TxCleanupFn<'r, AC: 'r, E, TransactionId: 'r> =
 pub type TxCleanup<AC, TransactionId> =
     Arc<Mutex<Vec<Box<dyn for<'r> TxCleanupFn<'r, AC, TxCleanupError, TransactionId>>>>>;
 
+pub trait TxCommitFn = FnOnce() + Send + Sync + 'static;
+
+pub type TxCommit = Arc<::std::sync::Mutex<Vec<Box<dyn TxCommitFn>>>>;
+
 impl<AC, C, TransactionId: Clone> From<DataSourceConnection<AC, C, TransactionId>>
     for Cow<'_, DataSourceConnection<AC, C, TransactionId>>
 {
@@ -124,7 +144,11 @@ pub trait TransactionalDataSource: DataSource + Sized {
     type Connection<'r>: Deref<Target = Self::AsyncConnection> + Send + Sync
     where
         Self: 'r;
-    type TxConnection<'r>: TransactionalDataSource<Backend = Self::Backend, AsyncConnection = Self::AsyncConnection>;
+    type TxConnection<'r>: TransactionalDataSource<
+        Backend = Self::Backend,
+        AsyncConnection = Self::AsyncConnection,
+        TransactionId = Self::TransactionId,
+    >;
 
     async fn query<'a, F, T, E>(&self, f: F) -> Result<T, E>
     where
@@ -143,6 +167,13 @@ pub trait TransactionalDataSource: DataSource + Sized {
         F: for<'r> TxCleanupFn<'r, Self::AsyncConnection, E, Self::TransactionId>,
         E: Into<TxCleanupError> + 'static;
 
+    /// Registers `f` to run once the current transaction has been committed, after its cleanup functions
+    /// (see [`TransactionalDataSource::tx_cleanup`]) have run. Functions registered within a transaction which
+    /// is rolled back are dropped without being run, while functions registered outside of a transaction run immediately.
+    async fn tx_commit<F>(&self, f: F)
+    where
+        F: TxCommitFn;
+
     async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
     where
         F: for<'r> TxFn<'a, Self::TxConnection<'r>, ScopedBoxFuture<'a, 'r, Result<T, E>>>,
@@ -213,6 +244,13 @@ impl<'d, D: TransactionalDataSource + Clone> TransactionalDataSource for &'d D {
         (**self).tx_cleanup(f).await
     }
 
+    async fn tx_commit<F>(&self, f: F)
+    where
+        F: TxCommitFn,
+    {
+        (**self).tx_commit(f).await
+    }
+
     async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
     where
         F: for<'r> TxFn<'a, Self::TxConnection<'r>, ScopedBoxFuture<'a, 'r, Result<T, E>>>,
@@ -257,6 +295,13 @@ impl<'d, D: TransactionalDataSource + Clone> TransactionalDataSource for Cow<'d,
         (**self).tx_cleanup(f).await
     }
 
+    async fn tx_commit<F>(&self, f: F)
+    where
+        F: TxCommitFn,
+    {
+        (**self).tx_commit(f).await
+    }
+
     async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
     where
         F: for<'r> TxFn<'a, Self::TxConnection<'r>, ScopedBoxFuture<'a, 'r, Result<T, E>>>,
@@ -181,6 +181,47 @@ pub mod operations {
             }
         }
 
+        /// Returns [`Error::NotFound`] if any of the rows with the provided ids, including soft deleted rows,
+        /// belongs to a tenant other than `tenant`, so that actions which address rows by id cannot reach
+        /// the rows of other tenants. Only tables scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]) are
+        /// checked, and a tenant must be provided for them.
+        #[framed]
+        #[instrument(skip_all)]
+        async fn check_tenant<'query, D, F, G>(
+            db: &D,
+            ids: Vec<Self::Id>,
+            tenant: Option<&crate::Tenant>,
+        ) -> Result<(), DbEntityError<<Self::Raw as TryInto<Self>>::Error>>
+        where
+            D: Db,
+            D::Backend: ::diesel::backend::Backend + 'static,
+            D::AsyncConnection: ::diesel_async::AsyncConnection<Backend = D::Backend>,
+
+            // Id bounds
+            Self::Id: Debug + Send,
+            <Self::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
+            <<Self::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+
+            // Query bounds
+            Self::Table: FilterColumns<D::Backend> + 'static,
+            Self::Table: FilterDsl<BoxedFilter<'static, Self::Table, D::Backend>, Output = F>,
+            F: FilterDsl<ht::EqAny<<Self::Table as Table>::PrimaryKey, Vec<Self::Id>>, Output = G>,
+            G: LoadQuery<'query, D::AsyncConnection, Self::Raw> + Send + 'query,
+        {
+            let Some(filter) = other_tenants::<Self::Table, D::Backend>(tenant).map_err(Error::from)? else {
+                return Ok(());
+            };
+            let result: Result<Vec<Self::Raw>, _> = db.get_filtered(ids, filter).await;
+            match result {
+                Ok(records) if records.is_empty() => Ok(()),
+                Ok(_) => Err(Error::NotFound.into()),
+                Err(err) => {
+                    error!(target: module_path!(), error = %err);
+                    Err(err.into())
+                }
+            }
+        }
+
         #[framed]
         #[instrument(skip_all)]
         async fn get_by_column<'query, D, C, U, F>(
@@ -253,6 +294,65 @@ pub mod operations {
             }
         }
 
+        /// Fetches a page of the rows which satisfy `filter`, typically produced by partially evaluating
+        /// an authorization policy (see [`AuthzFilter`](crate::AuthzFilter)). Because the filter is applied
+        /// before pagination, every page is full of authorized rows and page boundaries remain stable.
+        ///
+        /// If the table is scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]), only rows of `tenant`
+        /// are fetched and a tenant must be provided.
+        #[framed]
+        #[instrument(skip_all)]
+        async fn get_page_filtered<'query, D, P, F, G>(
+            db: &D,
+            page: P,
+            filter: &crate::AuthzFilter,
+            tenant: Option<&crate::Tenant>,
+        ) -> Result<Vec<Self>, DbEntityError<<Self::Raw as TryInto<Self>>::Error>>
+        where
+            D: Db,
+            D::Backend: ::diesel::backend::Backend + 'static,
+            D::AsyncConnection: ::diesel_async::AsyncConnection<Backend = D::Backend>,
+
+            // Page bounds
+            P: Borrow<Page> + Debug + Send,
+
+            // Filter bounds
+            Self::Table: FilterColumns<D::Backend> + 'static,
+            bool: ::diesel::serialize::ToSql<::diesel::sql_types::Bool, D::Backend>,
+            ht::And<BoxedFilter<'static, Self::Table, D::Backend>, BoxedFilter<'static, Self::Table, D::Backend>>:
+                ::diesel::expression::BoxableExpression<Self::Table, D::Backend, SqlType = ::diesel::sql_types::Bool>,
+            ht::Or<BoxedFilter<'static, Self::Table, D::Backend>, BoxedFilter<'static, Self::Table, D::Backend>>:
+                ::diesel::expression::BoxableExpression<Self::Table, D::Backend, SqlType = ::diesel::sql_types::Bool>,
+            ht::AsExprOf<bool, ::diesel::sql_types::Bool>:
+                ::diesel::expression::BoxableExpression<Self::Table, D::Backend, SqlType = ::diesel::sql_types::Bool>,
+
+            // Query bounds
+            Self::Table: FilterDsl<BoxedFilter<'static, Self::Table, D::Backend>, Output = F>,
+            F: IsNotDeleted<'query, D::AsyncConnection, Self::Raw, Self::Raw, IsNotDeletedFilter = G>,
+            G: Paginate + Send,
+            <G as AsQuery>::Query: 'query,
+            Paginated<<G as AsQuery>::Query>: Send + LoadQuery<'query, D::AsyncConnection, Self::Raw>,
+        {
+            if page.borrow().is_empty() || filter.is_deny_all() {
+                return Ok(vec![]);
+            }
+            let filter = filter
+                .to_diesel::<Self::Table, D::Backend>(tenant)
+                .map_err(Error::from)?;
+            let result: Result<Vec<Self::Raw>, _> = db.get_page_filtered(page, filter).await;
+            match result {
+                Ok(records) => Ok(records
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()
+                    .map_err(DbEntityError::conversion)?),
+                Err(err) => {
+                    error!(target: module_path!(), error = %err);
+                    Err(err.into())
+                }
+            }
+        }
+
         #[framed]
         #[instrument(skip_all)]
         async fn get_pages<'query, D, P, F>(
@@ -650,6 +750,206 @@ pub mod operations {
         }
     }
 
+    /// Inserts new records and updates existing ones in a single operation, where a record already
+    /// exists if a row with its primary key is present in the table. Existing records are updated with
+    /// the [`Patch`](DbUpdate::Patch) their [`Post`](DbInsert::Post) converts into, unless they have
+    /// been soft deleted in which case the upsert fails (see [`Db::upsert`]).
+    #[async_trait]
+    pub trait DbUpsert: DbInsert + DbUpdate {
+        #[framed]
+        #[instrument(skip_all)]
+        async fn upsert<'query, 'v, D, F, G>(
+            db: &D,
+            posts: impl IntoIterator<Item = Self::PostHelper<'v>> + Send + 'v,
+        ) -> Result<Vec<Self>, DbEntityError<<Self::Raw as TryInto<Self>>::Error>>
+        where
+            D: Db + 'query,
+            D::Backend: ::diesel::backend::Backend + 'static,
+            D::AsyncConnection: ::diesel_async::AsyncConnection<Backend = D::Backend>,
+            'v: 'query,
+
+            <Self::Raw as TryInto<Self>>::Error: Send,
+
+            // Id bounds
+            Self::Id: Clone + Hash + Eq + Send + Sync,
+            for<'a> &'a Self::Raw: Identifiable<Id = &'a Self::Id>,
+            for<'a> &'a Self::Post<'v>: Identifiable<Id = &'a Self::Id>,
+            <Self::Table as Table>::PrimaryKey: ::diesel::Column + ExpressionMethods,
+            <<Self::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+
+            // Insertable bounds
+            Self::Post<'v>: Insertable<Self::Table> + Clone,
+            <Self::Post<'v> as Insertable<Self::Table>>::Values: Send,
+            <Self::Table as QuerySource>::FromClause: Send,
+
+            // Changeset bounds
+            Self::Post<'v>: Into<Self::Patch<'v>>,
+            <Self::Patch<'v> as AsChangeset>::Changeset: Send,
+
+            // Upsert bounds
+            InsertStatement<
+                Self::Table,
+                OnConflictPrimaryKey<
+                    <Self::Post<'v> as Insertable<Self::Table>>::Values,
+                    <Self::Table as Table>::PrimaryKey,
+                    <Self::Patch<'v> as AsChangeset>::Changeset,
+                    BoxedFilter<'static, Self::Table, D::Backend>,
+                >,
+            >: LoadQuery<'query, D::AsyncConnection, Self::Raw>,
+
+            // Guard bounds for existing records
+            Self::Table: FilterColumns<D::Backend> + 'static,
+            Self::Raw: MaybeNotDeleted<Self::Table, D::Backend>,
+            bool: ::diesel::serialize::ToSql<::diesel::sql_types::Bool, D::Backend>,
+            ht::And<BoxedFilter<'static, Self::Table, D::Backend>, BoxedFilter<'static, Self::Table, D::Backend>>:
+                ::diesel::expression::BoxableExpression<Self::Table, D::Backend, SqlType = ::diesel::sql_types::Bool>,
+            ht::AsExprOf<bool, ::diesel::sql_types::Bool>:
+                ::diesel::expression::BoxableExpression<Self::Table, D::Backend, SqlType = ::diesel::sql_types::Bool>,
+
+            // Filter bounds for records which are not returned by the insert statement
+            Self::Table: FilterDsl<BoxedFilter<'static, Self::Table, D::Backend>, Output = F>,
+            F: FilterDsl<ht::EqAny<<Self::Table as Table>::PrimaryKey, Vec<Self::Id>>, Output = G>,
+            G: LoadQuery<'query, D::AsyncConnection, Self::Raw> + Send + 'query,
+
+            // Audit bounds
+            Self::Raw: MaybeAudit<'query, D::AsyncConnection>,
+        {
+            let db_post_helpers = posts.into_iter().collect::<Vec<_>>();
+            tracing::Span::current().record("posts", &*format!("{db_post_helpers:?}"));
+
+            if db_post_helpers.is_empty() {
+                return Ok(vec![]);
+            }
+
+            db.upsert::<_, _, Self::Patch<'v>, _, _, _, _, _>(
+                db_post_helpers.into_iter().map(Self::PostHelper::into),
+                None,
+            )
+            .map(|result| match result {
+                Ok(records) => Ok(records),
+                Err(err) => {
+                    let err = err;
+                    error!(target: module_path!(), error = %err);
+                    Err(err)
+                }
+            })
+            .await
+            .map_err(DbEntityError::from)
+            .and_then(|records| {
+                records
+                    .into_iter()
+                    .map(TryInto::try_into)
+                    .collect::<Result<_, _>>()
+                    .map_err(DbEntityError::conversion)
+            })
+        }
+    }
+
+    /// Restores soft deleted records, only available for entities whose raw representation is [`SoftDeletable`].
+    #[async_trait]
+    pub trait DbRestore: DbEntity {
+        #[framed]
+        #[instrument(skip_all)]
+        async fn restore<'query, D, F>(
+            db: &D,
+            ids: impl IntoIterator<Item = Self::Id> + Send,
+        ) -> Result<Vec<Self>, DbEntityError<<Self::Raw as TryInto<Self>>::Error>>
+        where
+            D: Db + 'query,
+            D::Backend: ::diesel::backend::Backend,
+            D::AsyncConnection: ::diesel_async::AsyncConnection<Backend = D::Backend>,
+
+            <Self::Raw as TryInto<Self>>::Error: Send,
+            Self::Raw: SoftDeletable,
+            <Self::Raw as SoftDeletable>::DeletedAt: ::diesel::Column<Table = Self::Table> + 'query,
+
+            // Id bounds
+            Self::Id: Debug + Send,
+            <Self::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
+            <<Self::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+            <Self::Table as QuerySource>::FromClause: Send,
+
+            // UpdateStatement bounds
+            Self::Table: FilterDsl<ht::EqAny<<Self::Table as Table>::PrimaryKey, Vec<Self::Id>>, Output = F>,
+            F: IntoUpdateTarget + HasTable<Table = Self::Table> + Send + 'query,
+            <F as IntoUpdateTarget>::WhereClause: Send + 'query,
+            ht::Update<F, ht::Eq<<Self::Raw as SoftDeletable>::DeletedAt, Option<::std::time::SystemTime>>>:
+                AsQuery + LoadQuery<'query, D::AsyncConnection, Self::Raw> + Send,
+
+            // Audit bounds
+            Self::Raw: MaybeAudit<'query, D::AsyncConnection>,
+        {
+            let ids = ids.into_iter().collect::<Vec<_>>();
+            tracing::Span::current().record("ids", &*format!("{ids:?}"));
+
+            db.restore(ids)
+                .map(|result| match result {
+                    Ok(records) => Ok(records),
+                    Err(err) => {
+                        let err = err;
+                        error!(target: module_path!(), error = %err);
+                        Err(err)
+                    }
+                })
+                .await
+                .map_err(DbEntityError::from)
+                .and_then(|records| {
+                    records
+                        .into_iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()
+                        .map_err(DbEntityError::conversion)
+                })
+        }
+    }
+
+    /// Permanently deletes records, even those of entities which are [`SoftDeletable`]
+    /// and would otherwise only be soft deleted by [`DbDelete::delete`].
+    #[async_trait]
+    pub trait DbPurge: DbDelete {
+        #[framed]
+        #[instrument(skip_all)]
+        async fn purge<'query, 'v, D, I>(
+            db: &D,
+            ids: I,
+        ) -> Result<Vec<Self>, DbEntityError<<Self::Raw as TryInto<Self>>::Error>>
+        where
+            D: Db + 'query,
+            D::Backend: ::diesel::backend::Backend,
+            D::AsyncConnection: ::diesel_async::AsyncConnection<Backend = D::Backend>,
+
+            I: Send,
+
+            // Id bounds
+            Self::Id: Debug + Send,
+            for<'a> &'a Self::Raw: Identifiable<Id = &'a Self::Id>,
+            <Self::Table as Table>::PrimaryKey: Expression + ExpressionMethods,
+            <<Self::Table as Table>::PrimaryKey as Expression>::SqlType: SqlType,
+
+            Self::Raw:
+                Deletable<'query, D::AsyncConnection, Self::Table, I, Self::Id, Self::DeletedAt, Self::DeletePatch<'v>>,
+        {
+            db.raw_tx(move |conn| async move { Self::Raw::hard_delete(conn, ids).await }.scope_boxed())
+                .map(|result| match result {
+                    Ok(records) => Ok(records),
+                    Err(err) => {
+                        let err = err;
+                        error!(target: module_path!(), error = %err);
+                        Err(err)
+                    }
+                })
+                .await
+                .map_err(DbEntityError::from)
+                .and_then(|records| {
+                    records
+                        .into_iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_, _>>()
+                        .map_err(DbEntityError::conversion)
+                })
+        }
+    }
+
     impl<T: DbEntity> DbGet for T {}
 
     impl<T: DbEntity> DbInsert for T
@@ -670,4 +970,10 @@ pub mod operations {
     {
         type Patch<'v> = T;
     }
+
+    impl<T: DbInsert + DbUpdate> DbUpsert for T {}
+
+    impl<T: DbEntity> DbRestore for T {}
+
+    impl<T: DbDelete> DbPurge for T {}
 }
@@ -1,26 +1,36 @@
-use crate::diesel::{audit::MaybeAudit, is_deleted::IsNotDeleted, macros::IncludesChanges, paginate::*};
-use crate::{DataSource, TransactionalDataSource, TxCleanupError};
+use crate::diesel::{
+    audit::MaybeAudit,
+    deletable::{MaybeNotDeleted, SoftDeletable},
+    filter::{BoxedFilter, FilterColumns},
+    is_deleted::IsNotDeleted,
+    macros::IncludesChanges,
+    paginate::*,
+    upsert::OnConflictPrimaryKey,
+};
+use crate::{Comparison, Condition, DataSource, Tenant, TransactionalDataSource, TxCleanupError};
 use ::async_backtrace::framed;
 use ::async_trait::async_trait;
 use ::cfg_if::cfg_if;
 use ::diesel::associations::HasTable;
 use ::diesel::backend::Backend;
 use ::diesel::dsl::SqlTypeOf;
-use ::diesel::expression::{AsExpression, Expression};
-use ::diesel::expression_methods::ExpressionMethods;
+use ::diesel::expression::{AsExpression, BoxableExpression, Expression, IntoSql};
+use ::diesel::expression_methods::{BoolExpressionMethods, ExpressionMethods};
 use ::diesel::helper_types as ht;
 use ::diesel::query_builder::*;
 use ::diesel::query_dsl::methods::{FilterDsl, FindDsl};
 use ::diesel::query_source::QuerySource;
 use ::diesel::result::Error;
-use ::diesel::sql_types::SqlType;
-use ::diesel::{Identifiable, Insertable, Table};
+use ::diesel::serialize::ToSql;
+use ::diesel::sql_types::{Bool, SqlType};
+use ::diesel::{Column, Identifiable, Insertable, Table};
 use ::diesel_async::{methods::*, AsyncConnection, RunQueryDsl};
 use ::futures::future::{ready, BoxFuture, FutureExt, TryFutureExt};
 use ::log::error;
 use ::scoped_futures::ScopedFutureExt;
 use ::std::borrow::Borrow;
 use ::std::collections::HashMap;
+use ::std::time::SystemTime;
 use ::std::{fmt::Debug, hash::Hash};
 use ::tracing::{instrument, Instrument};
 
@@ -101,6 +111,7 @@ cfg_if! {
                     tx_id: None,
                     connection: Arc::new(RwLock::new(connection)),
                     tx_cleanup: Arc::new(Mutex::new(vec![])),
+                    tx_commit: Default::default(),
                 }
             }
         }
@@ -179,6 +190,42 @@ where
         .boxed()
     }
 
+    /// Fetches the rows with the provided ids which satisfy `filter`, including soft deleted rows.
+    #[framed]
+    #[instrument(skip_all)]
+    fn get_filtered<'life0, 'async_trait, 'query, R, T, Pk, F, G>(
+        &'life0 self,
+        ids: Vec<Pk>,
+        filter: BoxedFilter<'static, T, Self::Backend>,
+    ) -> BoxFuture<'async_trait, Result<Vec<R>, Error>>
+    where
+        Pk: AsExpression<SqlTypeOf<T::PrimaryKey>> + Debug + Send,
+        R: Send + HasTable<Table = T>,
+        T: Table + FilterDsl<BoxedFilter<'static, T, Self::Backend>, Output = F>,
+        T::PrimaryKey: Expression + ExpressionMethods,
+        <T::PrimaryKey as Expression>::SqlType: SqlType,
+        F: FilterDsl<ht::EqAny<<T as Table>::PrimaryKey, Vec<Pk>>, Output = G>,
+        G: LoadQuery<'query, Self::AsyncConnection, R> + Send + 'query,
+
+        'life0: 'async_trait,
+        'query: 'async_trait,
+        R: 'async_trait,
+        T: 'async_trait,
+        Pk: 'async_trait,
+        F: 'async_trait,
+        G: 'async_trait,
+        Self: 'life0,
+    {
+        if ids.is_empty() {
+            return Box::pin(ready(Ok(vec![])));
+        }
+        execute_query!(
+            self,
+            R::table().filter(filter).filter(R::table().primary_key().eq_any(ids)),
+        )
+        .boxed()
+    }
+
     #[framed]
     #[instrument(skip_all)]
     fn get_by_column<'life0, 'async_trait, 'query, R, T, U, C, F>(
@@ -239,6 +286,39 @@ where
         execute_query!(self, R::table().is_not_deleted().paginate(page)).boxed()
     }
 
+    /// Same as [`get_page`](Db::get_page) but only includes rows which satisfy `filter`, allowing
+    /// the rows of a page to be restricted to those which the subject is authorized to act upon.
+    #[framed]
+    #[instrument(skip_all)]
+    fn get_page_filtered<'life0, 'async_trait, 'query, R, P, F, G>(
+        &'life0 self,
+        page: P,
+        filter: BoxedFilter<'static, <R as HasTable>::Table, Self::Backend>,
+    ) -> BoxFuture<'async_trait, Result<Vec<R>, Error>>
+    where
+        P: Borrow<Page> + Debug + Send,
+        R: Send + HasTable,
+        <R as HasTable>::Table:
+            Table + FilterDsl<BoxedFilter<'static, <R as HasTable>::Table, Self::Backend>, Output = F>,
+        F: IsNotDeleted<'query, Self::AsyncConnection, R, R, IsNotDeletedFilter = G>,
+        G: Paginate + Send,
+        <G as AsQuery>::Query: 'query,
+        Paginated<<G as AsQuery>::Query>: LoadQuery<'query, Self::AsyncConnection, R> + Send,
+
+        'life0: 'async_trait,
+        'query: 'async_trait,
+        R: 'async_trait,
+        P: 'async_trait,
+        F: 'async_trait,
+        G: 'async_trait,
+        Self: 'life0,
+    {
+        if page.borrow().is_empty() {
+            return Box::pin(ready(Ok(vec![])));
+        }
+        execute_query!(self, R::table().filter(filter).is_not_deleted().paginate(page)).boxed()
+    }
+
     #[framed]
     #[instrument(skip_all)]
     fn get_pages<'life0, 'async_trait, 'query, R, P, I, F>(
@@ -450,6 +530,191 @@ where
         }))
         .boxed()
     }
+
+    /// Inserts the values whose primary keys are not yet present in the table and updates the
+    /// rows whose primary keys are, using the changeset each value converts into. Values are upserted
+    /// within a single transaction with one `INSERT ... ON CONFLICT` statement per value (the changeset
+    /// of each value may update different columns), so that values inserted concurrently by another
+    /// transaction are updated rather than failing with a unique violation.
+    ///
+    /// Existing rows are only updated if they have not been soft deleted (see [`restore`](Db::restore))
+    /// and, if a tenant is provided and the table is scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]),
+    /// if they belong to `tenant`; otherwise the whole upsert fails with [`NotFound`](Error::NotFound).
+    ///
+    /// If several values share a primary key only the last of them is upserted, and a single record is
+    /// returned for each distinct primary key in the order in which it first appears.
+    #[framed]
+    #[instrument(skip_all)]
+    fn upsert<'life0, 'async_trait, 'query, 'v, R, V, U, I, T, Pk, F, G>(
+        &'life0 self,
+        values: I,
+        tenant: Option<&Tenant>,
+    ) -> BoxFuture<'async_trait, Result<Vec<R>, Error>>
+    where
+        V: HasTable<Table = T> + Insertable<T> + Clone + Into<U> + Send,
+        for<'a> &'a V: Identifiable<Id = &'a Pk>,
+        <V as Insertable<T>>::Values: Send + 'query,
+
+        U: AsChangeset<Target = T> + IncludesChanges + Send + Sync,
+        <U as AsChangeset>::Changeset: Send + 'query,
+
+        InsertStatement<
+            T,
+            OnConflictPrimaryKey<
+                <V as Insertable<T>>::Values,
+                T::PrimaryKey,
+                U::Changeset,
+                BoxedFilter<'static, T, Self::Backend>,
+            >,
+        >: LoadQuery<'query, Self::AsyncConnection, R>,
+
+        Pk: AsExpression<SqlTypeOf<T::PrimaryKey>> + Clone + Hash + Eq + Send + Sync,
+
+        T: Table + QueryId + Send + 'query,
+        <T as QuerySource>::FromClause: Send,
+
+        I: IntoIterator<Item = V> + Send,
+        R: Send,
+        for<'a> &'a R: Identifiable<Id = &'a Pk>,
+
+        R: MaybeAudit<'query, Self::AsyncConnection>,
+
+        // existing rows are only updated if they satisfy the guard built from the tenant and
+        // soft delete columns, and are read back with the same guard if they are not returned
+        // by the insert statement
+        Self::Backend: 'static,
+        T: FilterColumns<Self::Backend> + 'static,
+        R: MaybeNotDeleted<T, Self::Backend>,
+        bool: ToSql<Bool, Self::Backend>,
+        ht::And<BoxedFilter<'static, T, Self::Backend>, BoxedFilter<'static, T, Self::Backend>>:
+            BoxableExpression<T, Self::Backend, SqlType = Bool>,
+        ht::AsExprOf<bool, Bool>: BoxableExpression<T, Self::Backend, SqlType = Bool>,
+        T::PrimaryKey: Column + ExpressionMethods,
+        <T::PrimaryKey as Expression>::SqlType: SqlType,
+        T: FilterDsl<BoxedFilter<'static, T, Self::Backend>, Output = F>,
+        F: FilterDsl<ht::EqAny<<T as Table>::PrimaryKey, Vec<Pk>>, Output = G>,
+        G: LoadQuery<'query, Self::AsyncConnection, R> + Send + 'query,
+
+        'life0: 'async_trait,
+        'query: 'async_trait,
+        'v: 'async_trait + 'life0,
+        R: 'async_trait,
+        V: 'async_trait,
+        U: 'async_trait,
+        I: 'async_trait + 'v,
+        T: 'async_trait,
+        Pk: 'async_trait,
+        F: 'async_trait,
+        G: 'async_trait,
+    {
+        let (ids, values) = dedupe_by_id(values);
+        let tenant = tenant.cloned();
+
+        instrument_err!(self.raw_tx(move |conn| {
+            if values.is_empty() {
+                return Box::pin(ready(Ok(vec![])));
+            }
+
+            async move {
+                let mut all_upserted = Vec::with_capacity(ids.len());
+                let mut unchanged_ids = vec![];
+                for value in values {
+                    let id = value.id().clone();
+                    let patch: U = value.clone().into();
+                    let changeset = if patch.includes_changes() { Some(patch.as_changeset()) } else { None };
+                    let guard = upsert_guard::<R, T, Self::Backend>(tenant.as_ref())?;
+                    let upserted = ::diesel::insert_into(V::table())
+                        .values(OnConflictPrimaryKey::new(value.values(), changeset, guard))
+                        .get_results::<R>(conn)
+                        .await?;
+                    match upserted.len() {
+                        0 => unchanged_ids.push(id),
+                        _ => all_upserted.extend(upserted),
+                    }
+                }
+
+                R::maybe_insert_audit_records(conn, &all_upserted).await?;
+
+                let unchanged_records = if unchanged_ids.is_empty() {
+                    vec![]
+                } else {
+                    let guard = upsert_guard::<R, T, Self::Backend>(tenant.as_ref())?
+                        .unwrap_or_else(|| Box::new(true.into_sql::<Bool>()));
+                    FilterDsl::filter(
+                        FilterDsl::filter(V::table(), guard),
+                        V::table().primary_key().eq_any(unchanged_ids),
+                    )
+                    .get_results::<R>(&mut *conn)
+                    .await?
+                };
+
+                let mut all_records = unchanged_records
+                    .into_iter()
+                    .chain(all_upserted)
+                    .map(|record| (record.id().clone(), record))
+                    .collect::<HashMap<_, _>>();
+
+                // a conflicting row which does not satisfy the guard is neither updated nor read back, and a
+                // row whose changeset does not include any changes may have been purged since it conflicted
+                ids.iter()
+                    .map(|id| all_records.remove(id).ok_or(Error::NotFound))
+                    .collect::<Result<Vec<_>, _>>()
+            }
+            .scope_boxed()
+        }))
+        .boxed()
+    }
+
+    /// Clears the deleted at column of the soft deleted rows with the provided primary keys.
+    #[framed]
+    #[instrument(skip_all)]
+    fn restore<'life0, 'async_trait, 'query, R, T, Pk, I, F>(
+        &'life0 self,
+        ids: I,
+    ) -> BoxFuture<'async_trait, Result<Vec<R>, Error>>
+    where
+        Pk: AsExpression<SqlTypeOf<T::PrimaryKey>> + Send,
+        I: Debug + IntoIterator<Item = Pk> + Send,
+        R: Send + HasTable<Table = T> + SoftDeletable,
+        <R as SoftDeletable>::DeletedAt: Column<Table = T> + 'query,
+        T: Table + QueryId + Send + 'query,
+        <T as QuerySource>::FromClause: Send,
+        T::PrimaryKey: Expression + ExpressionMethods,
+        <T::PrimaryKey as Expression>::SqlType: SqlType,
+        T: FilterDsl<ht::EqAny<<T as Table>::PrimaryKey, Vec<Pk>>, Output = F>,
+        F: IntoUpdateTarget + HasTable<Table = T> + Send + 'query,
+        <F as IntoUpdateTarget>::WhereClause: Send + 'query,
+        ht::Update<F, ht::Eq<<R as SoftDeletable>::DeletedAt, Option<SystemTime>>>:
+            AsQuery + LoadQuery<'query, Self::AsyncConnection, R> + Send,
+
+        R: MaybeAudit<'query, Self::AsyncConnection>,
+
+        'life0: 'async_trait,
+        'query: 'async_trait,
+        R: 'async_trait,
+        T: 'async_trait,
+        Pk: 'async_trait,
+        F: 'async_trait,
+        I: 'async_trait,
+        Self: 'life0,
+    {
+        let ids = ids.into_iter().collect::<Vec<_>>();
+        if ids.is_empty() {
+            return Box::pin(ready(Ok(vec![])));
+        }
+
+        instrument_err!(self.raw_tx(move |conn| {
+            async move {
+                let all_restored = restore_query::<R, T, Pk, F>(ids).get_results::<R>(conn).await?;
+
+                R::maybe_insert_audit_records(conn, &all_restored).await?;
+
+                Ok(all_restored)
+            }
+            .scope_boxed()
+        }))
+        .boxed()
+    }
 }
 
 impl<D> Db for D
@@ -460,13 +725,83 @@ where
 {
 }
 
+/// Clears the deleted at column of the rows with the provided primary keys.
+fn restore_query<R, T, Pk, F>(ids: Vec<Pk>) -> ht::Update<F, ht::Eq<R::DeletedAt, Option<SystemTime>>>
+where
+    Pk: AsExpression<SqlTypeOf<T::PrimaryKey>>,
+    R: HasTable<Table = T> + SoftDeletable,
+    <R as SoftDeletable>::DeletedAt: Column<Table = T>,
+    T: Table,
+    T::PrimaryKey: Expression + ExpressionMethods,
+    <T::PrimaryKey as Expression>::SqlType: SqlType,
+    T: FilterDsl<ht::EqAny<<T as Table>::PrimaryKey, Vec<Pk>>, Output = F>,
+    F: IntoUpdateTarget + HasTable<Table = T>,
+    ht::Update<F, ht::Eq<<R as SoftDeletable>::DeletedAt, Option<SystemTime>>>: AsQuery,
+{
+    let query = FilterDsl::filter(R::table(), R::table().primary_key().eq_any(ids));
+    ::diesel::update(query).set(R::DeletedAt::default().eq(None::<SystemTime>))
+}
+
+/// Matches the existing rows which [`upsert`](Db::upsert) may update: rows which have not been soft deleted
+/// and, if the table is scoped by tenant and a tenant is provided, which belong to `tenant`.
+fn upsert_guard<R, T, DB>(tenant: Option<&Tenant>) -> Result<Option<BoxedFilter<'static, T, DB>>, Error>
+where
+    R: MaybeNotDeleted<T, DB>,
+    T: FilterColumns<DB> + 'static,
+    DB: Backend + 'static,
+    ht::And<BoxedFilter<'static, T, DB>, BoxedFilter<'static, T, DB>>: BoxableExpression<T, DB, SqlType = Bool>,
+{
+    let tenant = match (T::TENANT_COLUMN, tenant) {
+        (Some(column), Some(tenant)) => Some(T::filter_column(&Condition::new(
+            column,
+            Comparison::Eq,
+            tenant.clone(),
+        ))?),
+        _ => None,
+    };
+    Ok(match (tenant, R::maybe_not_deleted()) {
+        (Some(tenant), Some(not_deleted)) => Some(Box::new(tenant.and(not_deleted))),
+        (guard, None) | (None, guard) => guard,
+    })
+}
+
+/// Returns the distinct ids of `values` in the order in which they first appear,
+/// along with the last value for each of them.
+fn dedupe_by_id<V, Pk>(values: impl IntoIterator<Item = V>) -> (Vec<Pk>, Vec<V>)
+where
+    for<'a> &'a V: Identifiable<Id = &'a Pk>,
+    Pk: Clone + Hash + Eq,
+{
+    let mut indices = HashMap::<Pk, usize>::new();
+    let mut deduped = Vec::<V>::new();
+    for value in values {
+        match indices.get(value.id()) {
+            Some(index) => deduped[*index] = value,
+            None => {
+                indices.insert(value.id().clone(), deduped.len());
+                deduped.push(value);
+            }
+        }
+    }
+    let ids = deduped.iter().map(|value| value.id().clone()).collect();
+    (ids, deduped)
+}
+
 cfg_if! {
     if #[cfg(any(feature = "diesel-bb8", feature = "diesel-deadpool", feature = "diesel-mobc"))] {
-        use crate::{DataSourceConnRef, DataSourceConnection, TxCleanup, TxCleanupFn, TxFn};
+        use crate::{DataSourceConnRef, DataSourceConnection, TxCleanup, TxCleanupFn, TxCommit, TxCommitFn, TxFn};
         use scoped_futures::ScopedBoxFuture;
         use std::borrow::Cow;
         use uuid::Uuid;
 
+        /// Runs the functions registered with [`TransactionalDataSource::tx_commit`] within a transaction which has been committed.
+        fn run_tx_commit(tx_commit: TxCommit) {
+            let tx_commit_fns = std::mem::take(&mut *tx_commit.lock().unwrap_or_else(|err| err.into_inner()));
+            for tx_commit_fn in tx_commit_fns {
+                tx_commit_fn();
+            }
+        }
+
         impl<'d, C> DataSource for DbConnOwned<'d, C, Uuid>
         where
             C: AsyncConnection + PoolableConnection + Send + Sync + 'static,
@@ -550,6 +885,16 @@ cfg_if! {
                 tx_cleanup.push(Box::new(|x| f(x).map_err(Into::into).boxed()));
             }
 
+            async fn tx_commit<F>(&self, f: F)
+            where
+                F: TxCommitFn,
+            {
+                match self.tx_id {
+                    Some(_) => self.tx_commit.lock().unwrap_or_else(|err| err.into_inner()).push(Box::new(f)),
+                    None => f(),
+                }
+            }
+
             #[framed]
             async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
             where
@@ -559,11 +904,14 @@ cfg_if! {
                 'life0: 'a,
             {
                 let tx_cleanup = <Self as AsRef<TxCleanup<Self::AsyncConnection, Self::TransactionId>>>::as_ref(self).clone();
-                self.with_tx_connection(move |mut conn| async move {
+                let tx_commit = TxCommit::default();
+                let db_tx_commit = tx_commit.clone();
+                let value = self.with_tx_connection(move |mut conn| async move {
                     let db_connection = DataSourceConnection {
                         tx_id: Some(Uuid::new_v4()),
                         connection: Arc::new(RwLock::new(conn.deref_mut())),
                         tx_cleanup: tx_cleanup.clone(),
+                        tx_commit: db_tx_commit,
                     };
                     let value = callback.call_tx_fn(Cow::Borrowed(&db_connection)).await?;
                     let mut tx_cleanup = tx_cleanup.lock().await;
@@ -571,7 +919,9 @@ cfg_if! {
                         tx_cleanup_fn(&db_connection).await?;
                     }
                     Ok::<T, E>(value)
-                }.scope_boxed()).await
+                }.scope_boxed()).await?;
+                run_tx_commit(tx_commit);
+                Ok(value)
             }
 
             #[framed]
@@ -584,13 +934,16 @@ cfg_if! {
                 T: Send + 'a,
             {
                 let tx_cleanup = <Self as AsRef<TxCleanup<Self::AsyncConnection, Self::TransactionId>>>::as_ref(self).clone();
+                let tx_commit = TxCommit::default();
+                let db_tx_commit = tx_commit.clone();
                 #[allow(unused_mut)]
-                self.with_tx_connection(move |mut conn| async move {
+                let value = self.with_tx_connection(move |mut conn| async move {
                     let value = callback(conn).await?;
 
                     let db_connection = DataSourceConnection {
                         tx_id: Some(Uuid::new_v4()),
                         tx_cleanup: tx_cleanup.clone(),
+                        tx_commit: db_tx_commit,
                         connection: Arc::new(RwLock::new(conn.deref_mut())),
                     };
                     let mut tx_cleanup = tx_cleanup.lock().await;
@@ -598,7 +951,9 @@ cfg_if! {
                         tx_cleanup_fn(&db_connection).await?;
                     }
                     Ok(value)
-                }.scope_boxed()).await
+                }.scope_boxed()).await?;
+                run_tx_commit(tx_commit);
+                Ok(value)
             }
         }
 
@@ -646,6 +1001,16 @@ cfg_if! {
                 tx_cleanup.push(Box::new(|x| f(x).map_err(Into::into).boxed()));
             }
 
+            async fn tx_commit<F>(&self, f: F)
+            where
+                F: TxCommitFn,
+            {
+                match self.tx_id {
+                    Some(_) => self.tx_commit.lock().unwrap_or_else(|err| err.into_inner()).push(Box::new(f)),
+                    None => f(),
+                }
+            }
+
             async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
             where
                 F: for<'r> TxFn<'a, Self::TxConnection<'r>, ScopedBoxFuture<'a, 'r, Result<T, E>>>,
@@ -655,15 +1020,28 @@ cfg_if! {
             {
                 let tx_id = self.tx_id;
                 let tx_cleanup = <Self as AsRef<TxCleanup<Self::AsyncConnection, Self::TransactionId>>>::as_ref(self).clone();
-                self.with_tx_connection(move |conn| {
+                let outer_tx_commit = self.tx_commit.clone();
+                let tx_commit = TxCommit::default();
+                let db_tx_commit = tx_commit.clone();
+                let value = self.with_tx_connection(move |conn| {
                     let db_connection = DataSourceConnection {
                         connection: Arc::new(RwLock::new(conn)),
                         tx_cleanup: tx_cleanup.clone(),
+                        tx_commit: db_tx_commit,
                         tx_id,
                     };
                     callback.call_tx_fn(Cow::Owned(db_connection)).scope_boxed()
                 })
-                .await
+                .await?;
+                match tx_id {
+                    // functions registered within a nested transaction run once the outermost transaction is committed
+                    Some(_) => {
+                        let mut tx_commit_fns = std::mem::take(&mut *tx_commit.lock().unwrap_or_else(|err| err.into_inner()));
+                        outer_tx_commit.lock().unwrap_or_else(|err| err.into_inner()).append(&mut tx_commit_fns)
+                    }
+                    None => run_tx_commit(tx_commit),
+                }
+                Ok(value)
             }
 
             #[framed]
@@ -722,6 +1100,13 @@ cfg_if! {
             {
             }
 
+            async fn tx_commit<F>(&self, f: F)
+            where
+                F: TxCommitFn,
+            {
+                f()
+            }
+
             #[framed]
             async fn tx<'life0, 'a, T, E, F>(&'life0 self, callback: F) -> Result<T, E>
             where
@@ -730,13 +1115,16 @@ cfg_if! {
                 T: Send + 'a,
                 'life0: 'a,
             {
-                self.raw_tx(|async_connection| {
+                let tx_commit = TxCommit::default();
+                let db_tx_commit = tx_commit.clone();
+                let value = self.raw_tx(|async_connection| {
                     async move {
                         let tx_cleanup = TxCleanup::default();
                         let db_connection = DataSourceConnection {
                             tx_id: Some(Uuid::new_v4()),
                             connection: Arc::new(RwLock::new(async_connection)),
                             tx_cleanup: tx_cleanup.clone(),
+                            tx_commit: db_tx_commit,
                         };
                         let value = callback.call_tx_fn(Cow::Borrowed(&db_connection)).await?;
                         let mut tx_cleanup = tx_cleanup.lock().await;
@@ -747,7 +1135,9 @@ cfg_if! {
                     }
                     .scope_boxed()
                 })
-                .await
+                .await?;
+                run_tx_commit(tx_commit);
+                Ok(value)
             }
 
             #[framed]
@@ -768,6 +1158,7 @@ cfg_if! {
                     let db_connection = DataSourceConnection {
                         tx_id: Some(Uuid::new_v4()),
                         tx_cleanup: tx_cleanup.clone(),
+                        tx_commit: Default::default(),
                         connection: Arc::new(RwLock::new(conn.deref_mut())),
                     };
                     let mut tx_cleanup = tx_cleanup.lock().await;
@@ -780,3 +1171,109 @@ cfg_if! {
         }
     }
 }
+
+#[cfg(all(test, feature = "diesel-postgres"))]
+mod test {
+    use super::*;
+    use crate::diesel::deletable::hard_delete_query;
+    use crate::{AuthzFilter, Comparison, Condition, Tenant};
+    use ::diesel::debug_query;
+    use ::diesel::pg::Pg;
+    use ::diesel::Queryable;
+    use ::diesel_async::AsyncPgConnection;
+
+    diesel::table! {
+        item (id) {
+            id -> Int4,
+            tenant_id -> Text,
+            quantity -> Int4,
+            deleted_at -> Nullable<Timestamp>,
+        }
+    }
+
+    crate::filter_columns!(Pg; item {
+        id: i32,
+        #[tenant]
+        tenant_id: String,
+        quantity: i32,
+    });
+
+    #[derive(Clone, Debug, Identifiable, Insertable, PartialEq, Queryable)]
+    #[diesel(table_name = item)]
+    struct Item {
+        id: i32,
+        tenant_id: String,
+        quantity: i32,
+        deleted_at: Option<SystemTime>,
+    }
+
+    impl SoftDeletable for Item {
+        type DeletedAt = item::deleted_at;
+    }
+
+    /// Collapses whitespace, which differs between diesel versions.
+    fn sql<Q: QueryFragment<Pg>>(query: &Q) -> String {
+        debug_query::<Pg, _>(query)
+            .to_string()
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn item(id: i32, quantity: i32) -> Item {
+        Item {
+            id,
+            tenant_id: "acme".into(),
+            quantity,
+            deleted_at: None,
+        }
+    }
+
+    #[test]
+    fn upserts_the_last_value_of_each_id() {
+        let (ids, values) = dedupe_by_id::<_, i32>([item(1, 1), item(2, 1), item(1, 3)]);
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(values, vec![item(1, 3), item(2, 1)]);
+    }
+
+    #[test]
+    fn only_upserts_rows_of_the_tenant_which_are_not_deleted() {
+        let guard = upsert_guard::<Item, item::table, Pg>(Some(&Tenant::from("acme")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            sql(&item::table.filter(guard)),
+            r#"SELECT "item"."id", "item"."tenant_id", "item"."quantity", "item"."deleted_at" FROM "item" WHERE (("item"."tenant_id" = $1) AND ("item"."deleted_at" IS NULL)) -- binds: ["acme"]"#,
+        );
+    }
+
+    #[test]
+    fn restores_soft_deleted_rows() {
+        assert_eq!(
+            sql(&restore_query::<Item, _, _, _>(vec![1, 2])),
+            r#"UPDATE "item" SET "deleted_at" = $1 WHERE ("item"."id" = ANY($2)) -- binds: [None, [1, 2]]"#,
+        );
+    }
+
+    #[test]
+    fn purges_soft_deletable_rows() {
+        assert_eq!(
+            sql(&hard_delete_query::<Item, _, _, _>(vec![1, 2])),
+            r#"DELETE FROM "item" WHERE ("item"."id" = ANY($1)) -- binds: [[1, 2]]"#,
+        );
+    }
+
+    #[test]
+    fn lists_pages_of_rows_which_are_not_deleted() {
+        let filter = AuthzFilter(vec![vec![Condition::new("quantity", Comparison::Ge, 2)]]);
+        let filter = filter
+            .to_diesel::<item::table, Pg>(Some(&Tenant::from("acme")))
+            .unwrap();
+        let query = IsNotDeleted::<'_, AsyncPgConnection, Item, Item>::is_not_deleted(item::table.filter(filter))
+            .paginate(Page::new(1, 2));
+        assert_eq!(
+            sql(&query),
+            r#"select * from (select *, row_number() over () as offset from ( SELECT "item"."id", "item"."tenant_id", "item"."quantity", "item"."deleted_at" FROM "item" WHERE ((($1 AND ("item"."quantity" >= $2)) AND ("item"."tenant_id" = $3)) AND ("item"."deleted_at" IS NULL))) t ) s where true and s.offset > $4 and s.offset <= $5 -- binds: [true, 2, "acme", 2, 4]"#,
+        );
+    }
+}
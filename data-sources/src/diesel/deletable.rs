@@ -1,13 +1,14 @@
 use crate::diesel::prelude::*;
 use ::diesel::associations::HasTable;
+use ::diesel::backend::Backend;
 use ::diesel::dsl::SqlTypeOf;
-use ::diesel::expression::{AsExpression, Expression};
+use ::diesel::expression::{AsExpression, BoxableExpression, Expression};
 use ::diesel::expression_methods::ExpressionMethods;
 use ::diesel::helper_types as ht;
 use ::diesel::query_dsl::methods::{FilterDsl, FindDsl};
 use ::diesel::query_source::QuerySource;
 use ::diesel::result::Error;
-use ::diesel::sql_types::{Nullable, SqlType, Timestamp};
+use ::diesel::sql_types::{Bool, Nullable, SqlType, Timestamp};
 use ::diesel::{query_builder::*, Identifiable};
 use ::diesel::{Column, Table};
 use ::diesel_async::methods::*;
@@ -42,6 +43,29 @@ pub trait SoftDeletable {
     type DeletedAt: Default + Column<SqlType = Nullable<Timestamp>> + ExpressionMethods;
 }
 
+/// Matches the rows of table `T` which have not been soft deleted if `Self` is [`SoftDeletable`];
+/// returns `None` otherwise.
+pub trait MaybeNotDeleted<T, DB> {
+    fn maybe_not_deleted() -> Option<BoxedFilter<'static, T, DB>>;
+}
+
+impl<T, DB, R> MaybeNotDeleted<T, DB> for R {
+    default fn maybe_not_deleted() -> Option<BoxedFilter<'static, T, DB>> {
+        None
+    }
+}
+
+impl<T, DB, R> MaybeNotDeleted<T, DB> for R
+where
+    R: SoftDeletable,
+    DB: Backend,
+    ht::IsNull<R::DeletedAt>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+{
+    fn maybe_not_deleted() -> Option<BoxedFilter<'static, T, DB>> {
+        Some(Box::new(R::DeletedAt::default().is_null()))
+    }
+}
+
 impl<'query, C, Tab, I, Id, F1, F2, DeletedAt, DeletePatch, T> Deletable<'query, C, Tab, I, Id, DeletedAt, DeletePatch>
     for T
 where
@@ -78,8 +102,7 @@ where
         Self: 'async_trait,
     {
         async move {
-            let query = Self::table().filter(Self::table().primary_key().eq_any(ids));
-            let records = diesel::delete(query).get_results(conn).await?;
+            let records = hard_delete_query::<Self, Tab, I, F1>(ids).get_results(conn).await?;
 
             Self::maybe_insert_audit_records(conn, &records).await?;
 
@@ -102,6 +125,21 @@ where
     }
 }
 
+/// Permanently deletes the rows with the provided primary keys, regardless of whether `R` is [`SoftDeletable`].
+pub(crate) fn hard_delete_query<R, Tab, I, F>(ids: I) -> DeleteStatement<F::Table, F::WhereClause>
+where
+    R: HasTable<Table = Tab>,
+    Tab: Table,
+    Tab::PrimaryKey: Expression + ExpressionMethods,
+    <Tab::PrimaryKey as Expression>::SqlType: SqlType,
+    Tab: FilterDsl<ht::EqAny<Tab::PrimaryKey, I>, Output = F>,
+    I: IntoIterator,
+    I::Item: AsExpression<SqlTypeOf<Tab::PrimaryKey>>,
+    F: IntoUpdateTarget,
+{
+    diesel::delete(R::table().filter(R::table().primary_key().eq_any(ids)))
+}
+
 impl<'query, C, Tab, I, Id, F1, F2, DeletedAt, DeletePatch, T> Deletable<'query, C, Tab, I, Id, DeletedAt, DeletePatch>
     for T
 where
@@ -0,0 +1,326 @@
+use crate::filter::*;
+use crate::Tenant;
+use ::diesel::backend::Backend;
+use ::diesel::dsl::SqlTypeOf;
+use ::diesel::expression::{AsExpression, BoxableExpression, Expression, IntoSql};
+use ::diesel::expression_methods::{BoolExpressionMethods, ExpressionMethods, NullableExpressionMethods};
+use ::diesel::helper_types as ht;
+use ::diesel::serialize::ToSql;
+use ::diesel::sql_types::is_nullable::{self, IsSqlTypeNullable};
+use ::diesel::sql_types::{Bool, SqlType};
+use ::diesel::{Column, Table};
+use ::serde::de::DeserializeOwned;
+use ::serde::{Deserialize, Serialize};
+
+/// A boxed boolean expression on table `T`, the diesel representation of an [`AuthzFilter`].
+pub type BoxedFilter<'a, T, DB> = Box<dyn BoxableExpression<T, DB, SqlType = Bool> + 'a>;
+
+/// A page of the rows which satisfy an [`AuthzFilter`], see
+/// [`get_page_filtered`](crate::diesel::operations::DbGet::get_page_filtered).
+///
+/// The tenant which the rows are scoped to is not part of the page, it is taken from the event
+/// listing them instead so that callers cannot list the rows of other tenants.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FilteredPage {
+    pub page: crate::diesel::paginate::Page,
+    pub filter: AuthzFilter,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthzFilterError {
+    #[error("authorization filter references unknown column `{0}`")]
+    UnknownColumn(String),
+    #[error("invalid value for column `{column}` in authorization filter: {source}")]
+    InvalidValue { column: String, source: serde_json::Error },
+    #[error("authorization filter on a table scoped by column `{0}` requires a tenant")]
+    MissingTenant(&'static str),
+}
+
+impl From<AuthzFilterError> for diesel::result::Error {
+    fn from(value: AuthzFilterError) -> Self {
+        Self::QueryBuilderError(value.into())
+    }
+}
+
+/// Maps the field names referenced by an [`AuthzFilter`] to the columns of a table.
+///
+/// Typically implemented with the [`filter_columns`](crate::filter_columns) macro, only
+/// columns which policies may reference need to be listed.
+pub trait FilterColumns<DB: Backend>: Table + Sized {
+    /// The column identifying the tenant each row belongs to, if rows of this table are scoped by tenant.
+    ///
+    /// Reads made through an [`AuthzFilter`] are scoped by this column, while operations which address
+    /// rows by id or by column (e.g. [`DbGet::get`](crate::diesel::operations::DbGet::get)) are not unless
+    /// the rows are first checked with [`DbGet::check_tenant`](crate::diesel::operations::DbGet::check_tenant).
+    const TENANT_COLUMN: Option<&'static str> = None;
+
+    fn filter_column(condition: &Condition) -> Result<BoxedFilter<'static, Self, DB>, AuthzFilterError>;
+}
+
+impl AuthzFilter {
+    /// Converts this filter into a boxed diesel expression which can be passed to `filter`.
+    ///
+    /// If the table is scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]), the filter is first
+    /// [scoped](AuthzFilter::scope) to `tenant`, so that rows of other tenants are never matched
+    /// regardless of the filter's conditions; an error is returned if no tenant is provided.
+    pub fn to_diesel<T, DB>(&self, tenant: Option<&Tenant>) -> Result<BoxedFilter<'static, T, DB>, AuthzFilterError>
+    where
+        T: FilterColumns<DB> + 'static,
+        DB: Backend + 'static,
+        bool: ToSql<Bool, DB>,
+        ht::And<BoxedFilter<'static, T, DB>, BoxedFilter<'static, T, DB>>: BoxableExpression<T, DB, SqlType = Bool>,
+        ht::Or<BoxedFilter<'static, T, DB>, BoxedFilter<'static, T, DB>>: BoxableExpression<T, DB, SqlType = Bool>,
+        ht::AsExprOf<bool, Bool>: BoxableExpression<T, DB, SqlType = Bool>,
+    {
+        let scoped;
+        let filter = match T::TENANT_COLUMN {
+            Some(column) => {
+                scoped = self
+                    .clone()
+                    .scope(column, tenant.ok_or(AuthzFilterError::MissingTenant(column))?);
+                &scoped
+            }
+            None => self,
+        };
+        let mut disjunction: Option<BoxedFilter<'static, T, DB>> = None;
+        for conjunction in &filter.0 {
+            let mut expr: BoxedFilter<'static, T, DB> = Box::new(true.into_sql::<Bool>());
+            for condition in conjunction {
+                expr = Box::new(expr.and(T::filter_column(condition)?));
+            }
+            disjunction = Some(match disjunction {
+                Some(disjunction) => Box::new(disjunction.or(expr)),
+                None => expr,
+            });
+        }
+        Ok(disjunction.unwrap_or_else(|| Box::new(false.into_sql::<Bool>())))
+    }
+}
+
+/// Matches the rows of a table scoped by tenant (see [`FilterColumns::TENANT_COLUMN`]) which do not belong
+/// to `tenant`, including rows without a tenant; returns `None` if the table is not scoped by tenant.
+pub fn other_tenants<T, DB>(tenant: Option<&Tenant>) -> Result<Option<BoxedFilter<'static, T, DB>>, AuthzFilterError>
+where
+    T: FilterColumns<DB>,
+    DB: Backend,
+{
+    let Some(column) = T::TENANT_COLUMN else {
+        return Ok(None);
+    };
+    let tenant = tenant.ok_or(AuthzFilterError::MissingTenant(column))?;
+    T::filter_column(&Condition::new(column, Comparison::Eq, tenant.clone()).negate()).map(Some)
+}
+
+/// Converts a single condition on `column` into a boxed expression, deserializing the
+/// condition's value as `V`. Nullable columns are compared as if they were not null (see [`NonNullColumn`]).
+///
+/// A [negated](Condition::negated) condition is translated to `col IS NULL OR col <negated comparison> value`
+/// rather than `NOT (col <comparison> value)`, which would exclude rows where `col` is null even though the
+/// policy the condition came from allows them.
+pub fn filter_column<T, C, V, DB>(
+    column: C,
+    condition: &Condition,
+) -> Result<BoxedFilter<'static, T, DB>, AuthzFilterError>
+where
+    T: 'static,
+    DB: Backend + 'static,
+    C: Column<Table = T> + Copy + ExpressionMethods,
+    SqlTypeOf<C>: SqlType,
+    IsSqlTypeNullable<SqlTypeOf<C>>: NonNullColumn<C>,
+    NonNull<C>: ExpressionMethods,
+    SqlTypeOf<NonNull<C>>: SqlType,
+    V: AsExpression<SqlTypeOf<NonNull<C>>> + DeserializeOwned,
+    ht::Eq<NonNull<C>, V>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+    ht::NotEq<NonNull<C>, V>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+    ht::Lt<NonNull<C>, V>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+    ht::LtEq<NonNull<C>, V>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+    ht::Gt<NonNull<C>, V>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+    ht::GtEq<NonNull<C>, V>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+    ht::Or<ht::IsNull<C>, BoxedFilter<'static, T, DB>>: BoxableExpression<T, DB, SqlType = Bool> + 'static,
+{
+    let value: V =
+        serde_json::from_value(condition.value.clone()).map_err(|source| AuthzFilterError::InvalidValue {
+            column: condition.field.clone(),
+            source,
+        })?;
+    let comparison = match condition.negated {
+        true => condition.comparison.negate(),
+        false => condition.comparison,
+    };
+    let non_null = <IsSqlTypeNullable<SqlTypeOf<C>> as NonNullColumn<C>>::non_null(column);
+    let expr: BoxedFilter<'static, T, DB> = match comparison {
+        Comparison::Eq => Box::new(non_null.eq(value)),
+        Comparison::Ne => Box::new(non_null.ne(value)),
+        Comparison::Lt => Box::new(non_null.lt(value)),
+        Comparison::Le => Box::new(non_null.le(value)),
+        Comparison::Gt => Box::new(non_null.gt(value)),
+        Comparison::Ge => Box::new(non_null.ge(value)),
+    };
+    Ok(match condition.negated {
+        true => Box::new(column.is_null().or(expr)),
+        false => expr,
+    })
+}
+
+/// A column with the nullability stripped from its sql type, see [`NonNullColumn`].
+pub type NonNull<C> = <IsSqlTypeNullable<SqlTypeOf<C>> as NonNullColumn<C>>::Output;
+
+/// Strips the nullability from a column's sql type, so that comparisons against the column have type
+/// `Bool` rather than `Nullable<Bool>`. Implemented for the column's [`IsSqlTypeNullable`] marker.
+///
+/// A comparison against a null value is still null at runtime, which `filter` treats as false;
+/// [negated](Condition::negated) conditions check for null explicitly.
+pub trait NonNullColumn<C> {
+    type Output: Expression;
+
+    fn non_null(column: C) -> Self::Output;
+}
+
+impl<C: Expression> NonNullColumn<C> for is_nullable::NotNull {
+    type Output = C;
+
+    fn non_null(column: C) -> Self::Output {
+        column
+    }
+}
+
+impl<C: Expression> NonNullColumn<C> for is_nullable::IsNullable
+where
+    ht::AssumeNotNull<C>: Expression,
+{
+    type Output = ht::AssumeNotNull<C>;
+
+    fn non_null(column: C) -> Self::Output {
+        column.assume_not_null()
+    }
+}
+
+/// Implements [`FilterColumns`] for a table, mapping each listed column name
+/// to the rust type its values should be deserialized as.
+/// ```rs
+/// filter_columns!(diesel::pg::Pg; schema::cart {
+///     id: Uuid,
+///     account_id: Uuid,
+///     created_at: chrono::NaiveDateTime,
+/// });
+/// ```
+/// A column can be marked with `#[tenant]` to scope every filter on the table by tenant
+/// (see [`FilterColumns::TENANT_COLUMN`]).
+/// ```rs
+/// filter_columns!(diesel::pg::Pg; schema::cart {
+///     id: Uuid,
+///     #[tenant]
+///     tenant_id: Uuid,
+/// });
+/// ```
+#[macro_export]
+macro_rules! filter_columns {
+    ($backend:ty; $($table:ident)::+ { $($(#[$tenant:ident])? $column:ident: $ty:ty),* $(,)? }) => {
+        impl $crate::diesel::filter::FilterColumns<$backend> for $($table)::+::table {
+            const TENANT_COLUMN: Option<&'static str> = {
+                #[allow(unused_assignments, unused_mut)]
+                let mut tenant_column = None;
+                $($(
+                    $crate::__filter_columns_tenant!($tenant);
+                    tenant_column = Some(stringify!($column));
+                )?)*
+                tenant_column
+            };
+
+            fn filter_column(
+                condition: &$crate::Condition,
+            ) -> Result<$crate::diesel::filter::BoxedFilter<'static, Self, $backend>, $crate::diesel::filter::AuthzFilterError> {
+                #[allow(unused_imports)]
+                use $($table)::+ as columns;
+                match &*condition.field {
+                    $(stringify!($column) => $crate::diesel::filter::filter_column::<_, _, $ty, $backend>(columns::$column, condition),)*
+                    _ => Err($crate::diesel::filter::AuthzFilterError::UnknownColumn(condition.field.clone())),
+                }
+            }
+        }
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __filter_columns_tenant {
+    (tenant) => {};
+}
+
+#[cfg(all(test, feature = "diesel-postgres"))]
+mod test {
+    use super::*;
+    use ::diesel::pg::Pg;
+    use ::diesel::{debug_query, QueryDsl};
+
+    diesel::table! {
+        item (id) {
+            id -> Int4,
+            tenant_id -> Text,
+            quantity -> Nullable<Int4>,
+        }
+    }
+
+    crate::filter_columns!(Pg; item {
+        id: i32,
+        #[tenant]
+        tenant_id: String,
+        quantity: i32,
+    });
+
+    fn sql(filter: &AuthzFilter, tenant: &str) -> String {
+        let expr = filter
+            .to_diesel::<item::table, Pg>(Some(&Tenant::from(tenant)))
+            .unwrap();
+        debug_query::<Pg, _>(&item::table.select(item::id).filter(expr)).to_string()
+    }
+
+    #[test]
+    fn translates_conditions_scoped_by_tenant() {
+        let filter = AuthzFilter(vec![
+            vec![Condition::new("id", Comparison::Eq, 1)],
+            vec![Condition::new("quantity", Comparison::Ge, 2)],
+        ]);
+        assert_eq!(
+            sql(&filter, "acme"),
+            r#"SELECT "item"."id" FROM "item" WHERE ((($1 AND ("item"."id" = $2)) AND ("item"."tenant_id" = $3)) OR (($4 AND ("item"."quantity" >= $5)) AND ("item"."tenant_id" = $6))) -- binds: [true, 1, "acme", true, 2, "acme"]"#,
+        );
+    }
+
+    #[test]
+    fn negation_includes_null_values() {
+        let filter = AuthzFilter(vec![vec![Condition::new("quantity", Comparison::Lt, 5).negate()]]);
+        assert_eq!(
+            sql(&filter, "acme"),
+            r#"SELECT "item"."id" FROM "item" WHERE (($1 AND (("item"."quantity" IS NULL) OR ("item"."quantity" >= $2))) AND ("item"."tenant_id" = $3)) -- binds: [true, 5, "acme"]"#,
+        );
+    }
+
+    #[test]
+    fn matches_rows_of_other_tenants() {
+        let expr = other_tenants::<item::table, Pg>(Some(&Tenant::from("acme")))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            debug_query::<Pg, _>(&item::table.select(item::id).filter(expr)).to_string(),
+            r#"SELECT "item"."id" FROM "item" WHERE (("item"."tenant_id" IS NULL) OR ("item"."tenant_id" != $1)) -- binds: ["acme"]"#,
+        );
+        assert!(matches!(
+            other_tenants::<item::table, Pg>(None),
+            Err(AuthzFilterError::MissingTenant("tenant_id")),
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_columns_and_missing_tenants() {
+        let filter = AuthzFilter(vec![vec![Condition::new("owner", Comparison::Eq, 1)]]);
+        assert!(matches!(
+            filter.to_diesel::<item::table, Pg>(Some(&Tenant::from("acme"))),
+            Err(AuthzFilterError::UnknownColumn(column)) if column == "owner",
+        ));
+        assert!(matches!(
+            AuthzFilter::allow_all().to_diesel::<item::table, Pg>(None),
+            Err(AuthzFilterError::MissingTenant("tenant_id")),
+        ));
+    }
+}
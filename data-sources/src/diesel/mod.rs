@@ -1,8 +1,10 @@
 pub mod audit;
 pub mod connection;
 pub mod deletable;
+pub mod filter;
 pub mod is_deleted;
 pub mod paginate;
+pub mod upsert;
 
 #[doc(hidden)]
 pub mod macros;
@@ -21,9 +23,11 @@ pub mod prelude {
     pub use crate::diesel::_operations::{DbEntity, DbEntityError};
     pub use crate::diesel::audit::*;
     pub use crate::diesel::deletable::*;
+    pub use crate::diesel::filter::*;
     pub use crate::diesel::macros::*;
     pub use crate::diesel::paginate::*;
     pub use crate::diesel::schema::*;
+    pub use crate::diesel::upsert::*;
 
     #[cfg(any(feature = "diesel-bb8", feature = "diesel-deadpool", feature = "diesel-mobc"))]
     pub use crate::diesel::pool::*;
@@ -0,0 +1,140 @@
+use ::diesel::backend::Backend;
+use ::diesel::insertable::CanInsertInSingleQuery;
+use ::diesel::query_builder::*;
+use ::diesel::{Column, Insertable, QueryResult};
+use ::std::marker::PhantomData;
+
+/// The values of a single record inserted with `ON CONFLICT (<primary key>)`, such that a record whose
+/// primary key is already present in the table updates the existing row with `changeset` rather than failing
+/// with a unique violation. If there is no changeset the existing row is left as is and no row is returned.
+///
+/// If a `guard` is provided the existing row is only updated if it satisfies the guard (`DO UPDATE ... WHERE`),
+/// where columns of the table refer to the existing row; otherwise it is left as is and no row is returned.
+///
+/// Only tables whose primary key is a single column are supported.
+#[derive(Clone, Debug)]
+pub struct OnConflictPrimaryKey<Values, PrimaryKey, Changeset, Guard> {
+    values: Values,
+    changeset: Option<Changeset>,
+    guard: Option<Guard>,
+    primary_key: PhantomData<fn() -> PrimaryKey>,
+}
+
+impl<Values, PrimaryKey, Changeset, Guard> OnConflictPrimaryKey<Values, PrimaryKey, Changeset, Guard> {
+    pub fn new(values: Values, changeset: Option<Changeset>, guard: Option<Guard>) -> Self {
+        Self {
+            values,
+            changeset,
+            guard,
+            primary_key: PhantomData,
+        }
+    }
+}
+
+impl<T, Values, PrimaryKey, Changeset, Guard> Insertable<T>
+    for OnConflictPrimaryKey<Values, PrimaryKey, Changeset, Guard>
+{
+    type Values = Self;
+
+    fn values(self) -> Self::Values {
+        self
+    }
+}
+
+impl<Values, PrimaryKey, Changeset, Guard> QueryId for OnConflictPrimaryKey<Values, PrimaryKey, Changeset, Guard> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<DB, Values, PrimaryKey, Changeset, Guard> CanInsertInSingleQuery<DB>
+    for OnConflictPrimaryKey<Values, PrimaryKey, Changeset, Guard>
+where
+    DB: Backend,
+    Values: CanInsertInSingleQuery<DB>,
+{
+    fn rows_to_insert(&self) -> Option<usize> {
+        self.values.rows_to_insert()
+    }
+}
+
+#[cfg(feature = "diesel-postgres")]
+impl<Values, PrimaryKey, Changeset, Guard> QueryFragment<::diesel::pg::Pg>
+    for OnConflictPrimaryKey<Values, PrimaryKey, Changeset, Guard>
+where
+    Values: QueryFragment<::diesel::pg::Pg>,
+    PrimaryKey: Column,
+    Changeset: QueryFragment<::diesel::pg::Pg>,
+    Guard: QueryFragment<::diesel::pg::Pg>,
+{
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, ::diesel::pg::Pg>) -> QueryResult<()> {
+        self.values.walk_ast(out.reborrow())?;
+        out.push_sql(" ON CONFLICT (");
+        out.push_identifier(PrimaryKey::NAME)?;
+        out.push_sql(")");
+        match &self.changeset {
+            Some(changeset) => {
+                out.push_sql(" DO UPDATE SET ");
+                changeset.walk_ast(out.reborrow())?;
+                if let Some(guard) = &self.guard {
+                    out.push_sql(" WHERE ");
+                    guard.walk_ast(out.reborrow())?;
+                }
+            }
+            None => out.push_sql(" DO NOTHING"),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "diesel-postgres"))]
+mod test {
+    use super::*;
+    use ::diesel::pg::Pg;
+    use ::diesel::{debug_query, ExpressionMethods};
+
+    diesel::table! {
+        item (id) {
+            id -> Int4,
+            quantity -> Int4,
+            deleted_at -> Nullable<Timestamp>,
+        }
+    }
+
+    fn sql(
+        changeset: Option<::diesel::dsl::Eq<item::quantity, i32>>,
+        guard: Option<::diesel::dsl::IsNull<item::deleted_at>>,
+    ) -> String {
+        let values = (item::id.eq(1), item::quantity.eq(2));
+        let query = ::diesel::insert_into(item::table).values(OnConflictPrimaryKey::<_, item::id, _, _>::new(
+            Insertable::<item::table>::values(values),
+            changeset.map(AsChangeset::as_changeset),
+            guard,
+        ));
+        debug_query::<Pg, _>(&query).to_string()
+    }
+
+    #[test]
+    fn updates_rows_whose_primary_keys_conflict() {
+        assert_eq!(
+            sql(Some(item::quantity.eq(3)), None),
+            r#"INSERT INTO "item" ("id", "quantity") VALUES ($1, $2) ON CONFLICT ("id") DO UPDATE SET "quantity" = $3 -- binds: [1, 2, 3]"#,
+        );
+    }
+
+    #[test]
+    fn only_updates_conflicting_rows_which_satisfy_the_guard() {
+        assert_eq!(
+            sql(Some(item::quantity.eq(3)), Some(item::deleted_at.is_null())),
+            r#"INSERT INTO "item" ("id", "quantity") VALUES ($1, $2) ON CONFLICT ("id") DO UPDATE SET "quantity" = $3 WHERE ("item"."deleted_at" IS NULL) -- binds: [1, 2, 3]"#,
+        );
+    }
+
+    #[test]
+    fn leaves_rows_whose_primary_keys_conflict_without_changes() {
+        assert_eq!(
+            sql(None, Some(item::deleted_at.is_null())),
+            r#"INSERT INTO "item" ("id", "quantity") VALUES ($1, $2) ON CONFLICT ("id") DO NOTHING -- binds: [1, 2]"#,
+        );
+    }
+}
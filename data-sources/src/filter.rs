@@ -0,0 +1,167 @@
+use crate::Tenant;
+use ::serde::{Deserialize, Serialize};
+use ::serde_json::Value;
+
+/// Comparison operators which can appear in an [`AuthzFilter`] condition.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    /// The comparison which holds exactly when this one does not.
+    pub fn negate(self) -> Self {
+        match self {
+            Self::Eq => Self::Ne,
+            Self::Ne => Self::Eq,
+            Self::Lt => Self::Ge,
+            Self::Le => Self::Gt,
+            Self::Gt => Self::Le,
+            Self::Ge => Self::Lt,
+        }
+    }
+
+    /// The comparison which holds when the operands are swapped, i.e. `a < b` iff `b > a`.
+    pub fn flip(self) -> Self {
+        match self {
+            Self::Eq => Self::Eq,
+            Self::Ne => Self::Ne,
+            Self::Lt => Self::Gt,
+            Self::Le => Self::Ge,
+            Self::Gt => Self::Lt,
+            Self::Ge => Self::Le,
+        }
+    }
+}
+
+/// A comparison between a field of the object being authorized and a constant value.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Condition {
+    /// name of the object's field, e.g. a column name for sql backends
+    pub field: String,
+    pub comparison: Comparison,
+    pub value: Value,
+    /// If true, the condition holds whenever the comparison does not, including when the field has no value.
+    ///
+    /// Policy languages treat a comparison against a missing or null field as false, so its negation is true,
+    /// whereas in sql `col <> x` is null (and the row is excluded) when `col` is null. Negated conditions are
+    /// therefore kept distinct from the [negated comparison](Comparison::negate) so that data sources can
+    /// include rows where the field is null.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub negated: bool,
+}
+
+impl Condition {
+    pub fn new(field: impl Into<String>, comparison: Comparison, value: impl Into<Value>) -> Self {
+        Self {
+            field: field.into(),
+            comparison,
+            value: value.into(),
+            negated: false,
+        }
+    }
+
+    /// The condition which holds exactly when this one does not.
+    pub fn negate(self) -> Self {
+        Self {
+            negated: !self.negated,
+            ..self
+        }
+    }
+}
+
+/// The residual of a partially evaluated authorization policy, describing which objects a subject
+/// is allowed to act upon without having to fetch and authorize each object individually.
+///
+/// Filters are in disjunctive normal form: an object is allowed if it satisfies every condition in
+/// at least one of the conjunctions. Consequently, a filter with no conjunctions allows nothing and
+/// a filter containing an empty conjunction allows everything.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct AuthzFilter(pub Vec<Vec<Condition>>);
+
+impl AuthzFilter {
+    pub fn allow_all() -> Self {
+        Self(vec![vec![]])
+    }
+
+    pub fn deny_all() -> Self {
+        Self(vec![])
+    }
+
+    pub fn is_allow_all(&self) -> bool {
+        self.0.iter().any(Vec::is_empty)
+    }
+
+    pub fn is_deny_all(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Allows the objects allowed by either this filter or `other`.
+    pub fn or(mut self, other: Self) -> Self {
+        self.0.extend(other.0);
+        self
+    }
+
+    /// Restricts this filter to objects whose `field` is equal to `tenant`, by adding the
+    /// comparison to every conjunction. A filter which allows nothing continues to allow nothing.
+    pub fn scope(mut self, field: impl Into<String>, tenant: &Tenant) -> Self {
+        let condition = Condition::new(field, Comparison::Eq, tenant.clone());
+        for conjunction in &mut self.0 {
+            conjunction.push(condition.clone());
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn scopes_every_conjunction() {
+        let tenant = Tenant::from("acme");
+        let scoped = AuthzFilter::allow_all().scope("tenant_id", &tenant);
+        assert_eq!(
+            scoped,
+            AuthzFilter(vec![vec![Condition::new("tenant_id", Comparison::Eq, "acme")]])
+        );
+        assert!(!scoped.is_allow_all());
+        assert!(AuthzFilter::deny_all().scope("tenant_id", &tenant).is_deny_all());
+    }
+
+    #[test]
+    fn allows_objects_allowed_by_either_filter() {
+        let owned = AuthzFilter(vec![vec![Condition::new("account_id", Comparison::Eq, "a")]]);
+        assert_eq!(AuthzFilter::deny_all().or(owned.clone()), owned);
+        assert!(owned.clone().or(AuthzFilter::allow_all()).is_allow_all());
+        assert!(AuthzFilter::deny_all().or(AuthzFilter::deny_all()).is_deny_all());
+    }
+
+    #[test]
+    fn negation_is_kept_distinct_from_the_negated_comparison() {
+        let condition = Condition::new("quantity", Comparison::Lt, 5);
+        let negated = condition.clone().negate();
+        assert!(negated.negated);
+        assert_eq!(negated.comparison, Comparison::Lt);
+        assert_eq!(negated.clone().negate(), condition);
+
+        assert_eq!(
+            serde_json::to_value(&condition).unwrap(),
+            serde_json::json!({ "field": "quantity", "comparison": "lt", "value": 5 }),
+        );
+        assert_eq!(serde_json::to_value(&negated).unwrap()["negated"], true);
+        assert_eq!(
+            serde_json::from_value::<Condition>(
+                serde_json::json!({ "field": "quantity", "comparison": "lt", "value": 5 })
+            )
+            .unwrap(),
+            condition,
+        );
+    }
+}
@@ -4,12 +4,16 @@
 #![cfg_attr(feature = "diesel", feature(specialization))]
 
 pub mod core;
+pub mod filter;
 pub mod prelude;
+pub mod tenant;
 
 #[cfg(feature = "diesel")]
 pub mod diesel;
 
 pub use crate::core::*;
+pub use crate::filter::*;
+pub use crate::tenant::*;
 
 #[doc(hidden)]
 pub use authzen_data_sources_proc_macros as proc_macros;
@@ -0,0 +1,81 @@
+use ::derive_more::*;
+use ::serde::{Deserialize, Serialize};
+use ::serde_json::Value;
+
+/// Identifies the tenant which an event, transaction cache entry or query is scoped to.
+///
+/// Tenants are identified by their string representation, so that tenants identified by e.g. uuids
+/// can be compared against columns of the same type once deserialized (see [`AuthzFilter`](crate::AuthzFilter)).
+#[derive(Clone, Debug, Deserialize, Display, Eq, From, Hash, Into, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(transparent)]
+pub struct Tenant(pub String);
+
+impl Tenant {
+    pub fn new(id: impl ToString) -> Self {
+        Self(id.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<&str> for Tenant {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl From<uuid::Uuid> for Tenant {
+    fn from(value: uuid::Uuid) -> Self {
+        Self::new(value)
+    }
+}
+
+impl From<Tenant> for Value {
+    fn from(value: Tenant) -> Self {
+        Value::String(value.0)
+    }
+}
+
+/// Types which identify the tenant of a request, used by the `#[tenant]` field attribute of the
+/// [`Context`](https://docs.rs/authzen/latest/authzen/derive.Context.html) derive.
+pub trait AsTenant {
+    fn as_tenant(&self) -> Option<Tenant>;
+}
+
+impl AsTenant for Tenant {
+    fn as_tenant(&self) -> Option<Tenant> {
+        Some(self.clone())
+    }
+}
+
+impl AsTenant for str {
+    fn as_tenant(&self) -> Option<Tenant> {
+        Some(self.into())
+    }
+}
+
+impl AsTenant for String {
+    fn as_tenant(&self) -> Option<Tenant> {
+        Some(Tenant(self.clone()))
+    }
+}
+
+impl AsTenant for uuid::Uuid {
+    fn as_tenant(&self) -> Option<Tenant> {
+        Some((*self).into())
+    }
+}
+
+impl<T: ?Sized + AsTenant> AsTenant for &T {
+    fn as_tenant(&self) -> Option<Tenant> {
+        (**self).as_tenant()
+    }
+}
+
+impl<T: AsTenant> AsTenant for Option<T> {
+    fn as_tenant(&self) -> Option<Tenant> {
+        self.as_ref().and_then(AsTenant::as_tenant)
+    }
+}
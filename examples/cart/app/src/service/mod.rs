@@ -18,9 +18,16 @@ pub async fn do_things<D: Db>(ctx: Ctx<'_, D>, cart_id: Uuid, item_id: Uuid) ->
 
     CartItem::can_do_a_thing(&ctx, &[&db_cart_item]).await?;
 
-    let created_then_deleted_cart_items = CartItem::try_create_then_delete(&ctx, [db_cart_item])
-        .await?
+    // both actions are authorized and performed within a single transaction, so
+    // the created cart item is rolled back if its deletion is not authorized
+    let created_then_deleted_cart_items: Result<Vec<DbCartItem>, Error> = sequence!(
+        &ctx,
+        actions::Create<CartItem> => actions::Delete<CartItem>,
+        [db_cart_item],
+        |cart_items: Vec<DbCartItem>| cart_items.into_iter().map(|cart_item| cart_item.id).collect::<Vec<_>>(),
+    );
+    let created_then_deleted_cart_item = created_then_deleted_cart_items?
         .pop()
         .ok_or_else(|| Error::default_details("expected to have created and deleted a cart item"))?;
-    Ok(created_then_deleted_cart_items)
+    Ok(created_then_deleted_cart_item)
 }
@@ -64,6 +64,13 @@ reasons := allow {
 	count(allow) > 0
 }
 
+# authz_batch evaluates authz for each event in input.events, producing
+# an array of decisions in the same order as the provided events
+authz_batch := [decision |
+	some batch_event in input.events
+	decision := authz with input as object.union(batch_event, {"transaction_id": input.transaction_id})
+]
+
 # consts
 create := "create"
 
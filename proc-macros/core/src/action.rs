@@ -46,6 +46,7 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
     let ty = ty.unwrap_or_else(|| snake_name.clone());
 
     let source_mod = if internal { quote!() } else { quote!(authzen::) };
+    let registry_mod = if internal { quote!(crate::) } else { quote!(authzen::) };
     let data_sources_source_mod = if internal {
         quote!(authzen_data_sources::)
     } else {
@@ -57,6 +58,14 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
     let try_trait_name = format_ident!("Try{name}");
     let try_fn_name = format_ident!("try_{snake_name}");
     let try_one_fn_name = format_ident!("try_{snake_name}_one");
+    let try_verified_fn_name = format_ident!("try_{snake_name}_verified");
+
+    let resolved_subject = quote! {
+        <<Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::SubjectResolver as #source_mod SubjectResolver<
+            <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+            DS,
+        >>::Subject
+    };
 
     let can_fn_doc = format!("Query whether the subject is authorized to {ty} the specified object(s).");
 
@@ -75,6 +84,7 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
     );
     let try_fn_doc =
         format!("Query whether the subject is authorized to {ty} the specified objects. If so, perform the action.");
+    let try_verified_fn_doc = format!("Perform the action within a new transaction, then query whether the subject is authorized to {ty} the resulting objects. The transaction is rolled back if the action is not authorized.");
     let try_one_fn_doc =
         format!("Query whether the subject is authorized to {ty} the specified object. If so, perform the action. Expects the return type of the storage action to implement [`IntoIterator`].");
 
@@ -88,6 +98,12 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
             const TYPE: &'static str = #ty;
         }
 
+        impl<O: ?Sized + #source_mod ObjectType> #source_mod ObjectAction for #name<O> {
+            type Object = O;
+        }
+
+        #registry_mod __authzen_register_action!(#ty);
+
         #[doc = #try_trait_doc]
         pub trait #try_trait_name<'subject, 'context, 'input, Ctx>: Send + Sync
         where
@@ -100,27 +116,31 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
             ) -> std::pin::Pin<Box<dyn std::future::Future<
                 Output = Result<
                     <AE as #source_mod AuthzEngine<
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+                        #resolved_subject,
                         #name<Self>,
                         Self,
                         I,
                         <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Context<'context>,
                         DS::TransactionId,
                     >>::Ok,
-                    <AE as #source_mod AuthzEngine<
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
-                        #name<Self>,
-                        Self,
-                        I,
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Context<'context>,
-                        DS::TransactionId,
-                    >>::Error,
+                    #source_mod ActionError<
+                        <AE as #source_mod AuthzEngine<
+                            #resolved_subject,
+                            #name<Self>,
+                            Self,
+                            I,
+                            <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Context<'context>,
+                            DS::TransactionId,
+                        >>::Error,
+                        std::convert::Infallible,
+                        std::convert::Infallible,
+                    >,
                 >,
             > + Send + 'async_trait>>
             where
                 Self: #source_mod AsStorage<<DS as #data_sources_source_mod DataSource>::Backend>,
                 AE: #source_mod AuthzEngine<
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+                        #resolved_subject,
                         #name<Self>,
                         Self,
                         I,
@@ -130,6 +150,8 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                 DS: #data_sources_source_mod DataSource + Send + Sync,
                 TC: Send + Sync + #source_mod TransactionCache,
                 Ctx: #source_mod AuthorizationContext<AE, DS, TC>,
+                <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::SubjectResolver:
+                    #source_mod SubjectResolver<<Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>, DS>,
                 I: Send + Sync,
 
                 'subject: 'async_trait,
@@ -143,14 +165,16 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                 I: 'async_trait,
             {
                 use #source_mod AuthorizationContext;
-                Box::pin(<AE as AuthzEngine<
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
-                        #name<Self>,
-                        Self,
-                        I,
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Context<'context>,
-                        DS::TransactionId,
-                    >>::can_act(ctx.authz_engine(), ctx.subject(), input, ctx.context(), ctx.data_source().transaction_id()))
+                Box::pin(#source_mod can_act::<_, _, _, _, #name<Self>, Self, _, _>(
+                    ctx.authz_engine(),
+                    ctx.data_source(),
+                    ctx.decision_log(),
+                    ctx.subject_resolver(),
+                    ctx.subject(),
+                    input,
+                    ctx.context(),
+                    ctx.tenant(),
+                ))
             }
 
             #[doc = #try_fn_doc]
@@ -162,7 +186,7 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                     <#name<Self> as #source_mod StorageAction<DS, I>>::Ok,
                     #source_mod ActionError<
                         <AE as #source_mod AuthzEngine<
-                            <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+                            #resolved_subject,
                             #name<Self>,
                             Self,
                             I,
@@ -177,7 +201,7 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
             where
                 Self: #source_mod AsStorage<<DS as #data_sources_source_mod DataSource>::Backend>,
                 AE: #source_mod AuthzEngine<
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+                        #resolved_subject,
                         #name<Self>,
                         Self,
                         I,
@@ -189,6 +213,8 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                     + #source_mod TransactionCache
                     + #source_mod TransactionCacheAction<#name<Self>, DS, I>,
                 Ctx: #source_mod AuthorizationContext<AE, DS, TC>,
+                <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::SubjectResolver:
+                    #source_mod SubjectResolver<<Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>, DS>,
                 #name<Self>: #source_mod StorageAction<DS, I>,
                 I: Send + Sync,
 
@@ -208,11 +234,84 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                 let event = #source_mod Event {
                     context: ctx.context(),
                     subject: ctx.subject(),
+                    tenant: ctx.tenant(),
+                    action: std::marker::PhantomData::<#name<Self>>::default(),
+                    object: std::marker::PhantomData::<Self>::default(),
+                    input,
+                };
+                Box::pin(event.try_act(ctx.authz_engine(), ctx.data_source(), ctx.transaction_cache(), ctx.decision_log(), ctx.subject_resolver()))
+            }
+
+            #[doc = #try_verified_fn_doc]
+            fn #try_verified_fn_name<'life0, 'async_trait, AE, DS, TC, I>(
+                ctx: &'life0 Ctx,
+                input: I,
+            ) -> std::pin::Pin<Box<dyn std::future::Future<
+                Output = Result<
+                    <#name<Self> as #source_mod StorageAction<DS, I>>::Ok,
+                    #source_mod ActionError<
+                        <AE as #source_mod AuthzEngine<
+                            #resolved_subject,
+                            #name<Self>,
+                            Self,
+                            <#name<Self> as #source_mod StorageAction<DS, I>>::Ok,
+                            <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Context<'context>,
+                            DS::TransactionId,
+                        >>::Error,
+                        <#name<Self> as #source_mod StorageAction<DS, I>>::Error,
+                        <TC as #source_mod TransactionCache>::Error,
+                    >,
+                >,
+            > + Send + 'async_trait>>
+            where
+                Self: #source_mod AsStorage<<DS as #data_sources_source_mod DataSource>::Backend>,
+                AE: #source_mod AuthzEngine<
+                        #resolved_subject,
+                        #name<Self>,
+                        Self,
+                        <#name<Self> as #source_mod StorageAction<DS, I>>::Ok,
+                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Context<'context>,
+                        DS::TransactionId,
+                    > + Sync,
+                DS: #data_sources_source_mod TransactionalDataSource + Send + Sync,
+                <DS as #data_sources_source_mod DataSource>::Error: std::error::Error + Send + Sync + 'static,
+                TC: Send + Sync
+                    + #source_mod TransactionCache
+                    + #source_mod TransactionCacheAction<#name<Self>, DS, I>,
+                Ctx: #source_mod AuthorizationContext<AE, DS, TC>,
+                <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::SubjectResolver:
+                    #source_mod SubjectResolver<<Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>, DS>,
+                #name<Self>: #source_mod StorageAction<DS, I>
+                    + for<'r> #source_mod StorageAction<
+                        <DS as #data_sources_source_mod TransactionalDataSource>::TxConnection<'r>,
+                        I,
+                        Ok = <#name<Self> as #source_mod StorageAction<DS, I>>::Ok,
+                        Error = <#name<Self> as #source_mod StorageAction<DS, I>>::Error,
+                    >,
+                I: Send + Sync,
+
+                'subject: 'async_trait,
+                'context: 'async_trait,
+                'input: 'async_trait,
+                'life0: 'async_trait + 'subject + 'context,
+                Self: 'async_trait,
+                AE: 'async_trait,
+                DS: 'async_trait,
+                TC: 'async_trait,
+                I: 'async_trait,
+            {
+                use #source_mod AuthorizationContext;
+                use #source_mod TransactionCache;
+                use #source_mod TryActVerified;
+                let event = #source_mod Event {
+                    context: ctx.context(),
+                    subject: ctx.subject(),
+                    tenant: ctx.tenant(),
                     action: std::marker::PhantomData::<#name<Self>>::default(),
                     object: std::marker::PhantomData::<Self>::default(),
                     input,
                 };
-                Box::pin(event.try_act(ctx.authz_engine(), ctx.data_source(), ctx.transaction_cache()))
+                Box::pin(event.try_act_verified(ctx.authz_engine(), ctx.data_source(), ctx.transaction_cache(), ctx.decision_log(), ctx.subject_resolver()))
             }
 
             #[doc = #try_one_fn_doc]
@@ -224,7 +323,7 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                     <<#name<Self> as #source_mod StorageAction<DS, [I; 1]>>::Ok as IntoIterator>::Item,
                     #source_mod ActionError<
                         <AE as #source_mod AuthzEngine<
-                            <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+                            #resolved_subject,
                             #name<Self>,
                             Self,
                             [I; 1],
@@ -239,7 +338,7 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
             where
                 Self: #source_mod AsStorage<<DS as #data_sources_source_mod DataSource>::Backend>,
                 AE: #source_mod AuthzEngine<
-                        <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>,
+                        #resolved_subject,
                         #name<Self>,
                         Self,
                         [I; 1],
@@ -251,6 +350,8 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                     + #source_mod TransactionCache
                     + #source_mod TransactionCacheAction<#name<Self>, DS, [I; 1]>,
                 Ctx: #source_mod AuthorizationContext<AE, DS, TC>,
+                <Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::SubjectResolver:
+                    #source_mod SubjectResolver<<Ctx as #source_mod AuthorizationContext<AE, DS, TC>>::Subject<'subject>, DS>,
                 #name<Self>: #source_mod StorageAction<DS, [I; 1]>,
                 I: Send + Sync,
 
@@ -274,12 +375,13 @@ pub fn action(item: TokenStream) -> Result<TokenStream, Error> {
                 let event = #source_mod Event {
                     context: ctx.context(),
                     subject: ctx.subject(),
+                    tenant: ctx.tenant(),
                     action: std::marker::PhantomData::<#name<Self>>::default(),
                     object: std::marker::PhantomData::<Self>::default(),
                     input: [input],
                 };
                 Box::pin(
-                    event.try_act(ctx.authz_engine(), ctx.data_source(), ctx.transaction_cache())
+                    event.try_act(ctx.authz_engine(), ctx.data_source(), ctx.transaction_cache(), ctx.decision_log(), ctx.subject_resolver())
                         .and_then(|ok| {
                             let mut iter = ok.into_iter();
                             ready(iter.next().ok_or_else(|| #source_mod ActionError::DataSource(<#name<Self> as #source_mod StorageAction<DS, [I; 1]>>::Error::not_found())))
@@ -138,7 +138,29 @@ pub fn authz_object(item: TokenStream) -> Result<TokenStream, Error> {
     );
     let (as_storage_impl_generics, _, as_storage_where_clause) = as_storage_generics.split_for_impl();
 
+    // object schemas are registered with their lifetimes erased, so only objects without other generic parameters have schemas
+    let registration = match ast.generics.params.iter().collect::<Vec<_>>()[..] {
+        [syn::GenericParam::Lifetime(_)] => {
+            let mut static_inner_ty = inner_ty.clone();
+            if let syn::Type::Path(type_path) = &mut static_inner_ty {
+                for path_segment in &mut type_path.path.segments {
+                    if let syn::PathArguments::AngleBracketed(generics) = &mut path_segment.arguments {
+                        for arg in &mut generics.args {
+                            if let syn::GenericArgument::Lifetime(lt) = arg {
+                                *lt = parse_quote!('static);
+                            }
+                        }
+                    }
+                }
+            }
+            quote!(authzen::__authzen_register_object!(#service, #ty, #static_inner_ty);)
+        }
+        _ => quote!(authzen::__authzen_register_object!(#service, #ty);),
+    };
+
     let tokens = quote! {
+        #registration
+
         impl #impl_generics From<#inner_ty> for #ident #ty_generics #where_clause {
             fn from(value: #inner_ty) -> Self {
                 Self(std::borrow::Cow::Owned(value))
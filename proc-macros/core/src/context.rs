@@ -1,5 +1,6 @@
 use authzen_proc_macro_util::{
-    add_bounds_to_generics, find_field_attribute_in_struct, find_field_attributes_in_struct, MatchedAttribute,
+    add_bounds_to_generics, add_general_bounds_to_generics, find_field_attribute_in_struct,
+    find_field_attributes_in_struct, MatchedAttribute,
 };
 use convert_case::{Case, Casing};
 use proc_macro2::TokenStream;
@@ -36,6 +37,69 @@ pub fn context(item: TokenStream) -> Result<TokenStream, Error> {
 
     let matched_authz_engine_attributes = find_field_attributes_in_struct("authz_engine", &ast)?;
 
+    let mut matched_decision_log_attributes = find_field_attributes_in_struct("decision_log", &ast)?;
+
+    if matched_decision_log_attributes.len() > 1 {
+        return Err(Error::new_spanned(
+            &matched_decision_log_attributes[1].attr,
+            "`#[decision_log]` attribute cannot be used more than once".to_string(),
+        ));
+    }
+
+    let matched_decision_log_attribute = matched_decision_log_attributes.pop();
+
+    let decision_log_fn = matched_decision_log_attribute
+        .as_ref()
+        .map(|MatchedAttribute { field_accessor, .. }| {
+            quote! {
+                fn decision_log(&self) -> &dyn authzen::DecisionLog {
+                    &self.#field_accessor
+                }
+            }
+        })
+        .unwrap_or_default();
+
+    let mut matched_tenant_attributes = find_field_attributes_in_struct("tenant", &ast)?;
+
+    if matched_tenant_attributes.len() > 1 {
+        return Err(Error::new_spanned(
+            &matched_tenant_attributes[1].attr,
+            "`#[tenant]` attribute cannot be used more than once".to_string(),
+        ));
+    }
+
+    let matched_tenant_attribute = matched_tenant_attributes.pop();
+
+    let tenant_fn = matched_tenant_attribute
+        .as_ref()
+        .map(|MatchedAttribute { field_accessor, .. }| {
+            quote! {
+                fn tenant(&self) -> Option<authzen::Tenant> {
+                    authzen::AsTenant::as_tenant(&self.#field_accessor)
+                }
+            }
+        })
+        .unwrap_or_default();
+
+    let mut matched_subject_resolver_attributes = find_field_attributes_in_struct("subject_resolver", &ast)?;
+
+    if matched_subject_resolver_attributes.len() > 1 {
+        return Err(Error::new_spanned(
+            &matched_subject_resolver_attributes[1].attr,
+            "`#[subject_resolver]` attribute cannot be used more than once".to_string(),
+        ));
+    }
+
+    let matched_subject_resolver_attribute = matched_subject_resolver_attributes.pop();
+
+    let (subject_resolver_field_type, subject_resolver_field_access): (syn::Type, TokenStream) =
+        match &matched_subject_resolver_attribute {
+            Some(MatchedAttribute {
+                field, field_accessor, ..
+            }) => (field.ty.clone(), quote!(&self.#field_accessor)),
+            None => (parse_quote!(()), quote!(&())),
+        };
+
     let matched_data_source_attributes = find_field_attributes_in_struct("data_source", &ast)?;
 
     let mut matched_transaction_cache_attributes = find_field_attributes_in_struct("transaction_cache", &ast)?;
@@ -159,6 +223,29 @@ pub fn context(item: TokenStream) -> Result<TokenStream, Error> {
         Some(&parse_quote!(#subject_field_type)),
     );
 
+    if let Some(MatchedAttribute { field, .. }) = matched_decision_log_attribute {
+        let decision_log_field_type = &field.ty;
+        add_general_bounds_to_generics(
+            &mut trait_generics,
+            [parse_quote!(#decision_log_field_type: authzen::DecisionLog)],
+        );
+    }
+
+    if let Some(MatchedAttribute { field, .. }) = matched_tenant_attribute {
+        let tenant_field_type = &field.ty;
+        add_general_bounds_to_generics(
+            &mut trait_generics,
+            [parse_quote!(#tenant_field_type: authzen::AsTenant)],
+        );
+    }
+
+    if matched_subject_resolver_attribute.is_some() {
+        add_general_bounds_to_generics(
+            &mut trait_generics,
+            [parse_quote!(#subject_resolver_field_type: Send + Sync)],
+        );
+    }
+
     let (impl_generics, _, where_clause) = trait_generics.split_for_impl();
 
     let tokens = quote! {
@@ -169,6 +256,7 @@ pub fn context(item: TokenStream) -> Result<TokenStream, Error> {
                     impl #impl_generics authzen::AuthorizationContext<#authz_engine_field_types, #data_source_field_types, #transaction_cache_field_types> for #ident #ty_generics #where_clause {
                         type Context<#gat_lifetime> = #context_field_gat where Self: #gat_lifetime;
                         type Subject<#gat_lifetime> = #subject_field_gat where Self: #gat_lifetime;
+                        type SubjectResolver = #subject_resolver_field_type;
 
                         fn context(&self) -> Self::Context<'_> {
                             #context_field_access
@@ -185,6 +273,11 @@ pub fn context(item: TokenStream) -> Result<TokenStream, Error> {
                         fn transaction_cache(&self) -> &#transaction_cache_field_types {
                             #transaction_cache_field_accessors
                         }
+                        #decision_log_fn
+                        #tenant_fn
+                        fn subject_resolver(&self) -> &Self::SubjectResolver {
+                            #subject_resolver_field_access
+                        }
                     }
                 )*
             )*
@@ -136,6 +136,16 @@ impl Error {
         Error::init(StatusCode::BAD_REQUEST, None, format!("{err}"))
     }
 
+    #[framed]
+    pub fn forbidden_details(err: impl Display) -> Self {
+        Error::init(StatusCode::FORBIDDEN, None, format!("{err}"))
+    }
+
+    #[framed]
+    pub fn service_unavailable_details(err: impl Display) -> Self {
+        Error::init(StatusCode::SERVICE_UNAVAILABLE, None, format!("{err}"))
+    }
+
     #[cfg(feature = "graphql")]
     pub fn graphql(self) -> async_graphql::Error {
         use async_graphql::ErrorExtensions;
@@ -0,0 +1,148 @@
+use authzen_session::{
+    AccountSession, AccountSessionSubject, AccountSessionToken, RawSession, Session, HTTP_ACCOUNT_SESSION_JWT_HEADER,
+};
+use std::marker::PhantomData;
+use std::sync::Arc;
+use tonic::{service::Interceptor, Request, Status};
+
+/// Metadata key from which [`AccountSessionInterceptor`] reads account session jwts,
+/// identical to the http header [`HTTP_ACCOUNT_SESSION_JWT_HEADER`].
+pub const GRPC_ACCOUNT_SESSION_JWT_METADATA_KEY: &str = HTTP_ACCOUNT_SESSION_JWT_HEADER;
+
+/// gRPC counterpart to the account session handling of [`SessionLayer`](authzen_session::SessionLayer).
+///
+/// Decodes the account session jwt found in metadata key [`GRPC_ACCOUNT_SESSION_JWT_METADATA_KEY`]
+/// and inserts the same extensions into each request as an http request would receive, i.e.
+/// `Option<AccountSessionSubject<AccountId>>` and `Option<AccountSession<AccountId, Fields>>`.
+/// Requests with a missing or invalid jwt receive `None` for both extensions rather than being rejected;
+/// use [`grpc_account_session_subject`] when constructing an `AuthorizationContext` to require a subject.
+/// ```rs
+/// let interceptor = AccountSessionInterceptor::<Uuid, (), _, _>::new(
+///     DecodingKey::from_secret(secret),
+///     Validation::default(),
+/// );
+/// Server::builder()
+///     .add_service(CartServer::with_interceptor(cart_service, interceptor))
+///     .serve(addr)
+///     .await?;
+/// ```
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""), Debug(bound = ""))]
+pub struct AccountSessionInterceptor<AccountId, Fields, Key, Validation> {
+    #[derivative(Debug = "ignore")]
+    key: Arc<Key>,
+    #[derivative(Debug = "ignore")]
+    validation: Arc<Validation>,
+    _marker: PhantomData<fn() -> (AccountId, Fields)>,
+}
+
+impl<AccountId, Fields, Key, Validation> AccountSessionInterceptor<AccountId, Fields, Key, Validation> {
+    pub fn new(key: Key, validation: Validation) -> Self {
+        Self {
+            key: Arc::new(key),
+            validation: Arc::new(validation),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<AccountId, Fields, Key, Validation> Interceptor for AccountSessionInterceptor<AccountId, Fields, Key, Validation>
+where
+    AccountId: Clone + Send + Sync + 'static,
+    Fields: Clone + Send + Sync + 'static,
+    Session<AccountSessionToken<()>>: RawSession<AccountSession<AccountId, Fields>, Key = Key, Validation = Validation>,
+{
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        let parsed_session: Option<AccountSession<AccountId, Fields>> = request
+            .metadata()
+            .get(GRPC_ACCOUNT_SESSION_JWT_METADATA_KEY)
+            .and_then(|token| token.to_str().ok())
+            .map(Session::from_account_session_jwt)
+            .and_then(|session| session.try_decode(&self.key, &self.validation).ok());
+
+        let extensions = request.extensions_mut();
+        match parsed_session {
+            Some(parsed_session) => {
+                extensions.insert(Some(AccountSessionSubject(parsed_session.account_id.clone())));
+                extensions.insert(Some(parsed_session));
+            }
+            None => {
+                extensions.insert(None::<AccountSessionSubject<AccountId>>);
+                extensions.insert(None::<AccountSession<AccountId, Fields>>);
+            }
+        }
+
+        Ok(request)
+    }
+}
+
+/// Returns the account id inserted into a request's extensions by [`AccountSessionInterceptor`].
+pub fn get_grpc_account_id<AccountId: Send + Sync + 'static, T>(req: &Request<T>) -> Option<&AccountId> {
+    match req.extensions().get::<Option<AccountSessionSubject<AccountId>>>() {
+        Some(Some(subject)) => Some(&subject.0),
+        _ => None,
+    }
+}
+
+/// Returns the subject inserted into a request's extensions by [`AccountSessionInterceptor`],
+/// or an `UNAUTHENTICATED` status if the request did not provide a valid account session jwt.
+pub fn grpc_account_session_subject<AccountId: Clone + Send + Sync + 'static, T>(
+    req: &Request<T>,
+) -> Result<AccountSessionSubject<AccountId>, Status> {
+    get_grpc_account_id(req)
+        .cloned()
+        .map(AccountSessionSubject)
+        .ok_or_else(|| Status::unauthenticated("missing account session"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use authzen_session::{AccountSessionClaims, AccountSessionState};
+    use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation};
+
+    fn request(secret: &[u8]) -> Request<()> {
+        let token = AccountSessionClaims::new_exp_in(
+            AccountSessionState::builder().account_id(1u32).fields(()).build(),
+            "test",
+            chrono::Duration::minutes(5),
+        )
+        .encode(&Header::default(), &EncodingKey::from_secret(secret))
+        .unwrap();
+        let mut request = Request::new(());
+        request
+            .metadata_mut()
+            .insert(GRPC_ACCOUNT_SESSION_JWT_METADATA_KEY, token.token.parse().unwrap());
+        request
+    }
+
+    #[test]
+    fn inserts_the_subjects_of_valid_account_sessions() {
+        let mut interceptor =
+            AccountSessionInterceptor::<u32, (), _, _>::new(DecodingKey::from_secret(b"secret"), Validation::default());
+
+        let req = interceptor.call(request(b"secret")).unwrap();
+        assert_eq!(get_grpc_account_id::<u32, _>(&req), Some(&1));
+        assert_eq!(
+            grpc_account_session_subject::<u32, _>(&req).unwrap(),
+            AccountSessionSubject(1)
+        );
+        assert!(matches!(
+            req.extensions().get::<Option<AccountSession<u32, ()>>>(),
+            Some(Some(_))
+        ));
+
+        for req in [request(b"other secret"), Request::new(())] {
+            let req = interceptor.call(req).unwrap();
+            assert_eq!(get_grpc_account_id::<u32, _>(&req), None);
+            assert_eq!(
+                grpc_account_session_subject::<u32, _>(&req).unwrap_err().code(),
+                tonic::Code::Unauthenticated
+            );
+            assert!(matches!(
+                req.extensions().get::<Option<AccountSession<u32, ()>>>(),
+                Some(None)
+            ));
+        }
+    }
+}
@@ -29,6 +29,12 @@ cfg_if! {
         pub use client::*;
     }
 }
+cfg_if! {
+    if #[cfg(feature = "grpc")] {
+        mod grpc;
+        pub use grpc::*;
+    }
+}
 cfg_if! {
     if #[cfg(feature = "server")] {
         mod server;
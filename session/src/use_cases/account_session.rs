@@ -79,6 +79,23 @@ where
     }
 }
 
+impl Session<AccountSessionToken<()>> {
+    /// Wraps an encoded account session jwt which was provided directly by the client
+    /// (e.g. in header [`HTTP_ACCOUNT_SESSION_JWT_HEADER`]) rather than retrieved from a session store.
+    pub fn from_account_session_jwt(token: impl Into<String>) -> Self {
+        Self {
+            session_id: Uuid::new_v4(),
+            created_at: Utc::now().naive_utc(),
+            value: AccountSessionToken {
+                token: token.into(),
+                claims: (),
+            },
+            max_age: None,
+            expires: None,
+        }
+    }
+}
+
 #[async_trait]
 impl<ReqBody: Sync, S> SessionValue<ReqBody, S> for AccountSessionToken<()>
 where
@@ -86,16 +103,9 @@ where
 {
     fn get_unparsed_request_session(store: &S, req: &Request<ReqBody>) -> Result<RequestSession<S::Value>, Error> {
         if let Some(service_account_jwt) = req.headers().get(HTTP_ACCOUNT_SESSION_JWT_HEADER) {
-            return Ok(RequestSession::Session(Session {
-                session_id: Uuid::new_v4(),
-                created_at: Utc::now().naive_utc(),
-                value: AccountSessionToken {
-                    token: service_account_jwt.to_str()?.into(),
-                    claims: (),
-                },
-                max_age: None,
-                expires: None,
-            }));
+            return Ok(RequestSession::Session(Session::from_account_session_jwt(
+                service_account_jwt.to_str()?,
+            )));
         }
 
         match get_session_id_from_request(store, req) {